@@ -1,7 +1,4 @@
-use mem::{
-    injector::{Config, Injector},
-    windows::utils::get_process_id,
-};
+use mem::injector::{Config, Injector};
 
 fn main() {
     let dll_path = std::env::var("dll_path").expect("You must provide a dll path");
@@ -14,7 +11,6 @@ fn main() {
     let config = Config::default();
     let injector = Injector::new(config);
 
-    let pid = get_process_id(&process_name).unwrap();
-    injector.inject(pid, &dll_path).unwrap();
-    println!("Successfully Injected!")
+    let base = injector.inject_by_name(&process_name, &dll_path).unwrap();
+    println!("Successfully Injected at {:#X}!", base)
 }
@@ -0,0 +1,77 @@
+//! Compares `read_pointer_chains` against following every chain on its own
+//! with `read_pointer_chain`, on an entity list inside of this process. Run it
+//! with `cargo run --release --example pointer_chain_benchmark`.
+use std::{mem::offset_of, time::Instant};
+
+use mem::process::{Access, Process};
+
+const ENTITIES: usize = 500;
+const ROUNDS: u32 = 100;
+
+#[repr(C)]
+struct Position {
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+#[repr(C)]
+struct Entity {
+    health: u32,
+    position: Box<Position>,
+}
+
+fn main() {
+    let entities: Vec<Box<Entity>> = (0..ENTITIES)
+        .map(|index| {
+            Box::new(Entity {
+                health: 100,
+                position: Box::new(Position {
+                    x: index as f32,
+                    y: 0.0,
+                    z: 0.0,
+                }),
+            })
+        })
+        .collect();
+    let list: Vec<*const Entity> = entities.iter().map(|entity| &**entity as *const Entity).collect();
+
+    // Every base points at an entity, whose position is followed to its x
+    let bases: Vec<usize> = list.iter().map(|entity| entity as *const _ as usize).collect();
+    let offsets = [offset_of!(Entity, position), offset_of!(Position, x)];
+
+    let process = Process::open_by_pid(std::process::id(), Access::read_only()).unwrap();
+
+    let started = Instant::now();
+    let mut naive = Vec::new();
+    for _ in 0..ROUNDS {
+        naive = bases
+            .iter()
+            .map(|&base| process.read_pointer_chain(base, &offsets).ok())
+            .collect();
+    }
+    let naive_time = started.elapsed();
+
+    let started = Instant::now();
+    let mut batched = Vec::new();
+    for _ in 0..ROUNDS {
+        batched = process
+            .read_pointer_chains(&bases, &offsets)
+            .into_iter()
+            .map(Result::ok)
+            .collect();
+    }
+    let batched_time = started.elapsed();
+
+    assert_eq!(naive, batched);
+    for (address, entity) in batched.iter().zip(&entities) {
+        assert_eq!(*address, Some(&entity.position.x as *const f32 as usize));
+    }
+    println!(
+        "{} chains of {} levels, naive: {:?}, batched: {:?}",
+        ENTITIES,
+        offsets.len() + 1,
+        naive_time / ROUNDS,
+        batched_time / ROUNDS
+    );
+}
@@ -0,0 +1,56 @@
+//! Compares the speed of the scanner against a naive byte by byte scan on a
+//! 16MB buffer, the test in `scanner.rs` makes sure they give the same
+//! results. Run it with `cargo run --release --example scanner_benchmark`.
+use std::time::Instant;
+
+use mem::scanner::{parse_pattern, scan};
+
+/// A tiny xorshift generator so the benchmark doesn't need any dependencies.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+fn naive_scan_all(haystack: &[u8], pattern: &[Option<u8>]) -> Vec<usize> {
+    haystack
+        .windows(pattern.len())
+        .enumerate()
+        .filter(|(_, window)| {
+            window
+                .iter()
+                .zip(pattern)
+                .all(|(byte, expected)| expected.is_none() || *expected == Some(*byte))
+        })
+        .map(|(offset, _)| offset)
+        .collect()
+}
+
+fn main() {
+    let mut random = Random(0x2545_F491_4F6C_DD1D);
+
+    let pattern = "48 8B 05 ?? ?? ?? ?? 48 89 5C 24 08 E8";
+    let parsed = parse_pattern(pattern).unwrap();
+    let mut haystack: Vec<u8> = (0..16 << 20).map(|_| random.next() as u8).collect();
+    let planted = haystack.len() - 100;
+    for (offset, byte) in parsed.iter().enumerate() {
+        haystack[planted + offset] = byte.unwrap_or(0);
+    }
+
+    let started = Instant::now();
+    let naive = naive_scan_all(&haystack, &parsed).first().copied();
+    let naive_time = started.elapsed();
+
+    let started = Instant::now();
+    let accelerated = scan(&haystack, pattern).unwrap();
+    let accelerated_time = started.elapsed();
+
+    assert_eq!(naive, accelerated);
+    assert_eq!(accelerated, Some(planted));
+    println!("Naive: {:?}, accelerated: {:?}", naive_time, accelerated_time);
+}
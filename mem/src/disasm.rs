@@ -0,0 +1,633 @@
+//! `disasm` is a small x86/x64 length decoder. It doesn't care what an
+//! instruction does, only how long it is and whether it holds a displacement
+//! that is relative to the address of the instruction.
+//!
+//! That is everything needed to steal bytes for a hook without cutting an
+//! instruction in half.
+//!
+//! ```rust
+//! use mem::{
+//!     disasm::{covering_length, instruction_length},
+//!     process::Architecture,
+//! };
+//!
+//! // push rbp ; mov rbp, rsp ; sub rsp, 0x20
+//! let prologue = [0x55, 0x48, 0x89, 0xE5, 0x48, 0x83, 0xEC, 0x20];
+//! assert_eq!(instruction_length(&prologue, Architecture::X64), Some(1));
+//! assert_eq!(covering_length(&prologue, 5, Architecture::X64), Some(8));
+//! ```
+use crate::process::Architecture;
+
+/// A decoded instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Instruction {
+    /// The length of the instruction in bytes.
+    pub length: usize,
+    /// Set if the instruction holds a displacement relative to the end of the
+    /// instruction, like a `jmp rel32` or a rip relative memory operand.
+    pub relative: Option<Relative>,
+}
+
+/// Where a relative displacement sits inside of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Relative {
+    /// The offset of the displacement from the start of the instruction.
+    pub offset: usize,
+    /// The size of the displacement in bytes, either 1 or 4.
+    pub size: usize,
+    /// Whether this is a branch rather than a memory operand.
+    pub branch: bool,
+}
+
+/// No instruction can be longer than 15 bytes.
+pub(crate) const MAX_LENGTH: usize = 15;
+
+/// The length of the instruction at the start of `bytes` when it runs on
+/// `architecture`.
+///
+/// `None` is returned if `bytes` is too short or doesn't start with an
+/// instruction we know, which covers the general purpose, x87, SSE and AVX
+/// instructions functions are made of.
+#[must_use]
+pub fn instruction_length(bytes: &[u8], architecture: Architecture) -> Option<usize> {
+    decode(bytes, architecture == Architecture::X64).map(|instruction| instruction.length)
+}
+
+/// The length of the whole instructions at the start of `bytes` that cover at
+/// least `size` bytes, which is how many bytes have to be stolen to put a
+/// `size` byte jump over the start of a function.
+///
+/// `None` is returned if any of the instructions couldn't be decoded.
+#[must_use]
+pub fn covering_length(bytes: &[u8], size: usize, architecture: Architecture) -> Option<usize> {
+    let mut covered = 0;
+    while covered < size {
+        covered += instruction_length(bytes.get(covered..)?, architecture)?;
+    }
+
+    Some(covered)
+}
+
+/// Decodes the instruction at the start of `bytes`, `is_64` selects between
+/// 64-bit and 32-bit mode.
+///
+/// `None` is returned if `bytes` is too short or doesn't start with an
+/// instruction we know.
+pub(crate) fn decode(bytes: &[u8], is_64: bool) -> Option<Instruction> {
+    let mut decoder = Decoder {
+        bytes,
+        position: 0,
+        is_64,
+        operand_size_override: false,
+        address_size_override: false,
+        rex_w: false,
+        relative: None,
+    };
+
+    decoder.decode()?;
+
+    if decoder.position > MAX_LENGTH {
+        return None;
+    }
+
+    Some(Instruction {
+        length: decoder.position,
+        relative: decoder.relative,
+    })
+}
+
+/// The operands that follow an opcode.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Operands {
+    None,
+    ModRm,
+    /// A `ModRM` followed by an immediate of the given size.
+    ModRmImmediate(Immediate),
+    Immediate(Immediate),
+    /// A branch displacement of the given size.
+    Relative(usize),
+    Invalid,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Immediate {
+    Byte,
+    Word,
+    /// 4 bytes, or 2 with an operand size override.
+    Full,
+    /// `enter` takes a word and a byte.
+    WordByte,
+    /// `mov r64, imm64` takes 8 bytes with `REX.W`.
+    FullOrQuad,
+    /// Far pointers are a full immediate followed by a word.
+    FarPointer,
+    /// The absolute address of `mov al, moffs` and friends.
+    Address,
+}
+
+#[allow(clippy::struct_excessive_bools)]
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    is_64: bool,
+    operand_size_override: bool,
+    address_size_override: bool,
+    rex_w: bool,
+    relative: Option<Relative>,
+}
+
+impl Decoder<'_> {
+    fn decode(&mut self) -> Option<()> {
+        let opcode = loop {
+            let byte = self.next()?;
+            match byte {
+                0x66 => self.operand_size_override = true,
+                0x67 => self.address_size_override = true,
+                0xF0 | 0xF2 | 0xF3 | 0x26 | 0x2E | 0x36 | 0x3E | 0x64 | 0x65 => {},
+                _ => break byte,
+            }
+        };
+
+        let opcode = if self.is_64 && (0x40..=0x4F).contains(&opcode) {
+            // REX has to come right before the opcode
+            self.rex_w = opcode & 0x08 != 0;
+            self.next()?
+        } else {
+            opcode
+        };
+
+        let operands = match opcode {
+            0x0F => {
+                let opcode = self.next()?;
+                match opcode {
+                    0x38 => {
+                        self.next()?;
+                        Operands::ModRm
+                    },
+                    0x3A => {
+                        self.next()?;
+                        Operands::ModRmImmediate(Immediate::Byte)
+                    },
+                    _ => two_byte(opcode),
+                }
+            },
+            0xC4 | 0xC5 if self.is_vex() => return self.vex(opcode),
+            0x62 if self.is_vex() => return self.evex(),
+            _ => self.one_byte(opcode),
+        };
+
+        self.operands(operands)
+    }
+
+    /// Outside of 64-bit mode `c4`, `c5` and `62` are only a VEX or EVEX
+    /// prefix if the next byte would be a register `ModRM`.
+    fn is_vex(&self) -> bool { self.is_64 || self.peek().is_some_and(|byte| byte >> 6 == 3) }
+
+    fn vex(&mut self, prefix: u8) -> Option<()> {
+        let map = if prefix == 0xC4 {
+            let map = self.next()? & 0x1F;
+            self.rex_w = self.next()? & 0x80 != 0;
+            map
+        } else {
+            self.next()?;
+            1
+        };
+
+        self.mapped(map)
+    }
+
+    fn evex(&mut self) -> Option<()> {
+        let map = self.next()? & 0x07;
+        self.next()?;
+        self.next()?;
+
+        self.mapped(map)
+    }
+
+    /// Decodes the opcode of a VEX or EVEX instruction in `map`.
+    fn mapped(&mut self, map: u8) -> Option<()> {
+        let opcode = self.next()?;
+        let operands = match map {
+            1 => match two_byte(opcode) {
+                // vzeroupper and vzeroall
+                Operands::None => Operands::None,
+                Operands::ModRmImmediate(immediate) => Operands::ModRmImmediate(immediate),
+                _ => Operands::ModRm,
+            },
+            2 => Operands::ModRm,
+            3 => Operands::ModRmImmediate(Immediate::Byte),
+            _ => return None,
+        };
+
+        self.operands(operands)
+    }
+
+    // The tables read a lot easier in opcode order than with the arms merged
+    #[allow(clippy::match_same_arms)]
+    fn one_byte(&self, opcode: u8) -> Operands {
+        match opcode {
+            // The arithmetic instructions, `add` up to `cmp`
+            0x00..=0x3F => match opcode & 0x07 {
+                0..=3 => Operands::ModRm,
+                4 => Operands::Immediate(Immediate::Byte),
+                5 => Operands::Immediate(Immediate::Full),
+                // Segment pushes and pops, `daa` and friends
+                _ if self.is_64 => Operands::Invalid,
+                _ => Operands::None,
+            },
+            0x40..=0x5F => Operands::None,
+            0x60 | 0x61 if self.is_64 => Operands::Invalid,
+            0x60 | 0x61 => Operands::None,
+            0x62 => Operands::ModRm,
+            0x63 => Operands::ModRm,
+            0x68 => Operands::Immediate(Immediate::Full),
+            0x69 => Operands::ModRmImmediate(Immediate::Full),
+            0x6A => Operands::Immediate(Immediate::Byte),
+            0x6B => Operands::ModRmImmediate(Immediate::Byte),
+            0x6C..=0x6F => Operands::None,
+            0x70..=0x7F => Operands::Relative(1),
+            0x80 | 0x82 | 0x83 => Operands::ModRmImmediate(Immediate::Byte),
+            0x81 => Operands::ModRmImmediate(Immediate::Full),
+            0x84..=0x8F => Operands::ModRm,
+            0x9A if self.is_64 => Operands::Invalid,
+            0x9A => Operands::Immediate(Immediate::FarPointer),
+            0x90..=0x9F => Operands::None,
+            0xA0..=0xA3 => Operands::Immediate(Immediate::Address),
+            0xA8 => Operands::Immediate(Immediate::Byte),
+            0xA9 => Operands::Immediate(Immediate::Full),
+            0xA4..=0xAF => Operands::None,
+            0xB0..=0xB7 => Operands::Immediate(Immediate::Byte),
+            0xB8..=0xBF => Operands::Immediate(Immediate::FullOrQuad),
+            0xC0 | 0xC1 | 0xC6 => Operands::ModRmImmediate(Immediate::Byte),
+            0xC7 => Operands::ModRmImmediate(Immediate::Full),
+            0xC2 | 0xCA => Operands::Immediate(Immediate::Word),
+            0xC4 | 0xC5 => Operands::ModRm,
+            0xC8 => Operands::Immediate(Immediate::WordByte),
+            0xCD => Operands::Immediate(Immediate::Byte),
+            0xC3 | 0xC9 | 0xCB | 0xCC | 0xCE | 0xCF => Operands::None,
+            0xD0..=0xD3 => Operands::ModRm,
+            0xD4 | 0xD5 if self.is_64 => Operands::Invalid,
+            0xD4 | 0xD5 => Operands::Immediate(Immediate::Byte),
+            0xD6 | 0xD7 => Operands::None,
+            0xD8..=0xDF => Operands::ModRm,
+            0xE0..=0xE3 => Operands::Relative(1),
+            0xE4..=0xE7 => Operands::Immediate(Immediate::Byte),
+            0xE8 | 0xE9 => Operands::Relative(4),
+            0xEA if self.is_64 => Operands::Invalid,
+            0xEA => Operands::Immediate(Immediate::FarPointer),
+            0xEB => Operands::Relative(1),
+            0xEC..=0xEF | 0xF1 | 0xF4 | 0xF5 | 0xF8..=0xFD => Operands::None,
+            // `test` takes an immediate, the other instructions of the group don't
+            0xF6 | 0xF7 => match self.peek().map(|modrm| (modrm >> 3) & 0x07) {
+                Some(0 | 1) if opcode == 0xF6 => Operands::ModRmImmediate(Immediate::Byte),
+                Some(0 | 1) => Operands::ModRmImmediate(Immediate::Full),
+                _ => Operands::ModRm,
+            },
+            0xFE | 0xFF => Operands::ModRm,
+            _ => Operands::Invalid,
+        }
+    }
+
+    fn operands(&mut self, operands: Operands) -> Option<()> {
+        match operands {
+            Operands::None => {},
+            Operands::ModRm => self.modrm()?,
+            Operands::ModRmImmediate(immediate) => {
+                self.modrm()?;
+                self.immediate(immediate)?;
+            },
+            Operands::Immediate(immediate) => self.immediate(immediate)?,
+            Operands::Relative(size) => {
+                self.relative = Some(Relative {
+                    offset: self.position,
+                    size,
+                    branch: true,
+                });
+                self.skip(size)?;
+            },
+            Operands::Invalid => return None,
+        }
+
+        Some(())
+    }
+
+    fn modrm(&mut self) -> Option<()> {
+        let modrm = self.next()?;
+        let mode = modrm >> 6;
+        let rm = modrm & 0x07;
+
+        if mode == 3 {
+            return Some(());
+        }
+
+        // 16-bit addressing only exists outside of 64-bit mode
+        if !self.is_64 && self.address_size_override {
+            return match (mode, rm) {
+                (0, 6) | (2, _) => self.skip(2),
+                (1, _) => self.skip(1),
+                _ => Some(()),
+            };
+        }
+
+        let mut displacement = match mode {
+            1 => 1,
+            2 => 4,
+            _ => 0,
+        };
+
+        if rm == 4 {
+            let sib = self.next()?;
+            if mode == 0 && sib & 0x07 == 5 {
+                displacement = 4;
+            }
+        } else if mode == 0 && rm == 5 {
+            displacement = 4;
+            if self.is_64 {
+                self.relative = Some(Relative {
+                    offset: self.position,
+                    size: 4,
+                    branch: false,
+                });
+            }
+        }
+
+        self.skip(displacement)
+    }
+
+    #[allow(clippy::match_same_arms)]
+    const fn immediate(&mut self, immediate: Immediate) -> Option<()> {
+        let full = if self.operand_size_override { 2 } else { 4 };
+
+        let size = match immediate {
+            Immediate::Byte => 1,
+            Immediate::Word => 2,
+            Immediate::Full => full,
+            Immediate::WordByte => 3,
+            Immediate::FullOrQuad if self.rex_w => 8,
+            Immediate::FullOrQuad => full,
+            Immediate::FarPointer => full + 2,
+            Immediate::Address => match (self.is_64, self.address_size_override) {
+                (true, false) => 8,
+                (true, true) | (false, false) => 4,
+                (false, true) => 2,
+            },
+        };
+
+        self.skip(size)
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.position)?;
+        self.position += 1;
+
+        if self.position > MAX_LENGTH {
+            return None;
+        }
+
+        Some(byte)
+    }
+
+    fn peek(&self) -> Option<u8> { self.bytes.get(self.position).copied() }
+
+    const fn skip(&mut self, count: usize) -> Option<()> {
+        if self.position + count > self.bytes.len() {
+            return None;
+        }
+
+        self.position += count;
+        Some(())
+    }
+}
+
+/// The operands of the opcodes that follow `0f`.
+#[allow(clippy::match_same_arms)]
+const fn two_byte(opcode: u8) -> Operands {
+    match opcode {
+        0x00..=0x03 | 0x0D | 0x10..=0x1F | 0x20..=0x23 | 0x28..=0x2F => Operands::ModRm,
+        0x05..=0x09 | 0x0B | 0x0E | 0x30..=0x37 | 0x77 => Operands::None,
+        // 3DNow! puts its opcode in the immediate
+        0x0F => Operands::ModRmImmediate(Immediate::Byte),
+        0x40..=0x6F | 0x74..=0x76 | 0x78..=0x7F => Operands::ModRm,
+        0x70..=0x73 => Operands::ModRmImmediate(Immediate::Byte),
+        0x80..=0x8F => Operands::Relative(4),
+        0x90..=0x9F => Operands::ModRm,
+        0xA0..=0xA2 | 0xA8..=0xAA => Operands::None,
+        0xA4 | 0xAC | 0xBA | 0xC2 | 0xC4..=0xC6 => Operands::ModRmImmediate(Immediate::Byte),
+        0xA3 | 0xA5 | 0xAB | 0xAD..=0xAF | 0xB0..=0xB9 | 0xBB..=0xBF | 0xC0 | 0xC1 | 0xC3 | 0xC7 => Operands::ModRm,
+        0xC8..=0xCF => Operands::None,
+        0xD0..=0xFF => Operands::ModRm,
+        _ => Operands::Invalid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length(bytes: &[u8], architecture: Architecture) -> Option<usize> { instruction_length(bytes, architecture) }
+
+    fn relative(bytes: &[u8], is_64: bool) -> Option<Relative> { decode(bytes, is_64).unwrap().relative }
+
+    const fn branch(offset: usize, size: usize) -> Relative {
+        Relative {
+            offset,
+            size,
+            branch: true,
+        }
+    }
+
+    const fn memory(offset: usize) -> Relative {
+        Relative {
+            offset,
+            size: 4,
+            branch: false,
+        }
+    }
+
+    #[test]
+    fn legacy_prefixes_and_rex() {
+        // xchg ax, ax
+        assert_eq!(length(&[0x66, 0x90], Architecture::X64), Some(2));
+        // mov ax, 0x1234, the operand size override shrinks the immediate
+        assert_eq!(length(&[0x66, 0xB8, 0x34, 0x12], Architecture::X64), Some(4));
+        // rep movsq
+        assert_eq!(length(&[0xF3, 0x48, 0xA5], Architecture::X64), Some(3));
+        // lock cmpxchg [rdx], ecx
+        assert_eq!(length(&[0xF0, 0x0F, 0xB1, 0x0A], Architecture::X64), Some(4));
+        // mov rax, gs:[0x30]
+        let gs = [0x65, 0x48, 0x8B, 0x04, 0x25, 0x30, 0x00, 0x00, 0x00];
+        assert_eq!(length(&gs, Architecture::X64), Some(9));
+        // 48 is dec eax outside of 64-bit mode rather than REX.W
+        assert_eq!(length(&[0x48, 0x8B, 0xC0], Architecture::X86), Some(1));
+        assert_eq!(length(&[0x48, 0x8B, 0xC0], Architecture::X64), Some(3));
+    }
+
+    #[test]
+    fn instructions_are_at_most_15_bytes() {
+        let mut nop = vec![0x66; 14];
+        nop.push(0x90);
+        assert_eq!(length(&nop, Architecture::X64), Some(15));
+
+        nop.insert(0, 0x66);
+        assert_eq!(length(&nop, Architecture::X64), None);
+    }
+
+    #[test]
+    fn truncated_instructions() {
+        assert_eq!(length(&[], Architecture::X64), None);
+        assert_eq!(length(&[0x48, 0xB8, 0x00, 0x00], Architecture::X64), None);
+        assert_eq!(length(&[0xE8, 0x00, 0x00, 0x00], Architecture::X86), None);
+    }
+
+    #[test]
+    fn mov_immediates() {
+        let mut mov = vec![0x48, 0xB8];
+        mov.extend_from_slice(&0x1122_3344_5566_7788u64.to_le_bytes());
+        assert_eq!(length(&mov, Architecture::X64), Some(10));
+        // mov r11, imm64
+        mov[0] = 0x49;
+        mov[1] = 0xBB;
+        assert_eq!(length(&mov, Architecture::X64), Some(10));
+        // Without REX.W it is only a 4 byte immediate
+        assert_eq!(length(&mov[1..], Architecture::X64), Some(5));
+        assert_eq!(length(&mov[1..], Architecture::X86), Some(5));
+    }
+
+    #[test]
+    fn sib_and_displacements() {
+        // mov eax, [rsp+0x110]
+        assert_eq!(
+            length(&[0x8B, 0x84, 0x24, 0x10, 0x01, 0x00, 0x00], Architecture::X64),
+            Some(7)
+        );
+        // mov eax, [rcx*4+0x1000], a SIB without a base always has a disp32
+        assert_eq!(
+            length(&[0x8B, 0x04, 0x8D, 0x00, 0x10, 0x00, 0x00], Architecture::X64),
+            Some(7)
+        );
+        // mov rax, [rsp+8]
+        assert_eq!(length(&[0x48, 0x8B, 0x44, 0x24, 0x08], Architecture::X64), Some(5));
+        // mov eax, [rax]
+        assert_eq!(length(&[0x8B, 0x00], Architecture::X86), Some(2));
+    }
+
+    #[test]
+    fn rip_relative_operands() {
+        // mov rax, [rip+0x12345678]
+        let mov = [0x48, 0x8B, 0x05, 0x78, 0x56, 0x34, 0x12];
+        assert_eq!(length(&mov, Architecture::X64), Some(7));
+        assert_eq!(relative(&mov, true), Some(memory(3)));
+
+        // mov dword ptr [rip+0x10], 1, the immediate comes after the
+        // displacement
+        let store = [0xC7, 0x05, 0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+        assert_eq!(length(&store, Architecture::X64), Some(10));
+        assert_eq!(relative(&store, true), Some(memory(2)));
+
+        // call [rip+0x10] reads the target from memory, it isn't a branch
+        let call = [0xFF, 0x15, 0x10, 0x00, 0x00, 0x00];
+        assert_eq!(relative(&call, true), Some(memory(2)));
+
+        // The same encoding is an absolute address outside of 64-bit mode
+        assert_eq!(length(&mov[1..], Architecture::X86), Some(6));
+        assert_eq!(relative(&mov[1..], false), None);
+
+        // mov eax, [eip+0x10] with an address size override
+        let mov = [0x67, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00];
+        assert_eq!(relative(&mov, true), Some(memory(3)));
+    }
+
+    #[test]
+    fn branches() {
+        // call rel32
+        assert_eq!(relative(&[0xE8, 0x00, 0x00, 0x00, 0x00], true), Some(branch(1, 4)));
+        // je rel8
+        assert_eq!(relative(&[0x74, 0x06], false), Some(branch(1, 1)));
+        // je rel32
+        let je = [0x0F, 0x84, 0x00, 0x01, 0x00, 0x00];
+        assert_eq!(length(&je, Architecture::X64), Some(6));
+        assert_eq!(relative(&je, true), Some(branch(2, 4)));
+        // loop rel8
+        assert_eq!(relative(&[0xE2, 0xFE], true), Some(branch(1, 1)));
+        // ret
+        assert_eq!(relative(&[0xC3], true), None);
+    }
+
+    #[test]
+    fn test_immediates() {
+        // test cl, 1
+        assert_eq!(length(&[0xF6, 0xC1, 0x01], Architecture::X64), Some(3));
+        // test ecx, 0x100
+        assert_eq!(length(&[0xF7, 0xC1, 0x00, 0x01, 0x00, 0x00], Architecture::X64), Some(6));
+        // test cx, 0x100
+        assert_eq!(length(&[0x66, 0xF7, 0xC1, 0x00, 0x01], Architecture::X64), Some(5));
+        // test dword ptr [rip+0x10], 0x100
+        let test = [0xF7, 0x05, 0x10, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00];
+        assert_eq!(length(&test, Architecture::X64), Some(10));
+        assert_eq!(relative(&test, true), Some(memory(2)));
+        // neg eax and div cl are in the same groups but take no immediate
+        assert_eq!(length(&[0xF7, 0xD8], Architecture::X64), Some(2));
+        assert_eq!(length(&[0xF6, 0xF1], Architecture::X64), Some(2));
+    }
+
+    #[test]
+    fn three_byte_opcodes() {
+        // pshufb xmm0, xmm1
+        assert_eq!(length(&[0x66, 0x0F, 0x38, 0x00, 0xC1], Architecture::X64), Some(5));
+        // palignr xmm0, xmm1, 8
+        assert_eq!(length(&[0x66, 0x0F, 0x3A, 0x0F, 0xC1, 0x08], Architecture::X64), Some(6));
+        // pshufb xmm0, [rip+0x10]
+        let pshufb = [0x66, 0x0F, 0x38, 0x00, 0x05, 0x10, 0x00, 0x00, 0x00];
+        assert_eq!(relative(&pshufb, true), Some(memory(5)));
+        // nop dword ptr [rax+rax] and cpuid
+        assert_eq!(length(&[0x0F, 0x1F, 0x44, 0x00, 0x00], Architecture::X64), Some(5));
+        assert_eq!(length(&[0x0F, 0xA2], Architecture::X64), Some(2));
+    }
+
+    #[test]
+    fn vex_and_evex() {
+        // vzeroupper
+        assert_eq!(length(&[0xC5, 0xF8, 0x77], Architecture::X64), Some(3));
+        assert_eq!(length(&[0xC5, 0xF8, 0x77], Architecture::X86), Some(3));
+        // vmovaps ymm0, ymm1
+        assert_eq!(length(&[0xC5, 0xFC, 0x28, 0xC1], Architecture::X64), Some(4));
+        // vinsertf128 ymm0, ymm0, xmm1, 1
+        assert_eq!(length(&[0xC4, 0xE3, 0x7D, 0x18, 0xC1, 0x01], Architecture::X64), Some(6));
+        // vbroadcastss xmm0, [rip+0x10]
+        let broadcast = [0xC4, 0xE2, 0x79, 0x18, 0x05, 0x10, 0x00, 0x00, 0x00];
+        assert_eq!(length(&broadcast, Architecture::X64), Some(9));
+        assert_eq!(relative(&broadcast, true), Some(memory(5)));
+        // lds eax, [esi] outside of 64-bit mode, which isn't a VEX prefix
+        assert_eq!(length(&[0xC5, 0x06], Architecture::X86), Some(2));
+
+        // vmovaps zmm0, zmm1
+        assert_eq!(length(&[0x62, 0xF1, 0x7C, 0x48, 0x28, 0xC1], Architecture::X64), Some(6));
+        // vmovups zmm0, [rax+0x40]
+        assert_eq!(
+            length(&[0x62, 0xF1, 0x7C, 0x48, 0x10, 0x40, 0x01], Architecture::X64),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn address_size_override() {
+        // mov eax, [bx]
+        assert_eq!(length(&[0x67, 0x8B, 0x07], Architecture::X86), Some(3));
+        // mov eax, [0x1234]
+        assert_eq!(length(&[0x67, 0x8B, 0x06, 0x34, 0x12], Architecture::X86), Some(5));
+        // mov eax, [bx+0x10]
+        assert_eq!(length(&[0x67, 0x8B, 0x47, 0x10], Architecture::X86), Some(4));
+        // mov eax, [bx+0x1234]
+        assert_eq!(length(&[0x67, 0x8B, 0x87, 0x34, 0x12], Architecture::X86), Some(5));
+        // 67 selects 32-bit addressing in 64-bit mode, mov eax, [edi]
+        assert_eq!(length(&[0x67, 0x8B, 0x07], Architecture::X64), Some(3));
+
+        // mov eax, moffs takes an address of the current address size
+        let mut moffs = vec![0x67, 0xA1];
+        moffs.extend_from_slice(&[0; 8]);
+        assert_eq!(length(&moffs[1..], Architecture::X64), Some(9));
+        assert_eq!(length(&moffs, Architecture::X64), Some(6));
+        assert_eq!(length(&moffs[1..], Architecture::X86), Some(5));
+        assert_eq!(length(&moffs, Architecture::X86), Some(4));
+    }
+}
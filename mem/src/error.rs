@@ -1,37 +1,113 @@
 use thiserror::Error;
+use windows::Win32::Foundation::GetLastError;
+
+use crate::{process::Architecture, windows::wrappers::format_message};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Every error this crate returns. It implements `std::error::Error` so it
+/// works with `?` into a `Box<dyn std::error::Error>`, `anyhow` or `eyre`.
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("Handle was invalid: {0}")]
+    #[error("Handle was invalid: {0} ({})", format_message(*.0))]
     Handle(u32),
+    #[error("A null handle was passed in")]
+    InvalidHandle,
     #[error(transparent)]
     Os(#[from] std::io::Error),
     #[error("Error converting C string to a rust &str")]
     StringConversion(#[from] std::str::Utf8Error),
     #[error("Process not found")]
     ProcessNotFound,
+    #[error("The process was already closed")]
+    ProcessClosed,
+    #[error("The process has exited")]
+    ProcessExited,
+    #[error("More than one process has that name: {0:?}")]
+    AmbiguousProcess(Vec<u32>),
+    #[error("Module not found: {0}")]
+    ModuleNotFound(String),
     #[error(transparent)]
     NulError(#[from] std::ffi::NulError),
-    #[error("Couldn't find function in the process: {0}")]
+    #[error("Couldn't find function in the process: {0} ({})", format_message(*.0))]
     ProcessAddress(u32),
-    #[error("Error allocating or deallocting: {0}")]
+    #[error("Error allocating or deallocting: {0} ({})", format_message(*.0))]
     Allocation(u32),
-    #[error("Error pertaining to memory access: {0}")]
+    #[error("Error pertaining to memory access: {0} ({})", format_message(*.0))]
     MemoryError(u32),
-    #[error("Error pertaining to processes: {0}")]
+    /// Returned by the checked reads when the memory isn't committed and
+    /// readable, which tells a bad pointer apart from a read that failed for
+    /// another reason.
+    #[error("The memory at {0:#X} isn't committed and readable")]
+    InvalidRegion(usize),
+    #[error("Error pertaining to processes: {0} ({})", format_message(*.0))]
     ProcessError(u32),
+    /// Opening the process was denied, it is either protected or we need to
+    /// run elevated with `SeDebugPrivilege` enabled, see
+    /// `windows::utils::enable_debug_privilege`.
+    #[error(
+        "Access to process {pid} was denied, it may be protected or require running as administrator with SeDebugPrivilege"
+    )]
+    AccessDenied { pid: u32 },
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(&'static str),
     #[error("Timeout error")]
     Timeout,
     #[error("DLL path doesn't exist")]
     DllPath,
     #[error("You must enable the feature for that render type")]
     RenderType,
-    #[error("Couldn't allocate a console for the process: {0}")]
+    #[error("Couldn't allocate a console for the process: {0} ({})", format_message(*.0))]
     ConsoleAllocation(u32),
-    #[error("Unable to deallocate the console from the process: {0}")]
+    #[error("Unable to deallocate the console from the process: {0} ({})", format_message(*.0))]
     ConsoleDeallocation(u32),
     #[error("Failed to create a DirectX dummy device")]
     DummyDevice,
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+    #[error("Invalid struct layout line: {0}")]
+    InvalidLayout(String),
+    #[error("The struct layout has no field named {0}")]
+    FieldNotFound(String),
+    #[error("Invalid PE image: {0}")]
+    InvalidPe(&'static str),
+    #[error("Manual mapping failed: {0}")]
+    ManualMap(&'static str),
+    /// Also returned when the injector itself was built for a different
+    /// architecture than the process, `dll` is our architecture then.
+    #[error("The DLL was built for {dll:?} but the process is {process:?}")]
+    ArchitectureMismatch { dll: Architecture, process: Architecture },
+    #[error("{module} has checksum {found:08X} but {expected:08X} was expected")]
+    VersionMismatch { module: String, expected: u32, found: u32 },
+    #[error("Failed to hook: {0}")]
+    Hook(&'static str),
+    /// An error returned by one of the windows-rs functions that return a
+    /// `windows::core::Result`, the original error is its `source`.
+    #[error("Windows API error: {0}")]
+    Windows(#[from] windows::core::Error),
+    /// A windows API call failed, `context` names the function that failed.
+    #[error("{context} failed with {code}: {message}")]
+    Win32 {
+        code: u32,
+        message: String,
+        context: &'static str,
+    },
+}
+
+impl Error {
+    /// Creates an `Error::Win32` from the calling thread's last error code,
+    /// call it right after the function named by `context` failed.
+    #[must_use]
+    pub fn last(context: &'static str) -> Self { Self::win32(unsafe { GetLastError() }, context) }
+
+    /// Creates an `Error::Win32` from an error `code` returned by the function
+    /// named by `context`.
+    #[must_use]
+    pub fn win32(code: u32, context: &'static str) -> Self {
+        Self::Win32 {
+            code,
+            message: format_message(code),
+            context,
+        }
+    }
 }
@@ -0,0 +1,80 @@
+use super::write_protected;
+use crate::{
+    error::Error,
+    pe::{self, ImportName, PeHeaders},
+    windows::wrappers::get_module_handle,
+};
+
+/// Enough to hold the headers of any image the linker puts out.
+const HEADERS_SIZE: usize = 0x1000;
+
+/// `IatHook` replaces an entry in the import address table of a module loaded
+/// into our own process, so every call the module makes to that import goes
+/// to the detour instead.
+///
+/// The original entry is restored once the `IatHook` is dropped.
+pub struct IatHook {
+    thunk: usize,
+    original: usize,
+    detour: usize,
+}
+
+impl IatHook {
+    /// Hooks `function`, imported from `import_dll` by `module`, so it calls
+    /// `detour` instead. Both module names are compared case-insensitively.
+    ///
+    /// # Safety
+    /// The headers of `module` are trusted to describe its mapped image, so
+    /// they must not have been erased or tampered with. `module` has to stay
+    /// loaded for as long as the `IatHook` since dropping it writes the
+    /// original entry back, and `detour` has to have the same signature and
+    /// calling convention as `function`.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if `module` isn't loaded, `Error::InvalidPe` if
+    /// its imports couldn't be parsed, `Error::Hook` if it doesn't import
+    /// `function` from `import_dll` or `Error::MemoryError` if the import
+    /// address table couldn't be written.
+    #[allow(clippy::cast_sign_loss)]
+    pub unsafe fn new(module: &str, import_dll: &str, function: &str, detour: usize) -> Result<Self, Error> {
+        let base = get_module_handle(module).map_err(|_| Error::ModuleNotFound(module.to_owned()))? as usize;
+
+        let headers = PeHeaders::parse(std::slice::from_raw_parts(base as *const u8, HEADERS_SIZE))?;
+        let image = std::slice::from_raw_parts(base as *const u8, headers.size_of_image as usize);
+
+        let import = pe::imports(image, &headers)?
+            .into_iter()
+            .find(|import| {
+                import.module.eq_ignore_ascii_case(import_dll)
+                    && matches!(&import.function, ImportName::Name(name) if name == function)
+            })
+            .ok_or(Error::Hook("the module doesn't import that function"))?;
+
+        let thunk = base + import.thunk as usize;
+        let original = std::ptr::read(thunk as *const usize);
+
+        write_protected(thunk, &detour.to_ne_bytes())?;
+
+        Ok(Self { thunk, original, detour })
+    }
+
+    /// The address the import resolved to before it was hooked, call this to
+    /// call the original function.
+    #[must_use]
+    pub const fn original(&self) -> usize { self.original }
+
+    /// The address of the detour.
+    #[must_use]
+    pub const fn detour(&self) -> usize { self.detour }
+
+    /// The address of the hooked entry in the import address table.
+    #[must_use]
+    pub const fn thunk(&self) -> usize { self.thunk }
+}
+
+impl Drop for IatHook {
+    fn drop(&mut self) {
+        // Nothing useful can be done if restoring fails inside of drop
+        let _ = write_protected(self.thunk, &self.original.to_ne_bytes());
+    }
+}
@@ -0,0 +1,243 @@
+use windows::Win32::System::Memory::{MEM_COMMIT, MEM_RELEASE, MEM_RESERVE, PAGE_EXECUTE_READWRITE};
+
+use super::{
+    relocate::{relocate, relocated_size_of, Stolen},
+    write_protected,
+};
+#[cfg(target_arch = "x86_64")]
+use crate::process::find_near;
+use crate::{
+    disasm::{decode, MAX_LENGTH},
+    error::Error,
+    process::{LocalProcess, MemoryAccess},
+    windows::wrappers::{flush_instruction_cache, get_current_process, virtual_alloc_ex, virtual_free_ex, LPCVOID, LPVOID},
+};
+
+/// `jmp [rip]` followed by the absolute address, so the detour can be anywhere.
+#[cfg(target_arch = "x86_64")]
+pub(super) const JMP_SIZE: usize = 14;
+/// `jmp rel32`, which reaches the entire address space on x86.
+#[cfg(target_arch = "x86")]
+pub(super) const JMP_SIZE: usize = 5;
+
+const IS_64: bool = cfg!(target_arch = "x86_64");
+
+/// `InlineHook` overwrites the start of a function in our own process with a
+/// jump to a detour.
+///
+/// The instructions that were overwritten are copied into a trampoline
+/// followed by a jump back into the function, so calling `trampoline` calls
+/// the original function. Relative jumps, calls and conditional branches
+/// among them are rewritten to still reach their original targets. The hook
+/// is removed and the trampoline freed once the `InlineHook` is dropped.
+pub struct InlineHook {
+    target: usize,
+    detour: usize,
+    trampoline: usize,
+    original: Vec<u8>,
+    enabled: bool,
+}
+
+impl InlineHook {
+    /// Hooks the function at `target` so it jumps to `detour`, the hook is
+    /// enabled right away.
+    ///
+    /// Only the bytes at `target` that are in readable memory are decoded, so
+    /// a function that ends right before an unreadable page can't make us
+    /// fault.
+    ///
+    /// # Safety
+    /// `target` has to point to the start of a function in our own process
+    /// that is at least as long as the instructions the jump overwrites, and
+    /// no thread may be executing those instructions while they are
+    /// overwritten. `detour` has to be a function with the same signature and
+    /// calling convention as the target.
+    ///
+    /// # Errors
+    /// `Error::Hook` if the instructions at `target` couldn't be decoded or
+    /// can't be moved into a trampoline, `Error::MemoryError` if the trampoline
+    /// couldn't be allocated or the target couldn't be written.
+    pub unsafe fn new(target: usize, detour: usize) -> Result<Self, Error> {
+        let length = readable_length(target, JMP_SIZE + MAX_LENGTH - 1);
+        let code = std::slice::from_raw_parts(target as *const u8, length);
+
+        let mut instructions = Vec::new();
+        let mut stolen = 0;
+        while stolen < JMP_SIZE {
+            let instruction =
+                decode(&code[stolen..], IS_64).ok_or(Error::Hook("couldn't decode the instructions at the target"))?;
+
+            instructions.push(Stolen {
+                offset: stolen,
+                instruction,
+            });
+            stolen += instruction.length;
+        }
+
+        let original = code[..stolen].to_vec();
+
+        let relocated_size = relocated_size_of(code, &instructions)?;
+        let trampoline_size = relocated_size + JMP_SIZE;
+        let trampoline = alloc_trampoline(target, trampoline_size)?;
+
+        let trampoline_code = relocate(code, target, &instructions, trampoline).map(|mut trampoline_code| {
+            trampoline_code.extend_from_slice(&jmp(trampoline + relocated_size, target + stolen));
+            trampoline_code
+        });
+        let trampoline_code = match trampoline_code {
+            Ok(trampoline_code) => trampoline_code,
+            Err(error) => {
+                let _ = virtual_free_ex(get_current_process(), trampoline as LPVOID, 0, MEM_RELEASE);
+                return Err(error);
+            },
+        };
+
+        std::ptr::copy_nonoverlapping(trampoline_code.as_ptr(), trampoline as *mut u8, trampoline_size);
+        flush_instruction_cache(get_current_process(), trampoline as LPCVOID, trampoline_size)?;
+
+        let mut hook = Self {
+            target,
+            detour,
+            trampoline,
+            original,
+            enabled: false,
+        };
+        hook.enable()?;
+
+        Ok(hook)
+    }
+
+    /// Writes the jump to the detour over the target.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the protection of the target couldn't be
+    /// changed.
+    pub fn enable(&mut self) -> Result<(), Error> {
+        if self.enabled {
+            return Ok(());
+        }
+
+        let mut patch = jmp(self.target, self.detour);
+        // Pad out the rest of the last instruction we overwrote
+        patch.resize(self.original.len(), 0x90);
+
+        write_protected(self.target, &patch)?;
+        self.enabled = true;
+
+        Ok(())
+    }
+
+    /// Restores the original instructions of the target.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the protection of the target couldn't be
+    /// changed.
+    pub fn disable(&mut self) -> Result<(), Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        write_protected(self.target, &self.original)?;
+        self.enabled = false;
+
+        Ok(())
+    }
+
+    /// Whether the target currently jumps to the detour.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool { self.enabled }
+
+    /// The address of the hooked function.
+    #[must_use]
+    pub const fn target(&self) -> usize { self.target }
+
+    /// The address of the detour.
+    #[must_use]
+    pub const fn detour(&self) -> usize { self.detour }
+
+    /// The address of the trampoline, transmute it to the signature of the
+    /// target to call the original function.
+    #[must_use]
+    pub const fn trampoline(&self) -> usize { self.trampoline }
+}
+
+impl Drop for InlineHook {
+    fn drop(&mut self) {
+        // The trampoline has to stay around if the target still jumps to it
+        if self.disable().is_ok() {
+            let _ = virtual_free_ex(get_current_process(), self.trampoline as LPVOID, 0, MEM_RELEASE);
+        }
+    }
+}
+
+/// How many of the `size` bytes at `address` are committed and readable, the
+/// decoder gets no more than those.
+fn readable_length(address: usize, size: usize) -> usize {
+    let mut readable = 0;
+    while readable < size {
+        match LocalProcess.query(address + readable) {
+            Ok(region) if region.is_committed && region.is_readable && !region.is_guard => {
+                readable = region.end() - address;
+            },
+            _ => break,
+        }
+    }
+
+    readable.min(size)
+}
+
+/// Allocates `size` bytes of executable memory for the trampoline of a hook on
+/// `target`.
+///
+/// On x64 we try to allocate within 2GB of the target first so rip relative
+/// operands that were stolen can still reach what they point to.
+#[cfg_attr(target_arch = "x86", allow(unused_variables))]
+fn alloc_trampoline(target: usize, size: usize) -> Result<usize, Error> {
+    #[cfg(target_arch = "x86_64")]
+    if let Some(trampoline) = alloc_near(target, size) {
+        return Ok(trampoline);
+    }
+
+    let trampoline = virtual_alloc_ex(
+        get_current_process(),
+        None,
+        size,
+        MEM_COMMIT | MEM_RESERVE,
+        PAGE_EXECUTE_READWRITE,
+    )?;
+
+    Ok(trampoline as usize)
+}
+
+/// Allocates `size` bytes in the first free region within reach of a rel32
+/// from `target`.
+#[cfg(target_arch = "x86_64")]
+fn alloc_near(target: usize, size: usize) -> Option<usize> {
+    find_near(&LocalProcess, target, size, |address| {
+        virtual_alloc_ex(
+            get_current_process(),
+            Some(address as LPVOID),
+            size,
+            MEM_COMMIT | MEM_RESERVE,
+            PAGE_EXECUTE_READWRITE,
+        )
+        .ok()
+        .map(|trampoline| trampoline as usize)
+    })
+}
+
+/// Builds a jump at `from` that lands on `to`.
+#[cfg(target_arch = "x86_64")]
+pub(super) fn jmp(_from: usize, to: usize) -> Vec<u8> {
+    let mut code = vec![0xFF, 0x25, 0x00, 0x00, 0x00, 0x00];
+    code.extend_from_slice(&(to as u64).to_le_bytes());
+    code
+}
+
+/// Builds a jump at `from` that lands on `to`.
+#[cfg(target_arch = "x86")]
+pub(super) fn jmp(from: usize, to: usize) -> Vec<u8> {
+    let mut code = vec![0xE9];
+    code.extend_from_slice(&(to.wrapping_sub(from + JMP_SIZE) as u32).to_le_bytes());
+    code
+}
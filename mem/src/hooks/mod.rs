@@ -1,8 +1,50 @@
+//! `hooks` redirects code in our own process, either to hook the graphics
+//! libraries or any function we know the address of.
+#[cfg(feature = "internal")]
+mod iat;
+#[cfg(feature = "internal")]
+mod inline;
+#[cfg(feature = "internal")]
+mod relocate;
+#[cfg(feature = "internal")]
+mod vmt;
+
+#[cfg(feature = "internal")]
+pub use iat::IatHook;
+#[cfg(feature = "internal")]
+pub use inline::InlineHook;
+#[cfg(feature = "internal")]
+pub use vmt::VmtHook;
+#[cfg(feature = "internal")]
+use windows::Win32::System::Memory::PAGE_EXECUTE_READWRITE;
+
+#[cfg(feature = "internal")]
 use crate::{
-    error::{Error, Result},
-    hooks,
+    error::Error,
+    windows::wrappers::{flush_instruction_cache, get_current_process, virtual_protect, LPCVOID, LPVOID},
 };
 
+// This just checks if the user wants any of the graphic libraries
+#[cfg(any(
+    feature = "vulkan",
+    feature = "opengl",
+    feature = "d3d9",
+    feature = "d3d10",
+    feature = "d3d11",
+    feature = "d3d12"
+))]
+mod graphics;
+
+#[cfg(any(
+    feature = "vulkan",
+    feature = "opengl",
+    feature = "d3d9",
+    feature = "d3d10",
+    feature = "d3d11",
+    feature = "d3d12"
+))]
+pub use graphics::{GraphicsHook, MethodTable, RenderType};
+
 #[cfg(any(feature = "d3d9", feature = "d3d10", feature = "d3d11", feature = "d3d12"))]
 pub mod d3d;
 
@@ -12,48 +54,16 @@ pub mod opengl;
 #[cfg(feature = "vulkan")]
 pub mod vulkan;
 
-pub enum RenderType {
-    OPENGL = 0,
-    VULKAN,
-    D3D9,
-    D3D10,
-    D3D11,
-    D3D12,
-}
-
-pub type MethodTable = Vec<*const usize>;
+/// Copies `bytes` over `address` in our own process, the memory is made
+/// writable for as long as that takes.
+#[cfg(feature = "internal")]
+fn write_protected(address: usize, bytes: &[u8]) -> Result<(), Error> {
+    let mut old_protect = 0;
+    virtual_protect(address as LPVOID, bytes.len(), PAGE_EXECUTE_READWRITE, &raw mut old_protect)?;
 
-pub struct GraphicsHook {
-    method_table: MethodTable,
-}
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), address as *mut u8, bytes.len()) };
 
-impl GraphicsHook {
-    /// Acquires the method table by creating a dummy device
-    pub fn new(render_type: RenderType) -> Result<Self> {
-        let method_table: MethodTable = match render_type {
-            RenderType::OPENGL => {
-                #[cfg(not(feature = "opengl"))]
-                return Err(Error::RenderType);
-                hooks::opengl::get_method_table()?
-            },
-            RenderType::VULKAN => {
-                #[cfg(not(feature = "vulkan"))]
-                return Err(Error::RenderType);
-                hooks::vulkan::get_method_table()?
-            },
-            RenderType::D3D9 | RenderType::D3D10 | RenderType::D3D11 | RenderType::D3D12 => {
-                #[cfg(not(any(feature = "d3d9", feature = "d3d10", feature = "d3d11", feature = "d3d12")))]
-                return Err(Error::RenderType);
-                hooks::d3d::get_method_table(render_type)?
-            },
-        };
-
-        Ok(GraphicsHook)
-    }
-
-    // TODO: DOC
-    pub fn hook(index: u16) -> Result<()> {}
-
-    // TODO: DOC
-    pub fn unhook() -> Result<()> {}
+    let mut ignored = 0;
+    virtual_protect(address as LPVOID, bytes.len(), old_protect, &raw mut ignored)?;
+    flush_instruction_cache(get_current_process(), address as LPCVOID, bytes.len())
 }
@@ -0,0 +1,287 @@
+//! `relocate` moves stolen instructions into a trampoline. Instructions with a
+//! displacement relative to their own address would point somewhere else once
+//! they are moved, so they are rewritten to reach their original targets.
+use std::convert::TryFrom;
+
+use super::inline::{jmp, JMP_SIZE};
+use crate::{disasm::Instruction, error::Error};
+
+/// `call [rip+2] ; jmp +8` followed by the absolute address.
+#[cfg(target_arch = "x86_64")]
+const CALL_SIZE: usize = 16;
+/// `call rel32`, which reaches the entire address space on x86.
+#[cfg(target_arch = "x86")]
+const CALL_SIZE: usize = 5;
+
+/// A short conditional branch to a jump, and a short jump over it for when the
+/// condition doesn't hold.
+const CONDITIONAL_SIZE: usize = 4 + JMP_SIZE;
+
+/// What a relative instruction turns into inside of the trampoline.
+#[derive(Clone, Copy)]
+enum Branch {
+    Jump,
+    Call,
+    /// A conditional branch with the opcode of its short form, with the
+    /// number of prefix bytes in front of the opcode.
+    Conditional(u8, usize),
+}
+
+/// An instruction that was stolen from the start of the target.
+pub(super) struct Stolen {
+    /// The offset of the instruction from the start of the target.
+    pub offset: usize,
+    pub instruction: Instruction,
+}
+
+/// Builds the code of a trampoline at `trampoline` out of the `instructions`
+/// that were stolen from `code`, which was read from `source`.
+///
+/// # Errors
+/// `Error::Hook` if a branch lands in the middle of a stolen instruction or a
+/// rip relative operand can't reach its target from the trampoline.
+#[allow(clippy::cast_sign_loss)]
+pub(super) fn relocate(code: &[u8], source: usize, stolen: &[Stolen], trampoline: usize) -> Result<Vec<u8>, Error> {
+    // Rewritten instructions grow, so the new offsets are needed before any
+    // branch inside of the stolen bytes can be pointed at its new location
+    let mut offsets = Vec::with_capacity(stolen.len());
+    let mut size = 0;
+    for instruction in stolen {
+        offsets.push(size);
+        size += relocated_size(code, instruction)?;
+    }
+    let stolen_size = stolen.last().map_or(0, |last| last.offset + last.instruction.length);
+
+    let mut relocated = Vec::with_capacity(size);
+    for (instruction, new_offset) in stolen.iter().zip(&offsets) {
+        let at = trampoline + new_offset;
+        let bytes = &code[instruction.offset..instruction.offset + instruction.instruction.length];
+        let original_end = source + instruction.offset + bytes.len();
+
+        let Some(relative) = instruction.instruction.relative else {
+            relocated.extend_from_slice(bytes);
+            continue;
+        };
+
+        let displacement = read_displacement(bytes, relative.offset, relative.size);
+        let destination = original_end.wrapping_add(displacement as usize);
+
+        if !relative.branch {
+            // A rip relative memory operand, only its displacement changes
+            let displacement = (destination as isize).wrapping_sub((at + bytes.len()) as isize);
+            let displacement = i32::try_from(displacement)
+                .map_err(|_| Error::Hook("a rip relative operand can't reach its target from the trampoline"))?;
+
+            relocated.extend_from_slice(bytes);
+            let start = relocated.len() - bytes.len() + relative.offset;
+            relocated[start..start + 4].copy_from_slice(&displacement.to_le_bytes());
+            continue;
+        }
+
+        // Branches into the stolen bytes have to follow them into the
+        // trampoline
+        let destination = match destination.wrapping_sub(source) {
+            inside if inside < stolen_size => {
+                let index = stolen
+                    .iter()
+                    .position(|instruction| instruction.offset == inside)
+                    .ok_or(Error::Hook("a branch lands in the middle of a stolen instruction"))?;
+                trampoline + offsets[index]
+            },
+            _ => destination,
+        };
+
+        match branch_kind(bytes, relative.offset, relative.size)? {
+            Branch::Jump => relocated.extend_from_slice(&jmp(at, destination)),
+            Branch::Call => relocated.extend_from_slice(&call(at, destination)),
+            Branch::Conditional(opcode, prefixes) => {
+                // jcc +2 lands on the jump to the destination, the jmp right
+                // after it skips that jump when the condition doesn't hold
+                relocated.extend_from_slice(&bytes[..prefixes]);
+                relocated.extend_from_slice(&[opcode, 0x02, 0xEB, JMP_SIZE as u8]);
+                relocated.extend_from_slice(&jmp(at + prefixes + 4, destination));
+            },
+        }
+    }
+
+    Ok(relocated)
+}
+
+/// How many bytes the trampoline needs for the `stolen` instructions, not
+/// counting the jump back into the target.
+///
+/// # Errors
+/// `Error::Hook` if one of the instructions is a branch we can't relocate.
+pub(super) fn relocated_size_of(code: &[u8], stolen: &[Stolen]) -> Result<usize, Error> {
+    stolen.iter().map(|instruction| relocated_size(code, instruction)).sum()
+}
+
+/// How many bytes `instruction` takes up once it is in the trampoline.
+fn relocated_size(code: &[u8], instruction: &Stolen) -> Result<usize, Error> {
+    let bytes = &code[instruction.offset..instruction.offset + instruction.instruction.length];
+
+    match instruction.instruction.relative {
+        Some(relative) if relative.branch => Ok(match branch_kind(bytes, relative.offset, relative.size)? {
+            Branch::Jump => JMP_SIZE,
+            Branch::Call => CALL_SIZE,
+            Branch::Conditional(_, prefixes) => prefixes + CONDITIONAL_SIZE,
+        }),
+        _ => Ok(bytes.len()),
+    }
+}
+
+/// Works out what kind of branch the instruction in `bytes` is from the opcode
+/// in front of its displacement.
+fn branch_kind(bytes: &[u8], offset: usize, size: usize) -> Result<Branch, Error> {
+    let unknown = Error::Hook("unknown relative branch in the stolen instructions");
+    let opcode = bytes[offset - 1];
+
+    match (size, opcode) {
+        (4, 0xE9) | (1, 0xEB) => Ok(Branch::Jump),
+        (4, 0xE8) => Ok(Branch::Call),
+        // jcc rel32 is 0f 80 to 0f 8f, its short form is 70 to 7f
+        (4, 0x80..=0x8F) if offset >= 2 && bytes[offset - 2] == 0x0F => {
+            Ok(Branch::Conditional(0x70 | (opcode & 0x0F), offset - 2))
+        },
+        // jcc rel8 and loop, loope, loopne and jcxz
+        (1, 0x70..=0x7F | 0xE0..=0xE3) => Ok(Branch::Conditional(opcode, offset - 1)),
+        _ => Err(unknown),
+    }
+}
+
+/// Reads the signed displacement of `size` bytes at `offset`.
+fn read_displacement(bytes: &[u8], offset: usize, size: usize) -> isize {
+    if size == 1 {
+        isize::from(bytes[offset] as i8)
+    } else {
+        let mut displacement = [0; 4];
+        displacement.copy_from_slice(&bytes[offset..offset + 4]);
+        i32::from_le_bytes(displacement) as isize
+    }
+}
+
+/// Builds a call at `from` that calls `to`.
+#[cfg(target_arch = "x86_64")]
+fn call(_from: usize, to: usize) -> Vec<u8> {
+    let mut code = vec![0xFF, 0x15, 0x02, 0x00, 0x00, 0x00, 0xEB, 0x08];
+    code.extend_from_slice(&(to as u64).to_le_bytes());
+    code
+}
+
+/// Builds a call at `from` that calls `to`.
+#[cfg(target_arch = "x86")]
+fn call(from: usize, to: usize) -> Vec<u8> {
+    let mut code = vec![0xE8];
+    code.extend_from_slice(&(to.wrapping_sub(from + CALL_SIZE) as u32).to_le_bytes());
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::decode;
+
+    const IS_64: bool = cfg!(target_arch = "x86_64");
+
+    /// `xor eax, eax ; test ecx, ecx ; je +6 ; mov eax, 42 ; ret ; mov eax, 7 ;
+    /// ret`, the same function the inline hook test hooks.
+    const FUNCTION: [u8; 18] = [
+        0x31, 0xC0, 0x85, 0xC9, 0x74, 0x06, 0xB8, 0x2A, 0x00, 0x00, 0x00, 0xC3, 0xB8, 0x07, 0x00, 0x00, 0x00, 0xC3,
+    ];
+    const SOURCE: usize = 0x1000_0000;
+    const TRAMPOLINE: usize = 0x1100_0000;
+
+    /// Decodes instructions from the start of `code` like `InlineHook::new`
+    /// until there is room for a jump.
+    fn steal(code: &[u8]) -> Vec<Stolen> {
+        let mut instructions = Vec::new();
+        let mut stolen = 0;
+        while stolen < JMP_SIZE {
+            let instruction = decode(&code[stolen..], IS_64).unwrap();
+            instructions.push(Stolen {
+                offset: stolen,
+                instruction,
+            });
+            stolen += instruction.length;
+        }
+
+        instructions
+    }
+
+    #[test]
+    fn branch_kind_of_jumps_and_calls() {
+        assert!(matches!(branch_kind(&[0xEB, 0x10], 1, 1), Ok(Branch::Jump)));
+        assert!(matches!(branch_kind(&[0xE9, 0, 0, 0, 0], 1, 4), Ok(Branch::Jump)));
+        assert!(matches!(branch_kind(&[0xE8, 0, 0, 0, 0], 1, 4), Ok(Branch::Call)));
+    }
+
+    #[test]
+    fn branch_kind_of_conditional_branches() {
+        assert!(matches!(branch_kind(&[0x74, 0x06], 1, 1), Ok(Branch::Conditional(0x74, 0))));
+        // jne rel32 turns into the short jne
+        assert!(matches!(
+            branch_kind(&[0x0F, 0x85, 0, 0, 0, 0], 2, 4),
+            Ok(Branch::Conditional(0x75, 0))
+        ));
+        assert!(matches!(branch_kind(&[0xE3, 0x02], 1, 1), Ok(Branch::Conditional(0xE3, 0))));
+        assert!(branch_kind(&[0xC3, 0x00], 1, 1).is_err());
+    }
+
+    #[test]
+    fn relocates_a_rel8_jump_out_of_the_stolen_bytes() {
+        // jmp +0x10 ; nop padding so there is room for a jump
+        let mut code = vec![0xEB, 0x10];
+        code.extend_from_slice(&[0x90; 16]);
+        let stolen = steal(&code);
+
+        let relocated = relocate(&code, SOURCE, &stolen, TRAMPOLINE).unwrap();
+
+        assert_eq!(&relocated[..JMP_SIZE], jmp(TRAMPOLINE, SOURCE + 2 + 0x10).as_slice());
+        assert_eq!(relocated.len(), relocated_size_of(&code, &stolen).unwrap());
+    }
+
+    #[test]
+    fn relocates_a_rel32_call() {
+        // call +0x100 ; nop padding so there is room for a jump
+        let mut code = vec![0xE8, 0x00, 0x01, 0x00, 0x00];
+        code.extend_from_slice(&[0x90; 16]);
+        let stolen = steal(&code);
+
+        let relocated = relocate(&code, SOURCE, &stolen, TRAMPOLINE).unwrap();
+
+        assert_eq!(&relocated[..CALL_SIZE], call(TRAMPOLINE, SOURCE + 5 + 0x100).as_slice());
+        assert_eq!(relocated.len(), relocated_size_of(&code, &stolen).unwrap());
+    }
+
+    #[test]
+    fn relocates_a_short_conditional_branch() {
+        let stolen = steal(&FUNCTION);
+        let stolen_size = stolen.last().map(|last| last.offset + last.instruction.length).unwrap();
+
+        let relocated = relocate(&FUNCTION, SOURCE, &stolen, TRAMPOLINE).unwrap();
+
+        // The je at 4 lands on 12, which only moved into the trampoline if it
+        // was stolen too. Everything after the je moved by how much it grew.
+        let destination = if stolen_size > 12 {
+            TRAMPOLINE + 12 + CONDITIONAL_SIZE - 2
+        } else {
+            SOURCE + 12
+        };
+
+        assert_eq!(&relocated[..4], &FUNCTION[..4]);
+        assert_eq!(&relocated[4..8], &[0x74, 0x02, 0xEB, JMP_SIZE as u8]);
+        assert_eq!(&relocated[8..8 + JMP_SIZE], jmp(TRAMPOLINE + 8, destination).as_slice());
+        assert_eq!(&relocated[8 + JMP_SIZE..], &FUNCTION[6..stolen_size]);
+        assert_eq!(relocated.len(), relocated_size_of(&FUNCTION, &stolen).unwrap());
+    }
+
+    #[test]
+    fn rejects_a_branch_into_the_middle_of_a_stolen_instruction() {
+        // jmp -1 lands on its own displacement
+        let mut code = vec![0xEB, 0xFF];
+        code.extend_from_slice(&[0x90; 16]);
+        let stolen = steal(&code);
+
+        assert!(relocate(&code, SOURCE, &stolen, TRAMPOLINE).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use std::mem::size_of;
+
+use super::write_protected;
+use crate::error::Error;
+
+/// `VmtHook` swaps entries of a virtual method table in our own process.
+///
+/// The original entries are copied when the hook is created, every entry that
+/// was hooked is restored once the `VmtHook` is dropped.
+pub struct VmtHook {
+    vtable: usize,
+    original: Vec<usize>,
+    hooked: Vec<bool>,
+}
+
+impl VmtHook {
+    /// Copies the first `size` entries of the virtual method table at
+    /// `vtable`, which is the value of the vtable pointer at the start of an
+    /// object.
+    ///
+    /// # Safety
+    /// `vtable` has to point to a virtual method table in our own process with
+    /// at least `size` entries, and the table has to stay alive for as long as
+    /// the `VmtHook` since dropping it writes the original entries back. The
+    /// detours passed to `hook` have to match the signatures of the entries
+    /// they replace.
+    ///
+    /// # Errors
+    /// `Error::Hook` if `vtable` is null or `size` is zero.
+    pub unsafe fn new(vtable: usize, size: usize) -> Result<Self, Error> {
+        if vtable == 0 || size == 0 {
+            return Err(Error::Hook("vtable is null or empty"));
+        }
+
+        let original = std::slice::from_raw_parts(vtable as *const usize, size).to_vec();
+
+        Ok(Self {
+            vtable,
+            original,
+            hooked: vec![false; size],
+        })
+    }
+
+    /// Points the entry at `index` to `detour`, returning the original function
+    /// so the detour can call it.
+    ///
+    /// # Errors
+    /// `Error::Hook` if `index` is outside of the table, or
+    /// `Error::MemoryError` if the table couldn't be written.
+    pub fn hook(&mut self, index: usize, detour: usize) -> Result<usize, Error> {
+        let original = self.original(index).ok_or(Error::Hook("vtable index is out of range"))?;
+
+        write_protected(self.entry(index), &detour.to_ne_bytes())?;
+        self.hooked[index] = true;
+
+        Ok(original)
+    }
+
+    /// Restores the original entry at `index`.
+    ///
+    /// # Errors
+    /// `Error::Hook` if `index` is outside of the table, or
+    /// `Error::MemoryError` if the table couldn't be written.
+    pub fn unhook(&mut self, index: usize) -> Result<(), Error> {
+        let original = self.original(index).ok_or(Error::Hook("vtable index is out of range"))?;
+
+        if self.hooked[index] {
+            write_protected(self.entry(index), &original.to_ne_bytes())?;
+            self.hooked[index] = false;
+        }
+
+        Ok(())
+    }
+
+    /// The original function at `index`, `None` if `index` is outside of the
+    /// table.
+    #[must_use]
+    pub fn original(&self, index: usize) -> Option<usize> { self.original.get(index).copied() }
+
+    /// The address of the virtual method table.
+    #[must_use]
+    pub const fn vtable(&self) -> usize { self.vtable }
+
+    /// The number of entries that were copied out of the table.
+    #[must_use]
+    pub const fn len(&self) -> usize { self.original.len() }
+
+    /// Whether the table is empty, which can never happen since `new` requires
+    /// at least one entry.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool { self.original.is_empty() }
+
+    const fn entry(&self, index: usize) -> usize { self.vtable + index * size_of::<usize>() }
+}
+
+impl Drop for VmtHook {
+    fn drop(&mut self) {
+        for index in 0..self.original.len() {
+            // Nothing useful can be done if restoring fails inside of drop
+            let _ = self.unhook(index);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! `hijack` runs a stub on an existing thread of the target instead of creating
+//! a new one. The thread is suspended, pointed at the stub and resumed, once
+//! the stub signals it is done the thread gets its original context back.
+use std::time::{Duration, Instant};
+
+#[cfg(target_arch = "x86")]
+use windows::Win32::System::SystemServices::CONTEXT_i386 as CONTEXT_ARCH;
+#[cfg(target_arch = "x86_64")]
+use windows::Win32::System::SystemServices::CONTEXT_AMD64 as CONTEXT_ARCH;
+use windows::Win32::System::Threading::{THREAD_GET_CONTEXT, THREAD_SET_CONTEXT, THREAD_SUSPEND_RESUME};
+
+use crate::{
+    error::Error,
+    process::{walk_threads, Process},
+    windows::{
+        handle::SafeHandle,
+        wrappers::{
+            get_thread_context, open_thread, resume_thread, set_thread_context, suspend_thread, Context as ThreadContext,
+        },
+    },
+};
+
+const CONTEXT_CONTROL: u32 = CONTEXT_ARCH as u32 | 0x1;
+/// Every register we can get at, so the thread is restored exactly the way it
+/// was before we hijacked it.
+#[cfg(target_arch = "x86_64")]
+const CONTEXT_ALL: u32 = CONTEXT_ARCH as u32 | 0x1F;
+#[cfg(target_arch = "x86")]
+const CONTEXT_ALL: u32 = CONTEXT_ARCH as u32 | 0x3F;
+
+/// How long the hijacked thread has to start running the stub, threads that
+/// are blocked inside of a wait only pick up their new context once the wait
+/// returns.
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `GetThreadContext` needs a 16 byte aligned `CONTEXT` on x64, which the
+/// windows crate doesn't guarantee.
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Default)]
+struct Context(ThreadContext);
+
+/// Runs the stub at `code` on a thread of `process`, returning once the stub
+/// writes a non zero value to `done`.
+///
+/// The stub must never return, it has to spin once it wrote to `done` since the
+/// thread has nothing to return to.
+///
+/// `Error::Timeout` is returned if the stub isn't done within `timeout`. A
+/// thread that never started running the stub gets its original context back,
+/// one that is already inside of the called function is left to finish it and
+/// spin in the stub, since taking it back halfway could leave the target in a
+/// broken state.
+pub(super) fn run(process: &Process, code: usize, done: usize, timeout: Option<Duration>) -> Result<(), Error> {
+    let thread = first_thread(process)?;
+
+    suspend(&thread)?;
+    let original = match get_context(&thread, CONTEXT_ALL) {
+        Ok(context) => context,
+        Err(error) => {
+            let _ = resume(&thread);
+            return Err(error);
+        },
+    };
+
+    let mut hijacked = original;
+    set_instruction_pointer(&mut hijacked, code);
+    if let Err(error) = set_context(&thread, &hijacked) {
+        let _ = resume(&thread);
+        return Err(error);
+    }
+    resume(&thread)?;
+
+    let started = Instant::now();
+    let start_timeout = timeout.map_or(START_TIMEOUT, |timeout| timeout.min(START_TIMEOUT));
+    let mut waiting_for_start = true;
+    while process.read::<u8>(done)? == 0 {
+        let elapsed = started.elapsed();
+        if waiting_for_start && elapsed > start_timeout {
+            suspend(&thread)?;
+            let current = get_context(&thread, CONTEXT_CONTROL)?;
+
+            // It is only safe to take the thread back if it hasn't started
+            // running the stub yet
+            if instruction_pointer(&current) == code {
+                set_context(&thread, &original)?;
+                resume(&thread)?;
+                return Err(Error::Timeout);
+            }
+
+            waiting_for_start = false;
+            resume(&thread)?;
+        } else if timeout.is_some_and(|timeout| elapsed > timeout) {
+            return Err(Error::Timeout);
+        }
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    suspend(&thread)?;
+    set_context(&thread, &original)?;
+    resume(&thread)
+}
+
+/// Opens the first thread owned by `process`.
+fn first_thread(process: &Process) -> Result<SafeHandle, Error> {
+    let thread_id = walk_threads(process.pid(), |entry| Some(entry.th32ThreadID))?.ok_or(Error::ProcessNotFound)?;
+    open_thread(
+        THREAD_SUSPEND_RESUME | THREAD_GET_CONTEXT | THREAD_SET_CONTEXT,
+        false,
+        thread_id,
+    )
+}
+
+fn suspend(thread: &SafeHandle) -> Result<(), Error> { suspend_thread(**thread).map(|_| ()) }
+
+fn resume(thread: &SafeHandle) -> Result<(), Error> { resume_thread(**thread).map(|_| ()) }
+
+fn get_context(thread: &SafeHandle, flags: u32) -> Result<Context, Error> {
+    let mut context = Context::default();
+    context.0.ContextFlags = flags;
+
+    get_thread_context(**thread, &mut context.0)?;
+    Ok(context)
+}
+
+fn set_context(thread: &SafeHandle, context: &Context) -> Result<(), Error> { set_thread_context(**thread, &context.0) }
+
+#[cfg(target_arch = "x86_64")]
+const fn instruction_pointer(context: &Context) -> usize { context.0.Rip as usize }
+
+#[cfg(target_arch = "x86")]
+const fn instruction_pointer(context: &Context) -> usize { context.0.Eip as usize }
+
+#[cfg(target_arch = "x86_64")]
+const fn set_instruction_pointer(context: &mut Context, address: usize) { context.0.Rip = address as u64; }
+
+#[cfg(target_arch = "x86")]
+const fn set_instruction_pointer(context: &mut Context, address: usize) { context.0.Eip = address as u32; }
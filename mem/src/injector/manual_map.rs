@@ -0,0 +1,196 @@
+//! `manual_map` does the work of the windows loader ourselves so the DLL never
+//! shows up in the module list of the target process.
+use std::{collections::HashMap, time::Duration};
+
+use windows::Win32::System::{Memory::PAGE_EXECUTE_READWRITE, SystemServices::DLL_PROCESS_ATTACH};
+
+use super::{
+    stub::{self, Argument},
+    CodeExecutionMethod,
+};
+use crate::{
+    error::Error,
+    pe::{self, ImportName, PeHeaders},
+    process::{Architecture, Process},
+    windows::wrappers::{get_module_handle, get_proc_address},
+};
+
+/// Maps the DLL in `file` into `process` and calls its entry point, returning
+/// the base address it was mapped at. The headers are zeroed afterwards if
+/// `erase_headers` is set, if that fails the DLL stays mapped and running.
+/// Every call into the target has to return within `timeout`.
+///
+/// The target and the DLL have to be built for the same architecture as the
+/// injector, which lets us use our own addresses of `LoadLibraryA` and
+/// `GetProcAddress` since `kernel32.dll` is loaded at the same address in every
+/// process.
+pub(super) fn manual_map(
+    process: &Process,
+    file: &[u8],
+    method: CodeExecutionMethod,
+    timeout: Option<Duration>,
+    erase_headers: bool,
+) -> Result<usize, Error> {
+    let headers = PeHeaders::parse(file)?;
+    // The injector already made sure the DLL matches the target
+    if process.architecture()? != Architecture::HOST {
+        return Err(Error::ManualMap("target architecture doesn't match the injector"));
+    }
+
+    // The image is freed again if anything goes wrong before it is mapped
+    let image = process.alloc(headers.size_of_image as usize, PAGE_EXECUTE_READWRITE)?;
+    map(process, file, &headers, image.address(), method, timeout)?;
+
+    // DllMain already ran, so the image has to stay even if the headers can't
+    // be erased since threads the DLL started could be running in it
+    let base = image.into_raw();
+    if erase_headers {
+        process.write_bytes(base, &vec![0; headers.size_of_headers as usize])?;
+    }
+
+    Ok(base)
+}
+
+fn map(
+    process: &Process,
+    file: &[u8],
+    headers: &PeHeaders,
+    base: usize,
+    method: CodeExecutionMethod,
+    timeout: Option<Duration>,
+) -> Result<(), Error> {
+    let mut image = pe::map_sections(file, headers)?;
+    pe::relocate(&mut image, headers, base as u64)?;
+    resolve_imports(process, &mut image, headers, method, timeout)?;
+
+    process.write_bytes(base, &image)?;
+
+    let initializers = tls_callbacks(&image, headers, base)?;
+    for callback in initializers {
+        stub::call(
+            process,
+            method,
+            timeout,
+            callback,
+            &[
+                Argument::Value(base as u64),
+                Argument::Value(u64::from(DLL_PROCESS_ATTACH)),
+                Argument::Value(0),
+            ],
+        )?;
+    }
+
+    if headers.address_of_entry_point != 0 {
+        let entry_point = base + headers.address_of_entry_point as usize;
+        let result = stub::call(
+            process,
+            method,
+            timeout,
+            entry_point,
+            &[
+                Argument::Value(base as u64),
+                Argument::Value(u64::from(DLL_PROCESS_ATTACH)),
+                Argument::Value(0),
+            ],
+        )?;
+
+        // DllMain returns a BOOL
+        if result as u32 == 0 {
+            return Err(Error::ManualMap("DllMain returned FALSE"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads every module the image imports inside of the target and fills its
+/// import address table.
+fn resolve_imports(
+    process: &Process,
+    image: &mut [u8],
+    headers: &PeHeaders,
+    method: CodeExecutionMethod,
+    timeout: Option<Duration>,
+) -> Result<(), Error> {
+    let kernel32 = get_module_handle("kernel32.dll")?;
+    let load_library = get_proc_address(kernel32, "LoadLibraryA")?;
+    let get_proc = get_proc_address(kernel32, "GetProcAddress")?;
+
+    let mut modules = HashMap::new();
+    for import in pe::imports(image, headers)? {
+        let key = import.module.to_ascii_lowercase();
+        let module = if let Some(&module) = modules.get(&key) {
+            module
+        } else {
+            // LoadLibraryA just hands back the existing handle if the
+            // module is already loaded
+            let module = stub::call(process, method, timeout, load_library, &[Argument::String(&import.module)])?;
+            if module == 0 {
+                return Err(Error::ModuleNotFound(import.module));
+            }
+
+            modules.insert(key, module);
+            module
+        };
+
+        let function = match &import.function {
+            ImportName::Name(name) => stub::call(
+                process,
+                method,
+                timeout,
+                get_proc,
+                &[Argument::Value(module), Argument::String(name)],
+            )?,
+            ImportName::Ordinal(ordinal) => stub::call(
+                process,
+                method,
+                timeout,
+                get_proc,
+                &[Argument::Value(module), Argument::Value(u64::from(*ordinal))],
+            )?,
+        };
+
+        if function == 0 {
+            return Err(Error::ManualMap("couldn't resolve an imported function"));
+        }
+
+        let thunk = import.thunk as usize;
+        let pointer_size = headers.pointer_size();
+        image
+            .get_mut(thunk..thunk + pointer_size)
+            .ok_or(Error::InvalidPe("import thunk is outside of the image"))?
+            .copy_from_slice(&function.to_le_bytes()[..pointer_size]);
+    }
+
+    Ok(())
+}
+
+/// The addresses of the TLS callbacks of an image that was already relocated
+/// to `base`.
+fn tls_callbacks(image: &[u8], headers: &PeHeaders, base: usize) -> Result<Vec<usize>, Error> {
+    let Some(directory) = headers.directory(pe::DIRECTORY_TLS) else {
+        return Ok(Vec::new());
+    };
+
+    let pointer_size = headers.pointer_size();
+    // AddressOfCallBacks comes after the raw data range and the index address
+    let callbacks = pe::read_pointer(image, directory.virtual_address as usize + pointer_size * 3, headers.is_64)?;
+    if callbacks == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut address = (callbacks as usize)
+        .checked_sub(base)
+        .ok_or(Error::InvalidPe("TLS callbacks are outside of the image"))?;
+
+    let mut initializers = Vec::new();
+    loop {
+        let callback = pe::read_pointer(image, address, headers.is_64)?;
+        if callback == 0 {
+            return Ok(initializers);
+        }
+
+        initializers.push(callback as usize);
+        address += pointer_size;
+    }
+}
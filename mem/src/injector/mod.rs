@@ -0,0 +1,587 @@
+mod hijack;
+mod manual_map;
+mod stub;
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    error::Error,
+    pe::PeHeaders,
+    process::{self, Access, Architecture, Process},
+    windows::{
+        attributes::{GroupAffinity, ThreadAttributeList, PROC_THREAD_ATTRIBUTE_GROUP_AFFINITY},
+        utils::to_wide,
+        wrappers::{get_module_handle, get_proc_address},
+    },
+};
+
+/// How long we wait for the DLL to load unless `Config::timeout` says
+/// otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long we wait before the first retry unless `Config::retry_delay` says
+/// otherwise.
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// The id of a process together with the result of injecting into it, see
+/// `Injector::inject_all_by_name`.
+pub type InjectionResult = (u32, Result<usize, Error>);
+
+/// Several methods of loading our library into the target process
+pub enum InjectionMethod {
+    /// This is the typical method when safety is not really a concern
+    LoadLibrary,
+    /// `LoadLibraryEx` is just a extended version of `LoadLibrary` which isn't
+    /// always detected by anti-cheats
+    LoadLibraryEx,
+    /// By far the safest method of injection, this does mostly what the other
+    /// methods do but is instead just manually done by us rather than
+    /// windows functions thus you are hidden from `ToolHelp32Snapshot` and
+    /// module crawling because windows didn't load them
+    ManualMap,
+    /// Loads the library with `LoadLibrary` from an existing thread of the
+    /// target, which is hijacked for as long as the call takes, so no thread
+    /// is ever created
+    ThreadHijack,
+}
+
+/// These are methods in which the injector will execute the code from the DLL
+/// that is injected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeExecutionMethod {
+    /// Creates a new thread on the target process which will have `DllMain`
+    /// called
+    CreateRemoteThread,
+    /// Hijacks an existing thread, this is used for stealth if the anti cheat
+    /// detects creating a new thread
+    ThreadHijack,
+}
+
+/// Choices of what to do with PE headers to protect ourselves and be more
+/// stealthy
+pub enum PECloaking {
+    /// We will do nothing with PE headers
+    Keep,
+    /// PE headers will be erased
+    Erase,
+    /// PE headers will be scrambled with fake ones
+    Fake,
+}
+
+#[allow(clippy::struct_excessive_bools)]
+pub struct Config {
+    pub injection_method: InjectionMethod,
+    pub execution_method: CodeExecutionMethod,
+    pub cloak_thread: bool,
+    pub randomize_file_name: bool,
+    pub pe_cloaking: PECloaking,
+    /// Polls the module list of the target until the injected DLL shows up in
+    /// it instead of looking it up once. Manually mapped DLLs never show up in
+    /// the module list so this is ignored for `InjectionMethod::ManualMap`.
+    pub wait_for_module: bool,
+    /// Zeroes the DOS and NT headers of the DLL once it was mapped and its
+    /// entry point returned, which makes it harder to dump. The DLL must not
+    /// read its own headers at runtime when this is enabled, which rules out
+    /// things like `GetProcAddress` on itself. Only used by
+    /// `InjectionMethod::ManualMap`.
+    pub erase_headers: bool,
+    /// How long to wait for every function we call inside of the target to
+    /// return, like `LoadLibrary` or the entry point of a manually mapped DLL,
+    /// no matter which `CodeExecutionMethod` runs it. Also how long to wait for
+    /// the DLL to show up with `wait_for_module`. `None` waits for as long as
+    /// it takes.
+    pub timeout: Option<Duration>,
+    /// Creates the `LoadLibrary` thread with `CreateRemoteThreadEx` instead
+    /// of `CreateRemoteThread`, which is what lets `thread_affinity` be
+    /// applied. `LoadLibraryW` is an exported function so it is already a
+    /// valid CFG call target, and mitigation policies can only be set when a
+    /// process is created so there is no attribute for them here. Only used
+    /// with `CodeExecutionMethod::CreateRemoteThread`.
+    pub use_thread_ex: bool,
+    /// The processors the `LoadLibrary` thread may run on, only used with
+    /// `use_thread_ex`.
+    pub thread_affinity: Option<GroupAffinity>,
+    /// How many more times opening the target is attempted if it fails with
+    /// an error that can go away on its own, like the process not being
+    /// ready to be opened or read yet. Errors that would just happen again,
+    /// like an architecture mismatch, are returned right away. Nothing is
+    /// retried once code started running in the target, since that would load
+    /// the DLL a second time.
+    pub retries: u32,
+    /// How long to wait before the first retry, the delay doubles after every
+    /// retry.
+    pub retry_delay: Duration,
+}
+
+impl Config {
+    const fn max_stealth() -> Self {
+        Self {
+            injection_method: InjectionMethod::ManualMap,
+            execution_method: CodeExecutionMethod::ThreadHijack,
+            cloak_thread: true,
+            randomize_file_name: true,
+            pe_cloaking: PECloaking::Fake,
+            wait_for_module: false,
+            erase_headers: true,
+            timeout: Some(DEFAULT_TIMEOUT),
+            use_thread_ex: false,
+            thread_affinity: None,
+            retries: 0,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            injection_method: InjectionMethod::LoadLibrary,
+            execution_method: CodeExecutionMethod::CreateRemoteThread,
+            cloak_thread: false,
+            randomize_file_name: false,
+            pe_cloaking: PECloaking::Keep,
+            wait_for_module: false,
+            erase_headers: false,
+            timeout: Some(DEFAULT_TIMEOUT),
+            use_thread_ex: false,
+            thread_affinity: None,
+            retries: 0,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+}
+
+impl Config {
+    /// Starts building a `Config` from the defaults.
+    #[must_use]
+    pub fn builder() -> ConfigBuilder { ConfigBuilder::default() }
+}
+
+/// Builds a `Config` one option at a time, every option that isn't set keeps
+/// its value from `Config::default`.
+///
+/// ```ignore
+/// let config = Config::builder()
+///     .method(InjectionMethod::ManualMap)
+///     .erase_headers(true)
+///     .build();
+/// ```
+#[derive(Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    #[must_use]
+    pub const fn method(mut self, method: InjectionMethod) -> Self {
+        self.config.injection_method = method;
+        self
+    }
+
+    #[must_use]
+    pub const fn execution_method(mut self, method: CodeExecutionMethod) -> Self {
+        self.config.execution_method = method;
+        self
+    }
+
+    #[must_use]
+    pub const fn cloak_thread(mut self, cloak_thread: bool) -> Self {
+        self.config.cloak_thread = cloak_thread;
+        self
+    }
+
+    #[must_use]
+    pub const fn randomize_file_name(mut self, randomize_file_name: bool) -> Self {
+        self.config.randomize_file_name = randomize_file_name;
+        self
+    }
+
+    #[must_use]
+    pub const fn pe_cloaking(mut self, pe_cloaking: PECloaking) -> Self {
+        self.config.pe_cloaking = pe_cloaking;
+        self
+    }
+
+    #[must_use]
+    pub const fn wait_for_module(mut self, wait_for_module: bool) -> Self {
+        self.config.wait_for_module = wait_for_module;
+        self
+    }
+
+    #[must_use]
+    pub const fn erase_headers(mut self, erase_headers: bool) -> Self {
+        self.config.erase_headers = erase_headers;
+        self
+    }
+
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    #[must_use]
+    pub const fn use_thread_ex(mut self, use_thread_ex: bool) -> Self {
+        self.config.use_thread_ex = use_thread_ex;
+        self
+    }
+
+    #[must_use]
+    pub const fn thread_affinity(mut self, affinity: GroupAffinity) -> Self {
+        self.config.thread_affinity = Some(affinity);
+        self
+    }
+
+    #[must_use]
+    pub const fn retries(mut self, retries: u32) -> Self {
+        self.config.retries = retries;
+        self
+    }
+
+    #[must_use]
+    pub const fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.config.retry_delay = retry_delay;
+        self
+    }
+
+    #[must_use]
+    pub const fn build(self) -> Config { self.config }
+}
+
+pub struct Injector {
+    config: Config,
+}
+
+impl Injector {
+    #[must_use]
+    pub const fn new(config: Config) -> Self { Self { config } }
+
+    /// Injects the DLL at `dll_path` into the process `process_id` using the
+    /// configured `InjectionMethod` and returns the base address the DLL was
+    /// loaded at inside of the target.
+    ///
+    /// # Errors
+    /// `Error::DllPath` if the DLL couldn't be read,
+    /// `Error::ArchitectureMismatch` if the DLL, the process and the injector
+    /// aren't all built for the same architecture, `Error::ModuleNotFound` if
+    /// `LoadLibrary` failed inside of the target, `Error::Timeout` if a call
+    /// inside of the target didn't return within `Config::timeout` or the DLL
+    /// never showed up in the module list, `Error::InvalidPe` or
+    /// `Error::ManualMap` if manual mapping failed, or whatever error the
+    /// windows function that failed along the way returned. With
+    /// `Config::retries` the last error is returned once every attempt to
+    /// open the target failed.
+    pub fn inject(&self, process_id: u32, dll_path: &str) -> Result<usize, Error> {
+        let file = std::fs::read(dll_path).map_err(|_| Error::DllPath)?;
+        let process = self.open_target(process_id, &file)?;
+
+        self.inject_file(&process, &file, dll_path)
+    }
+
+    /// Injects the DLL at `dll_path` into the process named `process_name`,
+    /// see `inject`.
+    ///
+    /// # Errors
+    /// `Error::ProcessNotFound` if no process has the name,
+    /// `Error::AmbiguousProcess` if more than one process has it, or any error
+    /// `inject` returns.
+    pub fn inject_by_name(&self, process_name: &str, dll_path: &str) -> Result<usize, Error> {
+        let pids = pids_by_name(process_name)?;
+
+        match pids.as_slice() {
+            [] => Err(Error::ProcessNotFound),
+            [pid] => self.inject(*pid, dll_path),
+            _ => Err(Error::AmbiguousProcess(pids)),
+        }
+    }
+
+    /// Injects the DLL at `dll_path` into every process named `process_name`,
+    /// for when several instances of the target are running. Returns the id
+    /// of every process together with the result of injecting into it, see
+    /// `inject`.
+    ///
+    /// A failed injection doesn't stop the others from being attempted. The
+    /// list is empty if no process has the name.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the processes couldn't be enumerated, the
+    /// errors of the single injections are in the returned list.
+    pub fn inject_all_by_name(&self, process_name: &str, dll_path: &str) -> Result<Vec<InjectionResult>, Error> {
+        Ok(pids_by_name(process_name)?
+            .into_iter()
+            .map(|pid| (pid, self.inject(pid, dll_path)))
+            .collect())
+    }
+
+    /// Injects the DLL in `dll` into the process `process_id`, for DLLs that
+    /// are embedded into the injector with `include_bytes!`. Returns the base
+    /// address like `inject`.
+    ///
+    /// `InjectionMethod::ManualMap` maps the DLL straight from `dll` so it
+    /// never touches the disk. Every other method needs a file for
+    /// `LoadLibrary`, so `dll` is written to a new file in the temp directory
+    /// first. That file is left behind since it can't be deleted while the
+    /// target has the DLL loaded.
+    ///
+    /// # Errors
+    /// `Error::Os` if the temp file couldn't be written, `Error::DllPath` if
+    /// the temp directory isn't valid UTF-8, or any error `inject` returns.
+    pub fn inject_bytes(&self, process_id: u32, dll: &[u8]) -> Result<usize, Error> {
+        let process = self.open_target(process_id, dll)?;
+        if matches!(self.config.injection_method, InjectionMethod::ManualMap) {
+            return manual_map::manual_map(
+                &process,
+                dll,
+                self.execution_method(),
+                self.config.timeout,
+                self.config.erase_headers,
+            );
+        }
+
+        let path = write_temp_dll(dll)?;
+        let path = path.to_str().ok_or(Error::DllPath)?;
+        self.inject_file(&process, dll, path)
+    }
+
+    /// Opens the process `process_id` and makes sure the DLL in `file` can be
+    /// injected into it, see `check_architecture`.
+    ///
+    /// This is the only part of an injection that `Config::retries` applies
+    /// to, nothing has been done to the target yet so trying again can't load
+    /// the DLL twice.
+    fn open_target(&self, process_id: u32, file: &[u8]) -> Result<Process, Error> {
+        self.with_retries(|| {
+            let process = Process::open_by_pid(process_id, self.access_rights())?;
+            check_architecture(&process, file)?;
+
+            Ok(process)
+        })
+    }
+
+    /// Runs `attempt` until it succeeds, fails with an error that isn't worth
+    /// retrying, or `Config::retries` ran out.
+    fn with_retries<T>(&self, mut attempt: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+        let mut delay = self.config.retry_delay;
+
+        for _ in 0..self.config.retries {
+            match attempt() {
+                Err(error) if is_recoverable(&error) => {
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                },
+                result => return result,
+            }
+        }
+
+        attempt()
+    }
+
+    /// The rights the configured methods need on the target process.
+    const fn access_rights(&self) -> Access {
+        match self.execution_method() {
+            CodeExecutionMethod::CreateRemoteThread => Access::inject(),
+            CodeExecutionMethod::ThreadHijack => Access::read_write(),
+        }
+    }
+
+    /// `InjectionMethod::ThreadHijack` always runs on a hijacked thread no
+    /// matter what `Config::execution_method` says.
+    const fn execution_method(&self) -> CodeExecutionMethod {
+        match self.config.injection_method {
+            InjectionMethod::ThreadHijack => CodeExecutionMethod::ThreadHijack,
+            _ => self.config.execution_method,
+        }
+    }
+
+    /// The attributes of the `LoadLibrary` thread, `None` unless
+    /// `Config::use_thread_ex` is set.
+    fn thread_attributes(&self) -> Result<Option<ThreadAttributeList<'_>>, Error> {
+        if !self.config.use_thread_ex {
+            return Ok(None);
+        }
+
+        let Some(affinity) = &self.config.thread_affinity else {
+            return ThreadAttributeList::new(0).map(Some);
+        };
+
+        let mut attributes = ThreadAttributeList::new(1)?;
+        attributes.set(PROC_THREAD_ATTRIBUTE_GROUP_AFFINITY, affinity)?;
+        Ok(Some(attributes))
+    }
+
+    /// Injects the DLL in `file` which was read from `dll_path` into `process`,
+    /// which was already opened and checked by `open_target`.
+    fn inject_file(&self, process: &Process, file: &[u8], dll_path: &str) -> Result<usize, Error> {
+        let execution_method = self.execution_method();
+
+        let base = match (&self.config.injection_method, execution_method) {
+            (InjectionMethod::ManualMap, _) => {
+                return manual_map::manual_map(
+                    process,
+                    file,
+                    execution_method,
+                    self.config.timeout,
+                    self.config.erase_headers,
+                );
+            },
+            (_, CodeExecutionMethod::CreateRemoteThread) => {
+                let mut attributes = self.thread_attributes()?;
+                load_library(process, dll_path, self.config.timeout, attributes.as_mut())?
+            },
+            (_, CodeExecutionMethod::ThreadHijack) => {
+                let load_library = get_proc_address(get_module_handle("Kernel32.dll")?, "LoadLibraryW")?;
+
+                let module = stub::call(
+                    process,
+                    execution_method,
+                    self.config.timeout,
+                    load_library,
+                    &[stub::Argument::WideString(dll_path)],
+                )?;
+                if module == 0 {
+                    return Err(Error::ModuleNotFound(dll_path.to_owned()));
+                }
+
+                Some(module as usize)
+            },
+        };
+
+        match base {
+            Some(base) if !self.config.wait_for_module => Ok(base),
+            _ => find_module(process, dll_path, self.config.wait_for_module, self.config.timeout),
+        }
+    }
+}
+
+/// The ids of every process named `name`, the comparison is case-insensitive.
+fn pids_by_name(name: &str) -> Result<Vec<u32>, Error> {
+    Ok(process::find_by_name(name)?.into_iter().map(|process| process.pid).collect())
+}
+
+/// Whether `error` can go away by itself if opening the target is attempted
+/// again. A process that was just created can fail to be opened, or have its
+/// memory read, until it finished initializing.
+const fn is_recoverable(error: &Error) -> bool {
+    matches!(error, Error::ProcessError(_) | Error::MemoryError(_) | Error::Handle(_))
+}
+
+/// Writes `dll` to a file in the temp directory with a name that no other
+/// injection uses and returns its path.
+fn write_temp_dll(dll: &[u8]) -> Result<PathBuf, Error> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let path = std::env::temp_dir().join(format!("mem-{}-{nanos}.dll", std::process::id()));
+
+    std::fs::write(&path, dll)?;
+    Ok(path)
+}
+
+/// Makes sure we and the DLL in `file` were built for the architecture of
+/// `process` before anything is done to the process.
+///
+/// Every injection method calls functions at our own addresses of
+/// `LoadLibraryW` and runs stubs built for our architecture, which can't work
+/// in a process of a different one even if the DLL matches it.
+fn check_architecture(process: &Process, file: &[u8]) -> Result<(), Error> {
+    let process = process.architecture()?;
+    if process != Architecture::HOST {
+        return Err(Error::ArchitectureMismatch {
+            dll: Architecture::HOST,
+            process,
+        });
+    }
+
+    let dll = PeHeaders::parse(file)?
+        .architecture()
+        .ok_or(Error::InvalidPe("the DLL isn't built for x86 or x64"))?;
+
+    if dll == process {
+        Ok(())
+    } else {
+        Err(Error::ArchitectureMismatch { dll, process })
+    }
+}
+
+/// Calls `LoadLibraryW` on a new thread in the target, which returns the
+/// `HMODULE` of the DLL as the exit code of the thread. The wide version is
+/// used so paths that aren't ASCII load too.
+///
+/// Exit codes are only 32 bits so the base address can only be taken from them
+/// for 32 bit targets, `None` is returned for 64 bit targets.
+///
+/// The thread is created with `CreateRemoteThreadEx` if there are `attributes`.
+fn load_library(
+    process: &Process,
+    dll_path: &str,
+    timeout: Option<Duration>,
+    attributes: Option<&mut ThreadAttributeList>,
+) -> Result<Option<usize>, Error> {
+    let load_library = get_proc_address(get_module_handle("Kernel32.dll")?, "LoadLibraryW")?;
+
+    let path: Vec<u8> = to_wide(dll_path).into_iter().flat_map(u16::to_le_bytes).collect();
+    let path = process.alloc_write(&path)?;
+
+    let result = match (attributes, timeout) {
+        (Some(attributes), _) => process.call_remote_with_attributes(load_library, path.address(), attributes, timeout),
+        (None, Some(timeout)) => process.call_remote_with_timeout(load_library, path.address(), timeout),
+        (None, None) => process.call_remote(load_library, path.address()),
+    };
+    let module = match result {
+        // The thread could still read the path, so leave it allocated
+        Err(Error::Timeout) => {
+            let _ = path.into_raw();
+            return Err(Error::Timeout);
+        },
+        result => result?,
+    };
+    if module == 0 {
+        return Err(Error::ModuleNotFound(dll_path.to_owned()));
+    }
+
+    match process.architecture()? {
+        Architecture::X86 => Ok(Some(module as usize)),
+        Architecture::X64 => Ok(None),
+    }
+}
+
+/// Looks up the base address of the DLL at `dll_path` in the module list of
+/// `process`. With `wait` the list is polled until the DLL shows up, for up to
+/// `timeout` unless it is `None`.
+fn find_module(process: &Process, dll_path: &str, wait: bool, timeout: Option<Duration>) -> Result<usize, Error> {
+    let name = Path::new(dll_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or(Error::DllPath)?;
+
+    let started = Instant::now();
+    loop {
+        match find_module_base(process, name) {
+            Ok(base) => return Ok(base),
+            Err(Error::ModuleNotFound(_)) if wait && timeout.is_none_or(|timeout| started.elapsed() < timeout) => {
+                std::thread::sleep(Duration::from_millis(10));
+            },
+            Err(Error::ModuleNotFound(_)) if wait => return Err(Error::Timeout),
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// The base address of the module named `name`. Toolhelp only has the names
+/// in the ANSI code page, so names that aren't ASCII are looked up in the
+/// loader's own list of modules instead.
+fn find_module_base(process: &Process, name: &str) -> Result<usize, Error> {
+    if name.is_ascii() {
+        return process.module(name).map(|module| module.base);
+    }
+
+    let lowercase = name.to_lowercase();
+    process
+        .modules_via_peb()?
+        .into_iter()
+        .find(|module| module.name.to_lowercase() == lowercase)
+        .map(|module| module.base)
+        .ok_or_else(|| Error::ModuleNotFound(name.to_owned()))
+}
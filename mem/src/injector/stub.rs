@@ -0,0 +1,234 @@
+//! `stub` runs functions inside of the target process. A small piece of
+//! shellcode is written into the target that calls the function with our
+//! arguments and stores the return value, which is then read back once the
+//! stub is done.
+use std::time::Duration;
+
+use windows::Win32::System::Memory::PAGE_EXECUTE_READWRITE;
+
+use super::{hijack, CodeExecutionMethod};
+use crate::{error::Error, process::Process, windows::utils::to_wide};
+
+/// The start of the stub allocation holds the return value of the function
+/// followed by the flag a hijacked thread sets once it is done.
+const RESULT_SIZE: usize = 8;
+const HEADER_SIZE: usize = RESULT_SIZE + 8;
+/// Large enough for the code of any stub we build.
+const MAX_CODE_SIZE: usize = 128;
+
+/// An argument passed to a function called with `call`.
+pub(super) enum Argument<'a> {
+    Value(u64),
+    /// A string that is copied into the target, the function receives a
+    /// pointer to a null terminated copy of it.
+    String(&'a str),
+    /// Like `String` but copied as UTF-16, for the `W` versions of windows
+    /// functions.
+    WideString(&'a str),
+}
+
+/// Calls `function` inside of `process` with `arguments` on a thread picked by
+/// `method` and returns whatever it returned.
+///
+/// The target is assumed to have the same architecture as us, on x86 the
+/// function has to be `stdcall`. `Error::Timeout` is returned if the function
+/// didn't return within `timeout`, `None` waits for as long as it takes.
+pub(super) fn call(
+    process: &Process,
+    method: CodeExecutionMethod,
+    timeout: Option<Duration>,
+    function: usize,
+    arguments: &[Argument],
+) -> Result<u64, Error> {
+    let strings_size: usize = arguments
+        .iter()
+        .map(|argument| match argument {
+            Argument::Value(_) => 0,
+            Argument::String(string) => string.len() + 1,
+            // One more byte in case the string has to be aligned
+            Argument::WideString(string) => (string.encode_utf16().count() + 1) * 2 + 1,
+        })
+        .sum();
+    let size = HEADER_SIZE + strings_size + MAX_CODE_SIZE;
+
+    let allocation = process.alloc(size, PAGE_EXECUTE_READWRITE)?;
+
+    let result = run(process, method, timeout, allocation.address(), function, arguments);
+
+    // If we gave up waiting the thread might still be using the stub
+    if matches!(result, Err(Error::Timeout)) {
+        let _ = allocation.into_raw();
+    }
+
+    result
+}
+
+fn run(
+    process: &Process,
+    method: CodeExecutionMethod,
+    timeout: Option<Duration>,
+    allocation: usize,
+    function: usize,
+    arguments: &[Argument],
+) -> Result<u64, Error> {
+    let mut buffer = vec![0; HEADER_SIZE];
+
+    let mut values = Vec::with_capacity(arguments.len());
+    for argument in arguments {
+        match argument {
+            Argument::Value(value) => values.push(*value),
+            Argument::String(string) => {
+                values.push((allocation + buffer.len()) as u64);
+                buffer.extend_from_slice(string.as_bytes());
+                buffer.push(0);
+            },
+            Argument::WideString(string) => {
+                if !buffer.len().is_multiple_of(2) {
+                    buffer.push(0);
+                }
+
+                values.push((allocation + buffer.len()) as u64);
+                buffer.extend(to_wide(string).into_iter().flat_map(u16::to_le_bytes));
+            },
+        }
+    }
+
+    let code = allocation + buffer.len();
+    let result = allocation as u64;
+    let done = (allocation + RESULT_SIZE) as u64;
+
+    match method {
+        CodeExecutionMethod::CreateRemoteThread => {
+            buffer.extend_from_slice(&thread_stub(function as u64, &values, result)?);
+            process.write_bytes(allocation, &buffer)?;
+
+            process.create_remote_thread_at(code, 0)?.join(timeout)?;
+        },
+        CodeExecutionMethod::ThreadHijack => {
+            buffer.extend_from_slice(&hijack_stub(function as u64, &values, result, done)?);
+            process.write_bytes(allocation, &buffer)?;
+
+            hijack::run(process, code, done as usize, timeout)?;
+        },
+    }
+
+    let mut result = [0; RESULT_SIZE];
+    process.read_bytes(allocation, &mut result)?;
+    Ok(u64::from_le_bytes(result))
+}
+
+/// Builds a thread start routine which calls `function` with `arguments` and
+/// stores its return value at `result`.
+#[cfg(target_arch = "x86_64")]
+fn thread_stub(function: u64, arguments: &[u64], result: u64) -> Result<Vec<u8>, Error> {
+    // sub rsp, 0x28 for the shadow space and to keep the stack aligned
+    let mut code = vec![0x48, 0x83, 0xEC, 0x28];
+    push_call(&mut code, function, arguments, result)?;
+    // xor eax, eax ; add rsp, 0x28 ; ret
+    code.extend_from_slice(&[0x31, 0xC0, 0x48, 0x83, 0xC4, 0x28, 0xC3]);
+
+    Ok(code)
+}
+
+/// Builds a stub for a hijacked thread which calls `function` with `arguments`,
+/// stores its return value at `result`, sets `done` and then spins until we
+/// restore the thread.
+#[cfg(target_arch = "x86_64")]
+fn hijack_stub(function: u64, arguments: &[u64], result: u64, done: u64) -> Result<Vec<u8>, Error> {
+    // The thread could have been stopped anywhere so we align the stack
+    // ourselves, and rsp, -16 ; sub rsp, 0x20
+    let mut code = vec![0x48, 0x83, 0xE4, 0xF0, 0x48, 0x83, 0xEC, 0x20];
+    push_call(&mut code, function, arguments, result)?;
+    // mov rax, done ; mov byte ptr [rax], 1
+    code.extend_from_slice(&[0x48, 0xB8]);
+    code.extend_from_slice(&done.to_le_bytes());
+    code.extend_from_slice(&[0xC6, 0x00, 0x01]);
+    // jmp $
+    code.extend_from_slice(&[0xEB, 0xFE]);
+
+    Ok(code)
+}
+
+/// Loads `arguments` into the argument registers, calls `function` and stores
+/// rax at `result`.
+#[cfg(target_arch = "x86_64")]
+fn push_call(code: &mut Vec<u8>, function: u64, arguments: &[u64], result: u64) -> Result<(), Error> {
+    // mov rcx, mov rdx, mov r8, mov r9
+    const REGISTERS: [[u8; 2]; 4] = [[0x48, 0xB9], [0x48, 0xBA], [0x49, 0xB8], [0x49, 0xB9]];
+
+    if arguments.len() > REGISTERS.len() {
+        return Err(Error::InvalidArgument("too many arguments for a remote call"));
+    }
+
+    for (register, argument) in REGISTERS.iter().zip(arguments) {
+        code.extend_from_slice(register);
+        code.extend_from_slice(&argument.to_le_bytes());
+    }
+
+    // mov rax, function
+    code.extend_from_slice(&[0x48, 0xB8]);
+    code.extend_from_slice(&function.to_le_bytes());
+    // call rax
+    code.extend_from_slice(&[0xFF, 0xD0]);
+    // mov [result], rax
+    code.extend_from_slice(&[0x48, 0xA3]);
+    code.extend_from_slice(&result.to_le_bytes());
+
+    Ok(())
+}
+
+/// Builds a thread start routine which calls `function` with `arguments` and
+/// stores its return value at `result`.
+#[cfg(target_arch = "x86")]
+fn thread_stub(function: u64, arguments: &[u64], result: u64) -> Result<Vec<u8>, Error> {
+    let mut code = Vec::new();
+    push_call(&mut code, function, arguments, result)?;
+    // xor eax, eax ; ret 4 to pop the thread parameter
+    code.extend_from_slice(&[0x31, 0xC0, 0xC2, 0x04, 0x00]);
+
+    Ok(code)
+}
+
+/// Builds a stub for a hijacked thread which calls `function` with `arguments`,
+/// stores its return value at `result`, sets `done` and then spins until we
+/// restore the thread.
+#[cfg(target_arch = "x86")]
+fn hijack_stub(function: u64, arguments: &[u64], result: u64, done: u64) -> Result<Vec<u8>, Error> {
+    // The thread could have been stopped anywhere so we align the stack
+    // ourselves, and esp, -16
+    let mut code = vec![0x83, 0xE4, 0xF0];
+    push_call(&mut code, function, arguments, result)?;
+    // mov dword ptr [done], 1
+    code.extend_from_slice(&[0xC7, 0x05]);
+    code.extend_from_slice(&(done as u32).to_le_bytes());
+    code.extend_from_slice(&1u32.to_le_bytes());
+    // jmp $
+    code.extend_from_slice(&[0xEB, 0xFE]);
+
+    Ok(code)
+}
+
+/// Pushes `arguments`, calls `function` and stores eax at `result`.
+#[cfg(target_arch = "x86")]
+fn push_call(code: &mut Vec<u8>, function: u64, arguments: &[u64], result: u64) -> Result<(), Error> {
+    if arguments.len() > 8 {
+        return Err(Error::InvalidArgument("too many arguments for a remote call"));
+    }
+
+    // stdcall arguments are pushed right to left and popped by the callee
+    for argument in arguments.iter().rev() {
+        code.push(0x68);
+        code.extend_from_slice(&(*argument as u32).to_le_bytes());
+    }
+
+    // mov eax, function
+    code.push(0xB8);
+    code.extend_from_slice(&(function as u32).to_le_bytes());
+    // call eax
+    code.extend_from_slice(&[0xFF, 0xD0]);
+    // mov [result], eax
+    code.push(0xA3);
+    code.extend_from_slice(&(result as u32).to_le_bytes());
+
+    Ok(())
+}
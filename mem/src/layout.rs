@@ -0,0 +1,161 @@
+//! `layout` maps the names of the fields of a structure in the target to their
+//! offsets.
+//!
+//! This keeps the offsets for a version of the target in one place, or in a
+//! data file, instead of scattered over constants.
+use std::{collections::HashMap, str::FromStr};
+
+use crate::error::Error;
+
+/// Checks at compile time that a `#[repr(C)]` mirror of a structure in the
+/// target has the size, alignment and field offsets it expects.
+///
+/// An edit that shifts a field then fails the build instead of reading
+/// garbage. The checks are constants, so they cost nothing at runtime in any
+/// build.
+///
+/// ```rust
+/// #[repr(C)]
+/// #[derive(Clone, Copy)]
+/// struct Player {
+///     vtable: u64,
+///     health: f32,
+///     armor: f32,
+///     position: [f32; 3],
+/// }
+///
+/// mem::assert_layout!(
+///     Player,
+///     size = 0x20,
+///     align = 8,
+///     health = 0x8,
+///     position = 0x10
+/// );
+/// ```
+///
+/// ```compile_fail
+/// #[repr(C)]
+/// struct Player {
+///     vtable: u64,
+///     health: f32,
+/// }
+///
+/// mem::assert_layout!(Player, size = 0x10, align = 8, health = 0xC);
+/// ```
+#[macro_export]
+macro_rules! assert_layout {
+    ($type:ty, size = $size:expr, align = $align:expr $(, $field:ident = $offset:expr)* $(,)?) => {
+        const _: () = {
+            assert!(
+                ::core::mem::size_of::<$type>() == $size,
+                concat!("the size of ", stringify!($type), " doesn't match")
+            );
+            assert!(
+                ::core::mem::align_of::<$type>() == $align,
+                concat!("the alignment of ", stringify!($type), " doesn't match")
+            );
+            $(
+                assert!(
+                    ::core::mem::offset_of!($type, $field) == $offset,
+                    concat!("the offset of ", stringify!($type), "::", stringify!($field), " doesn't match")
+                );
+            )*
+        };
+    };
+}
+
+/// The offsets of the fields of a structure, read from a process with
+/// `Process::read_field`.
+///
+/// Layouts are built with `with_field` and `insert`, or parsed from lines of
+/// `name = offset` without any dependencies:
+///
+/// ```
+/// use mem::layout::StructLayout;
+///
+/// let layout: StructLayout = "
+///     # Player as of version 1.2
+///     health = 0x100
+///     armor = 260
+/// "
+/// .parse()
+/// .unwrap();
+///
+/// assert_eq!(layout.field("health"), Some(0x100));
+/// assert_eq!(layout.field("armor"), Some(0x104));
+/// ```
+///
+/// That is only the subset of TOML with integers at the top level. With the
+/// `serde` feature a layout can also be deserialized from any format that has
+/// a map of field names to offsets, like a JSON object or a TOML table.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct StructLayout {
+    fields: HashMap<String, usize>,
+}
+
+impl StructLayout {
+    #[must_use]
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds the field `name` at `offset`, replacing it if it already exists.
+    #[must_use]
+    pub fn with_field(mut self, name: &str, offset: usize) -> Self {
+        self.insert(name, offset);
+        self
+    }
+
+    /// Adds the field `name` at `offset` and returns the offset it had
+    /// before, if it already existed.
+    pub fn insert(&mut self, name: &str, offset: usize) -> Option<usize> { self.fields.insert(name.to_owned(), offset) }
+
+    /// The offset of the field `name`.
+    #[must_use]
+    pub fn field(&self, name: &str) -> Option<usize> { self.fields.get(name).copied() }
+
+    /// Every field and its offset, in no particular order.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.fields.iter().map(|(name, offset)| (name.as_str(), *offset))
+    }
+
+    /// Parses a layout from lines of `name = offset`, the offsets can be
+    /// decimal or hex with a `0x` prefix. Empty lines and everything after a
+    /// `#` are ignored.
+    ///
+    /// # Errors
+    /// `Error::InvalidLayout` with the line that isn't a field, or whose
+    /// offset isn't a number.
+    pub fn parse(layout: &str) -> Result<Self, Error> {
+        let mut parsed = Self::new();
+
+        for line in layout.lines() {
+            let field = line.split('#').next().unwrap_or_default().trim();
+            if field.is_empty() {
+                continue;
+            }
+
+            let invalid = || Error::InvalidLayout(line.trim().to_owned());
+            let (name, offset) = field.split_once('=').ok_or_else(invalid)?;
+            let (name, offset) = (name.trim(), offset.trim());
+            if name.is_empty() {
+                return Err(invalid());
+            }
+
+            let offset = offset
+                .strip_prefix("0x")
+                .or_else(|| offset.strip_prefix("0X"))
+                .map_or_else(|| offset.parse(), |hex| usize::from_str_radix(hex, 16))
+                .map_err(|_| invalid())?;
+
+            parsed.insert(name, offset);
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl FromStr for StructLayout {
+    type Err = Error;
+
+    fn from_str(layout: &str) -> Result<Self, Self::Err> { Self::parse(layout) }
+}
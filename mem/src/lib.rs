@@ -15,6 +15,18 @@ compile_error!("Currently only windows is supported");
 #[cfg(target_os = "windows")]
 pub mod windows;
 
+pub mod disasm;
+
+pub mod layout;
+
+pub mod module;
+
+pub mod pe;
+
+pub mod process;
+
+pub mod scanner;
+
 #[cfg(feature = "external")]
 pub mod external;
 #[cfg(feature = "injector")]
@@ -22,8 +34,10 @@ pub mod injector;
 #[cfg(feature = "internal")]
 pub mod internal;
 
-// This just checks if the user wants any of the graphic libraries
+// Hooking our own process needs `internal`, the graphics hooks also need one of
+// the graphic libraries
 #[cfg(any(
+    feature = "internal",
     feature = "vulkan",
     feature = "opengl",
     feature = "d3d9",
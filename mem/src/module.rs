@@ -0,0 +1,63 @@
+//! `module` describes an executable or DLL that is loaded into a process.
+use crate::{
+    process::Process,
+    windows::{
+        utils::windows_string_to_string,
+        wrappers::{get_module_information, HModule, ModuleEntry32},
+    },
+};
+
+/// A module loaded into a process, usually created from `Process::modules` or
+/// `Process::module`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Module {
+    /// The address the module is loaded at in the process.
+    pub base: usize,
+    /// The size of the module in bytes.
+    pub size: usize,
+    /// The file name of the module, for example `kernel32.dll`.
+    pub name: String,
+    /// The full path to the module on disk.
+    pub path: String,
+}
+
+impl Module {
+    /// Whether `address` is inside of the module.
+    #[must_use]
+    pub const fn contains(&self, address: usize) -> bool { address >= self.base && address - self.base < self.size }
+
+    /// Replaces `size` with the image size the windows loader has for the
+    /// module, which the toolhelp snapshot the module came from sometimes
+    /// under reports. The size is left alone if it couldn't be retrieved.
+    #[must_use]
+    pub fn with_image_size(mut self, process: &Process) -> Self {
+        if let Ok(info) = get_module_information(process.handle(), self.base as HModule) {
+            self.size = self.size.max(info.size);
+        }
+
+        self
+    }
+}
+
+/// What `get_module_information` knows about a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModuleInfo {
+    /// The address the module is loaded at.
+    pub base: usize,
+    /// The size of the mapped image in bytes.
+    pub size: usize,
+    /// The address of the entry point, 0 if the module doesn't have one.
+    pub entry_point: usize,
+}
+
+impl From<&ModuleEntry32> for Module {
+    fn from(entry: &ModuleEntry32) -> Self {
+        Self {
+            base: entry.modBaseAddr as usize,
+            size: entry.modBaseSize as usize,
+            name: windows_string_to_string(&entry.szModule),
+            path: windows_string_to_string(&entry.szExePath),
+        }
+    }
+}
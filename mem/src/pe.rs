@@ -0,0 +1,799 @@
+//! `pe` parses the parts of portable executable images (exe and dll files)
+//! that we need for things like manual mapping.
+//!
+//! Everything in here works on plain byte slices so the same code can be used
+//! on a file read from disk, an image mapped into our own process, or an image
+//! that was read out of another process.
+use std::convert::TryFrom;
+
+use crate::{error::Error, process::Architecture};
+
+/// The machine type of a PE built for x86.
+pub const MACHINE_I386: u16 = 0x014C;
+/// The machine type of a PE built for x64.
+pub const MACHINE_AMD64: u16 = 0x8664;
+
+/// Index of the export directory in `PeHeaders::data_directories`.
+pub const DIRECTORY_EXPORT: usize = 0;
+/// Index of the import directory in `PeHeaders::data_directories`.
+pub const DIRECTORY_IMPORT: usize = 1;
+/// Index of the base relocation directory in `PeHeaders::data_directories`.
+pub const DIRECTORY_BASE_RELOCATION: usize = 5;
+/// Index of the TLS directory in `PeHeaders::data_directories`.
+pub const DIRECTORY_TLS: usize = 9;
+
+const DOS_SIGNATURE: u16 = 0x5A4D;
+const NT_SIGNATURE: u32 = 0x0000_4550;
+const OPTIONAL_HEADER_32: u16 = 0x010B;
+const OPTIONAL_HEADER_64: u16 = 0x020B;
+const SECTION_HEADER_SIZE: usize = 40;
+const IMPORT_DESCRIPTOR_SIZE: usize = 20;
+
+const RELOCATION_ABSOLUTE: u16 = 0;
+const RELOCATION_HIGH_LOW: u16 = 3;
+const RELOCATION_DIR64: u16 = 10;
+
+/// The location of one of the data directories of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DataDirectory {
+    pub virtual_address: u32,
+    pub size: u32,
+}
+
+/// A single section header of an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub virtual_address: u32,
+    pub virtual_size: u32,
+    pub pointer_to_raw_data: u32,
+    pub size_of_raw_data: u32,
+    pub characteristics: u32,
+}
+
+/// The headers of an image that we care about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeHeaders {
+    /// Either `MACHINE_I386` or `MACHINE_AMD64` for the images we support.
+    pub machine: u16,
+    /// Whether the image has a 64-bit optional header, which also means its
+    /// pointers are 8 bytes.
+    pub is_64: bool,
+    pub image_base: u64,
+    pub size_of_image: u32,
+    pub size_of_headers: u32,
+    pub address_of_entry_point: u32,
+    pub section_alignment: u32,
+    pub file_alignment: u32,
+    pub data_directories: Vec<DataDirectory>,
+    pub sections: Vec<Section>,
+}
+
+impl PeHeaders {
+    /// Parses the headers at the start of `image`.
+    ///
+    /// # Errors
+    /// `Error::InvalidPe` if `image` doesn't start with valid headers.
+    pub fn parse(image: &[u8]) -> Result<Self, Error> {
+        if read_u16(image, 0)? != DOS_SIGNATURE {
+            return Err(Error::InvalidPe("missing DOS signature"));
+        }
+
+        let nt_headers = read_u32(image, 0x3C)? as usize;
+        if read_u32(image, nt_headers)? != NT_SIGNATURE {
+            return Err(Error::InvalidPe("missing NT signature"));
+        }
+
+        let file_header = nt_headers + 4;
+        let machine = read_u16(image, file_header)?;
+        let number_of_sections = read_u16(image, file_header + 2)? as usize;
+        let size_of_optional_header = read_u16(image, file_header + 16)? as usize;
+
+        let optional_header = file_header + 20;
+        let is_64 = match read_u16(image, optional_header)? {
+            OPTIONAL_HEADER_32 => false,
+            OPTIONAL_HEADER_64 => true,
+            _ => return Err(Error::InvalidPe("unknown optional header magic")),
+        };
+
+        let image_base = if is_64 {
+            read_u64(image, optional_header + 24)?
+        } else {
+            u64::from(read_u32(image, optional_header + 28)?)
+        };
+
+        let (number_of_directories, directories) = if is_64 {
+            (read_u32(image, optional_header + 108)?, optional_header + 112)
+        } else {
+            (read_u32(image, optional_header + 92)?, optional_header + 96)
+        };
+
+        let data_directories = (0..number_of_directories.min(16) as usize)
+            .map(|index| {
+                Ok(DataDirectory {
+                    virtual_address: read_u32(image, directories + index * 8)?,
+                    size: read_u32(image, directories + index * 8 + 4)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let section_headers = optional_header + size_of_optional_header;
+        let sections = (0..number_of_sections)
+            .map(|index| {
+                let header = section_headers + index * SECTION_HEADER_SIZE;
+                let name = bytes(image, header, 8)?;
+                let name_length = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+
+                Ok(Section {
+                    name: String::from_utf8_lossy(&name[..name_length]).into_owned(),
+                    virtual_size: read_u32(image, header + 8)?,
+                    virtual_address: read_u32(image, header + 12)?,
+                    size_of_raw_data: read_u32(image, header + 16)?,
+                    pointer_to_raw_data: read_u32(image, header + 20)?,
+                    characteristics: read_u32(image, header + 36)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self {
+            machine,
+            is_64,
+            image_base,
+            size_of_image: read_u32(image, optional_header + 56)?,
+            size_of_headers: read_u32(image, optional_header + 60)?,
+            address_of_entry_point: read_u32(image, optional_header + 16)?,
+            section_alignment: read_u32(image, optional_header + 32)?,
+            file_alignment: read_u32(image, optional_header + 36)?,
+            data_directories,
+            sections,
+        })
+    }
+
+    /// The data directory at `index`, `None` if the image doesn't have it.
+    #[must_use]
+    pub fn directory(&self, index: usize) -> Option<DataDirectory> {
+        self.data_directories
+            .get(index)
+            .copied()
+            .filter(|directory| directory.virtual_address != 0 && directory.size != 0)
+    }
+
+    /// The size of a pointer inside of the image.
+    #[must_use]
+    pub const fn pointer_size(&self) -> usize {
+        if self.is_64 {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// The architecture the image was built for, `None` if it isn't x86 or
+    /// x64.
+    #[must_use]
+    pub const fn architecture(&self) -> Option<Architecture> {
+        match self.machine {
+            MACHINE_I386 => Some(Architecture::X86),
+            MACHINE_AMD64 => Some(Architecture::X64),
+            _ => None,
+        }
+    }
+}
+
+/// How an imported function is looked up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportName {
+    Name(String),
+    Ordinal(u16),
+}
+
+/// A single function imported by an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    /// The name of the module the function is imported from.
+    pub module: String,
+    pub function: ImportName,
+    /// The RVA of the import address table entry the resolved address has to
+    /// be written to.
+    pub thunk: u32,
+}
+
+/// Every function imported by a mapped `image`.
+///
+/// # Errors
+/// `Error::InvalidPe` if the import directory is malformed.
+pub fn imports(image: &[u8], headers: &PeHeaders) -> Result<Vec<Import>, Error> {
+    let Some(directory) = headers.directory(DIRECTORY_IMPORT) else {
+        return Ok(Vec::new());
+    };
+
+    let pointer_size = headers.pointer_size();
+    let ordinal_flag = 1u64 << (pointer_size * 8 - 1);
+
+    let mut imports = Vec::new();
+    let mut descriptor = directory.virtual_address as usize;
+    loop {
+        let name = read_u32(image, descriptor + 12)?;
+        if name == 0 {
+            break;
+        }
+
+        let module = read_c_string(image, name as usize)?;
+        let first_thunk = read_u32(image, descriptor + 16)?;
+        // Some linkers leave the lookup table out, the IAT has the same
+        // contents before it is bound so we can read from that instead
+        let lookup_table = match read_u32(image, descriptor)? {
+            0 => first_thunk,
+            lookup_table => lookup_table,
+        };
+
+        for index in 0.. {
+            let lookup = read_pointer(image, lookup_table as usize + index * pointer_size, headers.is_64)?;
+            if lookup == 0 {
+                break;
+            }
+
+            let function = if lookup & ordinal_flag == 0 {
+                // Skip the hint in front of the name
+                ImportName::Name(read_c_string(image, (lookup & 0x7FFF_FFFF) as usize + 2)?)
+            } else {
+                ImportName::Ordinal((lookup & 0xFFFF) as u16)
+            };
+
+            imports.push(Import {
+                module: module.clone(),
+                function,
+                thunk: first_thunk + (index * pointer_size) as u32,
+            });
+        }
+
+        descriptor += IMPORT_DESCRIPTOR_SIZE;
+    }
+
+    Ok(imports)
+}
+
+/// A single function exported by an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    /// Functions can be exported by ordinal only, those don't have a name.
+    pub name: Option<String>,
+    pub ordinal: u16,
+    /// The RVA of the function.
+    pub rva: u32,
+    /// Exports that are forwarded to another module, like
+    /// `NTDLL.RtlAllocateHeap`, have the forwarder string instead of code
+    /// at their RVA.
+    pub forwarder: Option<String>,
+}
+
+/// Every function exported by a mapped image.
+///
+/// `image` doesn't have to be the whole image, it only has to start at the RVA
+/// `image_rva` and cover the export directory, which is exactly what reading
+/// just the export directory out of another process gives you.
+///
+/// # Errors
+/// `Error::InvalidPe` if the export directory is malformed.
+pub fn exports(image: &[u8], image_rva: u32, directory: DataDirectory) -> Result<Vec<Export>, Error> {
+    let offset = |rva: u32| {
+        rva.checked_sub(image_rva)
+            .map(|offset| offset as usize)
+            .ok_or(Error::InvalidPe("RVA is outside of the buffer"))
+    };
+
+    let directory_end = directory
+        .virtual_address
+        .checked_add(directory.size)
+        .ok_or(Error::InvalidPe("export directory is outside of the address space"))?;
+    let directory_range = directory.virtual_address..directory_end;
+
+    let export_directory = offset(directory.virtual_address)?;
+    let ordinal_base = read_u32(image, export_directory + 16)?;
+    let number_of_functions = read_u32(image, export_directory + 20)? as usize;
+    let number_of_names = read_u32(image, export_directory + 24)? as usize;
+    // The counts come straight from the image, so the tables they describe
+    // have to be in the buffer before anything is allocated for them
+    let functions = table(
+        image,
+        offset(read_u32(image, export_directory + 28)?)?,
+        number_of_functions,
+        4,
+    )?;
+    let names = table(image, offset(read_u32(image, export_directory + 32)?)?, number_of_names, 4)?;
+    let name_ordinals = table(image, offset(read_u32(image, export_directory + 36)?)?, number_of_names, 2)?;
+
+    let mut function_names = vec![None; number_of_functions];
+    for index in 0..number_of_names {
+        let function = read_u16(name_ordinals, index * 2)? as usize;
+        let name = offset(read_u32(names, index * 4)?)?;
+        if let Some(slot) = function_names.get_mut(function) {
+            *slot = Some(read_c_string(image, name)?);
+        }
+    }
+
+    let mut exports = Vec::with_capacity(number_of_functions);
+    for (index, name) in function_names.into_iter().enumerate() {
+        let rva = read_u32(functions, index * 4)?;
+        if rva == 0 {
+            continue;
+        }
+
+        // A RVA that points back inside of the export directory is a forwarder
+        let forwarder = if directory_range.contains(&rva) {
+            Some(read_c_string(image, offset(rva)?)?)
+        } else {
+            None
+        };
+
+        exports.push(Export {
+            name,
+            ordinal: (ordinal_base as usize + index) as u16,
+            rva,
+            forwarder,
+        });
+    }
+
+    Ok(exports)
+}
+
+/// Applies the base relocations of a mapped `image` so it can run at
+/// `new_base` instead of its preferred image base.
+///
+/// # Errors
+/// `Error::InvalidPe` if the relocation directory is malformed.
+pub fn relocate(image: &mut [u8], headers: &PeHeaders, new_base: u64) -> Result<(), Error> {
+    let delta = new_base.wrapping_sub(headers.image_base);
+    if delta == 0 {
+        return Ok(());
+    }
+
+    let Some(directory) = headers.directory(DIRECTORY_BASE_RELOCATION) else {
+        return Err(Error::InvalidPe("image can't be relocated"));
+    };
+
+    let mut block = directory.virtual_address as usize;
+    let end = block + directory.size as usize;
+    while block < end {
+        let page = read_u32(image, block)? as usize;
+        let block_size = read_u32(image, block + 4)? as usize;
+        if block_size < 8 {
+            return Err(Error::InvalidPe("relocation block is too small"));
+        }
+
+        for entry in (block + 8..block + block_size).step_by(2) {
+            let entry = read_u16(image, entry)?;
+            let address = page + (entry & 0x0FFF) as usize;
+
+            match entry >> 12 {
+                RELOCATION_ABSOLUTE => {},
+                RELOCATION_HIGH_LOW => {
+                    let value = read_u32(image, address)?.wrapping_add(delta as u32);
+                    bytes_mut(image, address, 4)?.copy_from_slice(&value.to_le_bytes());
+                },
+                RELOCATION_DIR64 => {
+                    let value = read_u64(image, address)?.wrapping_add(delta);
+                    bytes_mut(image, address, 8)?.copy_from_slice(&value.to_le_bytes());
+                },
+                _ => return Err(Error::InvalidPe("unsupported relocation type")),
+            }
+        }
+
+        block += block_size;
+    }
+
+    Ok(())
+}
+
+/// Maps a raw file `file` into a buffer laid out the way the loader would lay
+/// it out in memory, headers first and then every section at its RVA.
+///
+/// # Errors
+/// `Error::InvalidPe` if a section lies outside of the file or image.
+pub fn map_sections(file: &[u8], headers: &PeHeaders) -> Result<Vec<u8>, Error> {
+    let mut image = vec![0; headers.size_of_image as usize];
+
+    let size_of_headers = headers.size_of_headers as usize;
+    bytes_mut(&mut image, 0, size_of_headers)?.copy_from_slice(bytes(file, 0, size_of_headers)?);
+
+    for section in &headers.sections {
+        // Uninitialized data like .bss has a virtual size but nothing in the
+        // file
+        let size = match section.virtual_size {
+            0 => section.size_of_raw_data,
+            virtual_size => section.size_of_raw_data.min(virtual_size),
+        } as usize;
+
+        if size == 0 {
+            continue;
+        }
+
+        let raw_data = bytes(file, section.pointer_to_raw_data as usize, size)?;
+        bytes_mut(&mut image, section.virtual_address as usize, size)?.copy_from_slice(raw_data);
+    }
+
+    Ok(image)
+}
+
+/// Turns an image that was mapped into memory back into the layout of a file
+/// on disk, the inverse of `map_sections`.
+///
+/// Every section is copied from its virtual address to its raw data pointer,
+/// so the result can be opened by tools that expect a file, like a
+/// disassembler.
+///
+/// # Errors
+/// `Error::InvalidPe` if a section is outside of `image`.
+pub fn unmap_sections(image: &[u8], headers: &PeHeaders) -> Result<Vec<u8>, Error> {
+    let size_of_headers = headers.size_of_headers as usize;
+    let file_size = headers
+        .sections
+        .iter()
+        .map(|section| section.pointer_to_raw_data as usize + section.size_of_raw_data as usize)
+        .fold(size_of_headers, usize::max);
+
+    let mut file = vec![0; file_size];
+    bytes_mut(&mut file, 0, size_of_headers)?.copy_from_slice(bytes(image, 0, size_of_headers)?);
+
+    for section in &headers.sections {
+        // The raw data is padded to the file alignment, which can be more than
+        // the section has in memory
+        let size = match section.virtual_size {
+            0 => section.size_of_raw_data,
+            virtual_size => section.size_of_raw_data.min(virtual_size),
+        } as usize;
+
+        if size == 0 {
+            continue;
+        }
+
+        let data = bytes(image, section.virtual_address as usize, size)?;
+        bytes_mut(&mut file, section.pointer_to_raw_data as usize, size)?.copy_from_slice(data);
+    }
+
+    Ok(file)
+}
+
+/// Overwrites the `ImageBase` in the optional header of `image`, for example
+/// after the image was relocated to `image_base`.
+///
+/// # Errors
+/// `Error::InvalidPe` if `image` doesn't start with valid headers.
+pub fn set_image_base(image: &mut [u8], image_base: u64) -> Result<(), Error> {
+    let is_64 = PeHeaders::parse(image)?.is_64;
+    let optional_header = read_u32(image, 0x3C)? as usize + 24;
+
+    if is_64 {
+        bytes_mut(image, optional_header + 24, 8)?.copy_from_slice(&image_base.to_le_bytes());
+    } else {
+        let image_base = u32::try_from(image_base).map_err(|_| Error::InvalidPe("image base is out of range"))?;
+        bytes_mut(image, optional_header + 28, 4)?.copy_from_slice(&image_base.to_le_bytes());
+    }
+
+    Ok(())
+}
+
+/// Reads a pointer sized value from a image with the given pointer size.
+pub(crate) fn read_pointer(image: &[u8], offset: usize, is_64: bool) -> Result<u64, Error> {
+    if is_64 {
+        read_u64(image, offset)
+    } else {
+        read_u32(image, offset).map(u64::from)
+    }
+}
+
+pub(crate) fn read_u16(image: &[u8], offset: usize) -> Result<u16, Error> {
+    let mut value = [0; 2];
+    value.copy_from_slice(bytes(image, offset, 2)?);
+    Ok(u16::from_le_bytes(value))
+}
+
+pub(crate) fn read_u32(image: &[u8], offset: usize) -> Result<u32, Error> {
+    let mut value = [0; 4];
+    value.copy_from_slice(bytes(image, offset, 4)?);
+    Ok(u32::from_le_bytes(value))
+}
+
+pub(crate) fn read_u64(image: &[u8], offset: usize) -> Result<u64, Error> {
+    let mut value = [0; 8];
+    value.copy_from_slice(bytes(image, offset, 8)?);
+    Ok(u64::from_le_bytes(value))
+}
+
+/// Reads a null terminated string starting at `offset`.
+pub(crate) fn read_c_string(image: &[u8], offset: usize) -> Result<String, Error> {
+    let string = image
+        .get(offset..)
+        .ok_or(Error::InvalidPe("string is outside of the image"))?;
+    let length = string
+        .iter()
+        .position(|&c| c == 0)
+        .ok_or(Error::InvalidPe("string isn't null terminated"))?;
+
+    Ok(String::from_utf8_lossy(&string[..length]).into_owned())
+}
+
+/// The `count` entries of `entry_size` bytes each of a table at `offset`.
+fn table(image: &[u8], offset: usize, count: usize, entry_size: usize) -> Result<&[u8], Error> {
+    let length = count
+        .checked_mul(entry_size)
+        .ok_or(Error::InvalidPe("table is outside of the image"))?;
+
+    bytes(image, offset, length)
+}
+
+fn bytes(image: &[u8], offset: usize, length: usize) -> Result<&[u8], Error> {
+    offset
+        .checked_add(length)
+        .and_then(|end| image.get(offset..end))
+        .ok_or(Error::InvalidPe("read is outside of the image"))
+}
+
+fn bytes_mut(image: &mut [u8], offset: usize, length: usize) -> Result<&mut [u8], Error> {
+    offset
+        .checked_add(length)
+        .and_then(move |end| image.get_mut(offset..end))
+        .ok_or(Error::InvalidPe("write is outside of the image"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAGE_BASE: u64 = 0x1_4000_0000;
+    const NT_HEADERS: usize = 0x40;
+    const OPTIONAL_HEADER: usize = NT_HEADERS + 24;
+    const SECTION_HEADERS: usize = OPTIONAL_HEADER + 0xF0;
+    const EXPORTS: DataDirectory = DataDirectory {
+        virtual_address: 0x2100,
+        size: 0x80,
+    };
+
+    fn put(image: &mut [u8], offset: usize, value: &[u8]) { image[offset..offset + value.len()].copy_from_slice(value) }
+
+    fn put_u16(image: &mut [u8], offset: usize, value: u16) { put(image, offset, &value.to_le_bytes()) }
+
+    fn put_u32(image: &mut [u8], offset: usize, value: u32) { put(image, offset, &value.to_le_bytes()) }
+
+    fn put_u64(image: &mut [u8], offset: usize, value: u64) { put(image, offset, &value.to_le_bytes()) }
+
+    fn put_section(image: &mut [u8], index: usize, name: &[u8], virtual_address: u32, virtual_size: u32, raw: u32) {
+        let header = SECTION_HEADERS + index * SECTION_HEADER_SIZE;
+        put(image, header, name);
+        put_u32(image, header + 8, virtual_size);
+        put_u32(image, header + 12, virtual_address);
+        put_u32(image, header + 16, 0x200);
+        put_u32(image, header + 20, raw);
+    }
+
+    /// A mapped x64 DLL with a `.text` section holding a pointer that has to
+    /// be relocated, and a `.rdata` section with the import, export and
+    /// relocation directories:
+    ///
+    /// - imports `Sleep` by name and ordinal 7 from `kernel32.dll`
+    /// - exports `run` as ordinal 1 and forwards ordinal 2 to `NTDLL.Sleep`
+    fn image() -> Vec<u8> {
+        let mut image = vec![0; 0x3000];
+
+        put_u16(&mut image, 0, DOS_SIGNATURE);
+        put_u32(&mut image, 0x3C, NT_HEADERS as u32);
+        put_u32(&mut image, NT_HEADERS, NT_SIGNATURE);
+        put_u16(&mut image, NT_HEADERS + 4, MACHINE_AMD64);
+        put_u16(&mut image, NT_HEADERS + 6, 2);
+        put_u16(&mut image, NT_HEADERS + 20, 0xF0);
+
+        put_u16(&mut image, OPTIONAL_HEADER, OPTIONAL_HEADER_64);
+        put_u32(&mut image, OPTIONAL_HEADER + 16, 0x1000);
+        put_u64(&mut image, OPTIONAL_HEADER + 24, IMAGE_BASE);
+        put_u32(&mut image, OPTIONAL_HEADER + 32, 0x1000);
+        put_u32(&mut image, OPTIONAL_HEADER + 36, 0x200);
+        put_u32(&mut image, OPTIONAL_HEADER + 56, 0x3000);
+        put_u32(&mut image, OPTIONAL_HEADER + 60, 0x200);
+        put_u32(&mut image, OPTIONAL_HEADER + 108, 16);
+        let directories = OPTIONAL_HEADER + 112;
+        for (index, rva, size) in [
+            (DIRECTORY_EXPORT, 0x2100, 0x80),
+            (DIRECTORY_IMPORT, 0x2000, 0x28),
+            (DIRECTORY_BASE_RELOCATION, 0x2180, 0x0C),
+        ] {
+            put_u32(&mut image, directories + index * 8, rva);
+            put_u32(&mut image, directories + index * 8 + 4, size);
+        }
+
+        put_section(&mut image, 0, b".text", 0x1000, 0x10, 0x200);
+        put_section(&mut image, 1, b".rdata", 0x2000, 0x18C, 0x400);
+
+        // ret, followed by a pointer to itself
+        image[0x1000] = 0xC3;
+        put_u64(&mut image, 0x1008, IMAGE_BASE + 0x1000);
+
+        // One import descriptor and the null one that ends the list
+        put_u32(&mut image, 0x2000, 0x2040);
+        put_u32(&mut image, 0x2000 + 12, 0x2080);
+        put_u32(&mut image, 0x2000 + 16, 0x2060);
+        for table in [0x2040, 0x2060] {
+            put_u64(&mut image, table, 0x2090);
+            put_u64(&mut image, table + 8, (1 << 63) | 7);
+        }
+        put(&mut image, 0x2080, b"kernel32.dll\0");
+        put(&mut image, 0x2092, b"Sleep\0");
+
+        put_u32(&mut image, 0x2100 + 16, 1);
+        put_u32(&mut image, 0x2100 + 20, 2);
+        put_u32(&mut image, 0x2100 + 24, 1);
+        put_u32(&mut image, 0x2100 + 28, 0x2130);
+        put_u32(&mut image, 0x2100 + 32, 0x2140);
+        put_u32(&mut image, 0x2100 + 36, 0x2144);
+        put_u32(&mut image, 0x2130, 0x1000);
+        put_u32(&mut image, 0x2134, 0x2150);
+        put_u32(&mut image, 0x2140, 0x2160);
+        put_u16(&mut image, 0x2144, 0);
+        put(&mut image, 0x2150, b"NTDLL.Sleep\0");
+        put(&mut image, 0x2160, b"run\0");
+
+        // A DIR64 relocation of the pointer and an absolute one as padding
+        put_u32(&mut image, 0x2180, 0x1000);
+        put_u32(&mut image, 0x2184, 0x0C);
+        put_u16(&mut image, 0x2188, (RELOCATION_DIR64 << 12) | 0x008);
+
+        image
+    }
+
+    #[test]
+    fn parses_headers() {
+        let headers = PeHeaders::parse(&image()).unwrap();
+
+        assert!(headers.is_64);
+        assert_eq!(headers.architecture(), Some(Architecture::X64));
+        assert_eq!(headers.pointer_size(), 8);
+        assert_eq!(headers.image_base, IMAGE_BASE);
+        assert_eq!(headers.size_of_image, 0x3000);
+        assert_eq!(headers.size_of_headers, 0x200);
+        assert_eq!(headers.address_of_entry_point, 0x1000);
+        assert_eq!(headers.data_directories.len(), 16);
+        assert_eq!(headers.directory(DIRECTORY_EXPORT), Some(EXPORTS));
+        assert_eq!(headers.directory(DIRECTORY_TLS), None);
+
+        let names: Vec<&str> = headers.sections.iter().map(|section| section.name.as_str()).collect();
+        assert_eq!(names, [".text", ".rdata"]);
+        assert_eq!(headers.sections[1].virtual_address, 0x2000);
+        assert_eq!(headers.sections[1].pointer_to_raw_data, 0x400);
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let invalid = |image: &[u8]| matches!(PeHeaders::parse(image), Err(Error::InvalidPe(_)));
+
+        assert!(invalid(&[]));
+        assert!(invalid(&image()[..0x100]));
+
+        let mut image = image();
+        put_u16(&mut image, OPTIONAL_HEADER, 0x1234);
+        assert!(invalid(&image));
+
+        put_u32(&mut image, 0x3C, u32::MAX);
+        assert!(invalid(&image));
+
+        put_u32(&mut image, 0x3C, NT_HEADERS as u32 + 4);
+        assert!(invalid(&image));
+
+        put_u16(&mut image, 0, 0);
+        assert!(invalid(&image));
+    }
+
+    #[test]
+    fn relocates_to_a_new_base() {
+        let mut image = image();
+        let headers = PeHeaders::parse(&image).unwrap();
+
+        relocate(&mut image, &headers, IMAGE_BASE).unwrap();
+        assert_eq!(read_u64(&image, 0x1008).unwrap(), IMAGE_BASE + 0x1000);
+
+        relocate(&mut image, &headers, 0x7FF0_0000_0000).unwrap();
+        assert_eq!(read_u64(&image, 0x1008).unwrap(), 0x7FF0_0000_1000);
+
+        // A block that claims to be smaller than its own header
+        put_u32(&mut image, 0x2184, 4);
+        assert!(matches!(
+            relocate(&mut image, &headers, 0x1000_0000),
+            Err(Error::InvalidPe(_))
+        ));
+
+        // Without relocations the image can only run at its preferred base
+        let mut headers = headers;
+        headers.data_directories[DIRECTORY_BASE_RELOCATION] = DataDirectory::default();
+        relocate(&mut image, &headers, IMAGE_BASE).unwrap();
+        assert!(matches!(
+            relocate(&mut image, &headers, 0x1000_0000),
+            Err(Error::InvalidPe(_))
+        ));
+    }
+
+    #[test]
+    fn reads_imports() {
+        let image = image();
+        let headers = PeHeaders::parse(&image).unwrap();
+
+        let imports = imports(&image, &headers).unwrap();
+        assert_eq!(
+            imports,
+            [
+                Import {
+                    module: "kernel32.dll".to_owned(),
+                    function: ImportName::Name("Sleep".to_owned()),
+                    thunk: 0x2060,
+                },
+                Import {
+                    module: "kernel32.dll".to_owned(),
+                    function: ImportName::Ordinal(7),
+                    thunk: 0x2068,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_exports() {
+        let image = image();
+        let expected = [
+            Export {
+                name: Some("run".to_owned()),
+                ordinal: 1,
+                rva: 0x1000,
+                forwarder: None,
+            },
+            Export {
+                name: None,
+                ordinal: 2,
+                rva: 0x2150,
+                forwarder: Some("NTDLL.Sleep".to_owned()),
+            },
+        ];
+
+        assert_eq!(exports(&image, 0, EXPORTS).unwrap(), expected);
+        // Just the export directory, like it is read out of another process
+        assert_eq!(exports(&image[0x2100..0x2180], 0x2100, EXPORTS).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_malformed_exports() {
+        let invalid = |image: &[u8], directory| matches!(exports(image, 0, directory), Err(Error::InvalidPe(_)));
+
+        let overflowing = DataDirectory {
+            virtual_address: EXPORTS.virtual_address,
+            size: u32::MAX,
+        };
+        assert!(invalid(&image(), overflowing));
+
+        // Counts that don't fit in the buffer are rejected before anything is
+        // allocated for them
+        let mut image = image();
+        put_u32(&mut image, 0x2100 + 20, u32::MAX);
+        assert!(invalid(&image, EXPORTS));
+
+        put_u32(&mut image, 0x2100 + 20, 2);
+        put_u32(&mut image, 0x2100 + 24, 0x1000_0000);
+        assert!(invalid(&image, EXPORTS));
+    }
+
+    #[test]
+    fn unmaps_and_maps_sections() {
+        let image = image();
+        let headers = PeHeaders::parse(&image).unwrap();
+
+        let file = unmap_sections(&image, &headers).unwrap();
+        assert_eq!(file.len(), 0x600);
+        assert_eq!(file[..0x200], image[..0x200]);
+        assert_eq!(file[0x200..0x210], image[0x1000..0x1010]);
+        assert_eq!(file[0x400..0x58C], image[0x2000..0x218C]);
+
+        assert_eq!(map_sections(&file, &headers).unwrap(), image);
+
+        // A section whose raw data is past the end of the file
+        let mut headers = headers;
+        headers.sections[1].pointer_to_raw_data = 0x1_0000;
+        assert!(matches!(map_sections(&file, &headers), Err(Error::InvalidPe(_))));
+        headers.sections[1].virtual_address = 0x1_0000;
+        assert!(matches!(unmap_sections(&image, &headers), Err(Error::InvalidPe(_))));
+    }
+}
@@ -0,0 +1,83 @@
+use super::{MemoryRegion, Process, Protection};
+use crate::{
+    error::Error,
+    windows::wrappers::{virtual_protect_ex, PageProtectionFlags, LPVOID},
+};
+
+/// Reading, writing and querying memory of a process, implemented by `Process`
+/// for another process and by `LocalProcess` for our own.
+///
+/// Tools that only need these can be written once against `impl MemoryAccess`
+/// and used both from an injected DLL and from an external program.
+///
+/// ```ignore
+/// fn health(memory: &impl MemoryAccess, player: usize) -> Result<u32, Error> {
+///     memory.read::<u32>(player + 0x100)
+/// }
+/// ```
+pub trait MemoryAccess {
+    /// Reads `buffer.len()` bytes at `address` into `buffer`.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if not all of the bytes could be read.
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<(), Error>;
+
+    /// Writes all of `bytes` to `address`.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if not all of the bytes could be written.
+    fn write_bytes(&self, address: usize, bytes: &[u8]) -> Result<(), Error>;
+
+    /// Reads a `T` from `address`, whatever bytes are there are interpreted as
+    /// a `T` so this is meant for plain old data types.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the read fails or `T` is zero sized.
+    fn read<T: Copy>(&self, address: usize) -> Result<T, Error>;
+
+    /// Writes `value` to `address`.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the write fails or `T` is zero sized.
+    fn write<T: Copy>(&self, address: usize, value: &T) -> Result<(), Error>;
+
+    /// Changes the protection of `size` bytes at `address` to `protection` and
+    /// returns the raw `PAGE_*` flags the first page had before. Use a
+    /// `ProtectGuard` instead if the protection should be put back afterwards.
+    ///
+    /// # Errors
+    /// `Error::Allocation` or `Error::MemoryError` if the protection couldn't
+    /// be changed.
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<PageProtectionFlags, Error>;
+
+    /// Queries the region of pages `address` is in.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the region couldn't be queried.
+    fn query(&self, address: usize) -> Result<MemoryRegion, Error>;
+}
+
+impl MemoryAccess for Process {
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<(), Error> { Self::read_bytes(self, address, buffer) }
+
+    fn write_bytes(&self, address: usize, bytes: &[u8]) -> Result<(), Error> { Self::write_bytes(self, address, bytes) }
+
+    fn read<T: Copy>(&self, address: usize) -> Result<T, Error> { Self::read(self, address) }
+
+    fn write<T: Copy>(&self, address: usize, value: &T) -> Result<(), Error> { Self::write(self, address, value) }
+
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<PageProtectionFlags, Error> {
+        let mut old_protection = 0;
+        virtual_protect_ex(
+            self.open_handle()?,
+            address as LPVOID,
+            size,
+            protection.flags(),
+            &raw mut old_protection,
+        )?;
+
+        Ok(old_protection)
+    }
+
+    fn query(&self, address: usize) -> Result<MemoryRegion, Error> { Self::query(self, address) }
+}
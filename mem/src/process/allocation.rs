@@ -0,0 +1,311 @@
+use windows::Win32::{
+    Foundation::ERROR_INVALID_PARAMETER,
+    System::Memory::{
+        MEM_COMMIT, MEM_RELEASE, MEM_RESERVE, PAGE_EXECUTE, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE,
+        PAGE_EXECUTE_WRITECOPY, PAGE_NOACCESS, PAGE_READONLY, PAGE_READWRITE, PAGE_WRITECOPY,
+    },
+};
+
+use super::{MemoryAccess, Process};
+use crate::{
+    error::Error,
+    windows::wrappers::{virtual_alloc_ex, virtual_free_ex, PageProtectionFlags, LPVOID},
+};
+
+/// Allocations always start on a multiple of this.
+const GRANULARITY: usize = 0x1_0000;
+/// A bit less than 2GB so the end of an allocation is in reach of a rel32 too.
+const REACH: usize = 0x7FF0_0000;
+
+/// The access allowed to a range of pages, these map to the `PAGE_*`
+/// constants so you don't have to import them from windows-rs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protection {
+    NoAccess,
+    ReadOnly,
+    ReadWrite,
+    /// Writing to the pages gives the process its own copy of them.
+    WriteCopy,
+    Execute,
+    ReadExecute,
+    ExecuteReadWrite,
+    ExecuteWriteCopy,
+}
+
+impl Protection {
+    /// The `PAGE_*` constant windows uses for the protection.
+    #[must_use]
+    pub const fn flags(self) -> PageProtectionFlags {
+        match self {
+            Self::NoAccess => PAGE_NOACCESS,
+            Self::ReadOnly => PAGE_READONLY,
+            Self::ReadWrite => PAGE_READWRITE,
+            Self::WriteCopy => PAGE_WRITECOPY,
+            Self::Execute => PAGE_EXECUTE,
+            Self::ReadExecute => PAGE_EXECUTE_READ,
+            Self::ExecuteReadWrite => PAGE_EXECUTE_READWRITE,
+            Self::ExecuteWriteCopy => PAGE_EXECUTE_WRITECOPY,
+        }
+    }
+}
+
+impl From<Protection> for PageProtectionFlags {
+    fn from(protection: Protection) -> Self { protection.flags() }
+}
+
+/// Memory allocated inside of a process, it is freed again once the
+/// `RemoteAlloc` is dropped.
+///
+/// Created with `Process::allocate`, `Process::alloc`, `Process::alloc_write`
+/// or `Process::alloc_write_with_protection`. `Process::reserve` only reserves
+/// the addresses, parts of it are committed later with `commit`.
+pub struct RemoteAlloc<'a> {
+    process: &'a Process,
+    address: usize,
+    size: usize,
+    /// The protection pages get once they are committed.
+    protection: PageProtectionFlags,
+}
+
+impl RemoteAlloc<'_> {
+    /// The address of the allocation inside of the process.
+    #[must_use]
+    pub const fn address(&self) -> usize { self.address }
+
+    /// The size that was requested for the allocation, windows rounds this up
+    /// to a multiple of the page size.
+    #[must_use]
+    pub const fn size(&self) -> usize { self.size }
+
+    /// Writes `bytes` into the allocation starting `offset` bytes in.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the bytes don't fit into the allocation or
+    /// couldn't be written.
+    pub fn write(&self, offset: usize, bytes: &[u8]) -> Result<(), Error> {
+        match offset.checked_add(bytes.len()) {
+            Some(end) if end <= self.size => self.process.write_bytes(self.address + offset, bytes),
+            _ => Err(Error::MemoryError(ERROR_INVALID_PARAMETER)),
+        }
+    }
+
+    /// Commits `size` bytes starting `offset` bytes into the allocation, with
+    /// the protection it was created with. The pages are zeroed the first
+    /// time they are committed, committing them again keeps their contents.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the range doesn't fit into the allocation or
+    /// couldn't be committed.
+    pub fn commit(&self, offset: usize, size: usize) -> Result<(), Error> {
+        match offset.checked_add(size) {
+            Some(end) if end <= self.size => {
+                let address = (self.address + offset) as LPVOID;
+                virtual_alloc_ex(self.process.open_handle()?, Some(address), size, MEM_COMMIT, self.protection)?;
+                Ok(())
+            },
+            _ => Err(Error::MemoryError(ERROR_INVALID_PARAMETER)),
+        }
+    }
+
+    /// Whether all of the allocation can be reached with a rel32 jump or call
+    /// from `from`. If it can't a 14 byte absolute `jmp [rip]` is needed
+    /// instead.
+    #[must_use]
+    pub const fn is_within_reach(&self, from: usize) -> bool {
+        self.address.abs_diff(from) <= REACH && (self.address + self.size).abs_diff(from) <= REACH
+    }
+
+    /// Gives up ownership of the allocation without freeing it and returns its
+    /// address, the memory stays allocated for the life of the process.
+    #[must_use]
+    pub const fn into_raw(self) -> usize {
+        let address = self.address;
+        std::mem::forget(self);
+        address
+    }
+}
+
+impl Drop for RemoteAlloc<'_> {
+    fn drop(&mut self) {
+        // The process could have exited already in which case there is nothing
+        // left to free
+        let _ = virtual_free_ex(self.process.handle(), self.address as LPVOID, 0, MEM_RELEASE);
+    }
+}
+
+impl Process {
+    /// Allocates `size` zeroed bytes inside of the process with `protection`.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the memory couldn't be allocated.
+    pub fn allocate(&self, size: usize, protection: Protection) -> Result<RemoteAlloc<'_>, Error> {
+        self.alloc(size, protection.flags())
+    }
+
+    /// Like `allocate` but takes the raw `PAGE_*` flags.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the memory couldn't be allocated.
+    pub fn alloc(&self, size: usize, protection: PageProtectionFlags) -> Result<RemoteAlloc<'_>, Error> {
+        let address = virtual_alloc_ex(self.open_handle()?, None, size, MEM_COMMIT | MEM_RESERVE, protection)? as usize;
+
+        Ok(RemoteAlloc {
+            process: self,
+            address,
+            size,
+            protection,
+        })
+    }
+
+    /// Reserves `size` bytes of addresses inside of the process without
+    /// committing any memory to them, so large buffers only cost memory for
+    /// the parts that are actually used. Pages are committed with
+    /// `RemoteAlloc::commit` and get `protection` once they are.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the addresses couldn't be reserved.
+    pub fn reserve(&self, size: usize, protection: Protection) -> Result<RemoteAlloc<'_>, Error> {
+        let address = virtual_alloc_ex(self.open_handle()?, None, size, MEM_RESERVE, PAGE_NOACCESS)? as usize;
+
+        Ok(RemoteAlloc {
+            process: self,
+            address,
+            size,
+            protection: protection.flags(),
+        })
+    }
+
+    /// Commits `size` bytes at `address`, which have to be part of a region
+    /// that was reserved before, with `protection`.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the pages aren't reserved or couldn't be
+    /// committed.
+    pub fn commit(&self, address: usize, size: usize, protection: Protection) -> Result<(), Error> {
+        virtual_alloc_ex(
+            self.open_handle()?,
+            Some(address as LPVOID),
+            size,
+            MEM_COMMIT,
+            protection.flags(),
+        )?;
+        Ok(())
+    }
+
+    /// Reserves and commits `size` bytes in one go, which is the same as
+    /// `allocate`.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the memory couldn't be allocated.
+    pub fn reserve_and_commit(&self, size: usize, protection: Protection) -> Result<RemoteAlloc<'_>, Error> {
+        self.allocate(size, protection)
+    }
+
+    /// Allocates `size` bytes in the first free region within reach of a rel32
+    /// from `target`, which is what hooks and code caves need to jump back and
+    /// forth with 5 byte jumps.
+    ///
+    /// If no free region is close enough the memory is allocated wherever
+    /// windows puts it instead, check `RemoteAlloc::is_within_reach(target)`
+    /// and use a 14 byte absolute jump when it returns false.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the memory couldn't be allocated at all.
+    pub fn allocate_near(&self, target: usize, size: usize, protection: Protection) -> Result<RemoteAlloc<'_>, Error> {
+        let handle = self.open_handle()?;
+        let near = find_near(self, target, size, |address| {
+            virtual_alloc_ex(
+                handle,
+                Some(address as LPVOID),
+                size,
+                MEM_COMMIT | MEM_RESERVE,
+                protection.flags(),
+            )
+            .ok()
+            .map(|address| address as usize)
+        });
+
+        near.map_or_else(
+            || self.allocate(size, protection),
+            |address| {
+                Ok(RemoteAlloc {
+                    process: self,
+                    address,
+                    size,
+                    protection: protection.flags(),
+                })
+            },
+        )
+    }
+
+    /// Allocates readable and writable memory inside of the process and copies
+    /// `data` into it, which is handy for passing strings to remote functions.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the memory couldn't be allocated or written.
+    pub fn alloc_write(&self, data: &[u8]) -> Result<RemoteAlloc<'_>, Error> {
+        self.alloc_write_with_protection(data, PAGE_READWRITE)
+    }
+
+    /// Like `alloc_write` but with a custom `protection`, for example
+    /// `PAGE_EXECUTE_READWRITE` for shellcode.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the memory couldn't be allocated or written.
+    pub fn alloc_write_with_protection(
+        &self,
+        data: &[u8],
+        protection: PageProtectionFlags,
+    ) -> Result<RemoteAlloc<'_>, Error> {
+        let allocation = self.alloc(data.len(), protection)?;
+        self.write_bytes(allocation.address(), data)?;
+
+        Ok(allocation)
+    }
+}
+
+/// Walks the free regions of `memory` within reach of a rel32 from `target`,
+/// above it first and below it second, and returns the first address
+/// `try_alloc` managed to allocate `size` bytes at.
+pub fn find_near(
+    memory: &impl MemoryAccess,
+    target: usize,
+    size: usize,
+    mut try_alloc: impl FnMut(usize) -> Option<usize>,
+) -> Option<usize> {
+    let highest = target.saturating_add(REACH);
+    let mut current = target;
+    while current < highest {
+        let Ok(region) = memory.query(current) else {
+            break;
+        };
+
+        let candidate = region.base.next_multiple_of(GRANULARITY);
+        if !region.is_committed && candidate < region.end() && candidate < highest {
+            if let Some(address) = try_alloc(candidate) {
+                return Some(address);
+            }
+        }
+
+        current = region.end();
+    }
+
+    let lowest = target.saturating_sub(REACH).max(GRANULARITY);
+    let mut current = target;
+    while current > lowest {
+        let Ok(region) = memory.query(current - 1) else {
+            break;
+        };
+
+        let candidate = region.end().saturating_sub(size) / GRANULARITY * GRANULARITY;
+        if !region.is_committed && candidate >= region.base && candidate >= lowest {
+            if let Some(address) = try_alloc(candidate) {
+                return Some(address);
+            }
+        }
+
+        current = region.base;
+    }
+
+    None
+}
@@ -0,0 +1,59 @@
+use super::Process;
+use crate::{
+    error::Error,
+    windows::wrappers::{get_current_process, is_wow64_process},
+};
+
+/// The instruction set a process runs on, which decides how large its pointers
+/// are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Architecture {
+    X86,
+    X64,
+}
+
+impl Architecture {
+    /// The architecture this crate was compiled for.
+    #[cfg(target_pointer_width = "64")]
+    pub const HOST: Self = Self::X64;
+    #[cfg(target_pointer_width = "32")]
+    pub const HOST: Self = Self::X86;
+
+    /// The size of a pointer in bytes.
+    #[must_use]
+    pub const fn pointer_size(self) -> usize {
+        match self {
+            Self::X86 => 4,
+            Self::X64 => 8,
+        }
+    }
+}
+
+impl Process {
+    /// Whether the process is a 32 bit process running on 64 bit windows.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the handle doesn't have
+    /// `PROCESS_QUERY_LIMITED_INFORMATION` access.
+    pub fn is_wow64(&self) -> Result<bool, Error> { is_wow64_process(self.open_handle()?) }
+
+    /// The architecture the process runs on.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the handle doesn't have
+    /// `PROCESS_QUERY_LIMITED_INFORMATION` access.
+    pub fn architecture(&self) -> Result<Architecture, Error> {
+        if self.is_wow64()? {
+            return Ok(Architecture::X86);
+        }
+
+        // A process that isn't running under WOW64 has the architecture of the
+        // system, which is only 64 bit for a 32 bit build if we ourselves run
+        // under WOW64
+        match Architecture::HOST {
+            Architecture::X64 => Ok(Architecture::X64),
+            Architecture::X86 if is_wow64_process(get_current_process())? => Ok(Architecture::X64),
+            Architecture::X86 => Ok(Architecture::X86),
+        }
+    }
+}
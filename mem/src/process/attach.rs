@@ -0,0 +1,46 @@
+use super::{Access, Process};
+use crate::{error::Error, windows::utils::find_process_by_window};
+
+/// How `Process::attach` finds the process to open.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// The process with this id.
+    Pid(u32),
+    /// The first process whose executable name matches, the comparison is
+    /// case-insensitive.
+    Name(String),
+    /// The process that owns the top level window with this title.
+    Window(String),
+}
+
+impl From<u32> for Target {
+    fn from(pid: u32) -> Self { Self::Pid(pid) }
+}
+
+impl From<&str> for Target {
+    fn from(name: &str) -> Self { Self::Name(name.to_owned()) }
+}
+
+impl Process {
+    /// Finds the process described by `target` and opens it with
+    /// `Access::read_write()`, which is what most tools need.
+    ///
+    /// ```ignore
+    /// let game = Process::attach(Target::Window("Untitled - Notepad".to_owned()))?;
+    /// let by_name = Process::attach("notepad.exe")?;
+    /// ```
+    ///
+    /// # Errors
+    /// `Error::ProcessNotFound` if there is no such process,
+    /// `Error::AccessDenied` if we aren't allowed to open it, or
+    /// `Error::ProcessError` if it couldn't be opened for any other reason.
+    pub fn attach(target: impl Into<Target>) -> Result<Self, Error> {
+        let access = Access::read_write();
+
+        match target.into() {
+            Target::Pid(pid) => Self::open_by_pid(pid, access),
+            Target::Name(name) => Self::open_by_name(&name, access),
+            Target::Window(title) => Self::open_by_pid(find_process_by_window(&title)?, access),
+        }
+    }
+}
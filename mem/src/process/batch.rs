@@ -0,0 +1,86 @@
+use super::Process;
+use crate::error::Error;
+
+/// Requests that are at most this far apart are merged into a single read, the
+/// bytes in between are read and thrown away.
+const MAX_GAP: usize = 64;
+/// Spans are never merged past this size so one bad page doesn't make us read
+/// a huge region twice.
+const MAX_SPAN: usize = 0x4000;
+
+/// A single read for `Process::read_batch`, the bytes at `address` are read
+/// into all of `buffer`.
+pub struct ReadRequest<'a> {
+    pub address: usize,
+    pub buffer: &'a mut [u8],
+}
+
+impl<'a> ReadRequest<'a> {
+    #[must_use]
+    pub const fn new(address: usize, buffer: &'a mut [u8]) -> Self { Self { address, buffer } }
+
+    /// The number of bytes that will be read.
+    #[must_use]
+    pub const fn size(&self) -> usize { self.buffer.len() }
+}
+
+impl Process {
+    /// Fills the buffer of every request, returning the result of each read in
+    /// the same order as `requests`.
+    ///
+    /// Requests that are close to each other are merged into a single
+    /// `ReadProcessMemory` call, which is a lot faster than reading every value
+    /// on its own. If a merged read fails every request in it is read on its
+    /// own instead, so a single unreadable address only fails its own request.
+    pub fn read_batch(&self, requests: &mut [ReadRequest]) -> Vec<Result<(), Error>> {
+        let mut results: Vec<Result<(), Error>> = requests.iter().map(|_| Ok(())).collect();
+
+        let mut order: Vec<usize> = (0..requests.len()).collect();
+        order.sort_by_key(|&index| requests[index].address);
+
+        let mut span_buffer = Vec::new();
+        let mut start = 0;
+        while start < order.len() {
+            let span_start = requests[order[start]].address;
+            let mut span_end = span_start + requests[order[start]].size();
+
+            let mut end = start + 1;
+            while end < order.len() {
+                let request = &requests[order[end]];
+                let request_end = request.address + request.size();
+                if request.address > span_end + MAX_GAP || request_end.max(span_end) - span_start > MAX_SPAN {
+                    break;
+                }
+
+                span_end = span_end.max(request_end);
+                end += 1;
+            }
+
+            let span = &order[start..end];
+            if span.len() == 1 {
+                let request = &mut requests[span[0]];
+                results[span[0]] = self.read_bytes(request.address, request.buffer);
+            } else {
+                span_buffer.resize(span_end - span_start, 0);
+
+                if self.read_bytes(span_start, &mut span_buffer).is_ok() {
+                    for &index in span {
+                        let request = &mut requests[index];
+                        let offset = request.address - span_start;
+                        let size = request.size();
+                        request.buffer.copy_from_slice(&span_buffer[offset..offset + size]);
+                    }
+                } else {
+                    for &index in span {
+                        let request = &mut requests[index];
+                        results[index] = self.read_bytes(request.address, request.buffer);
+                    }
+                }
+            }
+
+            start = end;
+        }
+
+        results
+    }
+}
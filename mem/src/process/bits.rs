@@ -0,0 +1,55 @@
+use super::Process;
+use crate::error::Error;
+
+impl Process {
+    /// Whether bit `bit` of the byte at `address` is set, bit 0 is the least
+    /// significant one.
+    ///
+    /// # Errors
+    /// `Error::InvalidArgument` if `bit` is 8 or more, or
+    /// `Error::MemoryError` if the byte couldn't be read.
+    pub fn read_bit(&self, address: usize, bit: u8) -> Result<bool, Error> {
+        let mask = bit_mask(bit)?;
+
+        Ok(self.read::<u8>(address)? & mask != 0)
+    }
+
+    /// Sets bit `bit` of the byte at `address` to `value` without touching
+    /// the other bits, for flag fields that pack several flags into a byte.
+    ///
+    /// The byte is read, changed and written back, so the other bits can
+    /// still be overwritten if the process changes them in between. Pages
+    /// that aren't writable are handled like in `write_protected`.
+    ///
+    /// # Errors
+    /// `Error::InvalidArgument` if `bit` is 8 or more, or any error
+    /// `write_protected` returns.
+    pub fn set_bit(&self, address: usize, bit: u8, value: bool) -> Result<(), Error> {
+        let mask = bit_mask(bit)?;
+        let byte = self.read::<u8>(address)?;
+
+        let new = if value { byte | mask } else { byte & !mask };
+        if new == byte {
+            return Ok(());
+        }
+
+        self.write_protected(address, &new)
+    }
+
+    /// Flips bit `bit` of the byte at `address` and returns whether it is set
+    /// now, see `set_bit`.
+    ///
+    /// # Errors
+    /// Any error `set_bit` returns.
+    pub fn toggle_bit(&self, address: usize, bit: u8) -> Result<bool, Error> {
+        let value = !self.read_bit(address, bit)?;
+        self.set_bit(address, bit, value)?;
+
+        Ok(value)
+    }
+}
+
+fn bit_mask(bit: u8) -> Result<u8, Error> {
+    1_u8.checked_shl(u32::from(bit))
+        .ok_or(Error::InvalidArgument("a byte only has bits 0 to 7"))
+}
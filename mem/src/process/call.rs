@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use windows::Win32::System::Memory::PAGE_EXECUTE_READWRITE;
+
+use super::{MemoryAccess, Process, Protection, RemoteThread};
+use crate::{
+    error::Error,
+    windows::{
+        attributes::ThreadAttributeList,
+        handle::SafeHandle,
+        wrappers::{
+            create_remote_thread, create_remote_thread_ex, flush_instruction_cache, get_module_handle, get_proc_address,
+            ProcThreadAttributeList, DWORD, LPCVOID, LPVOID,
+        },
+    },
+};
+
+impl Process {
+    /// Runs `function` on a new thread inside of the process with `argument` as
+    /// its only parameter and returns the exit code of the thread, which is the
+    /// return value of `function` cut down to 32 bits.
+    ///
+    /// `function` must have the signature of a thread start routine, for
+    /// example `LoadLibraryA` or `FreeLibrary`. This waits for the thread for
+    /// as long as it takes, see `call_remote_with_timeout`.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the thread couldn't be created.
+    pub fn call_remote(&self, function: usize, argument: usize) -> Result<u32, Error> {
+        self.call_remote_inner(function, argument, None, None)
+    }
+
+    /// Like `call_remote` but gives up waiting for the thread after `timeout`.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the thread couldn't be created or
+    /// `Error::Timeout` if the thread didn't exit in time, the thread is left
+    /// running in that case.
+    pub fn call_remote_with_timeout(&self, function: usize, argument: usize, timeout: Duration) -> Result<u32, Error> {
+        self.call_remote_inner(function, argument, None, Some(timeout))
+    }
+
+    /// Like `call_remote_with_timeout` but creates the thread with
+    /// `CreateRemoteThreadEx` and the attributes in `attributes`, for example
+    /// to pin it to certain processors. A `timeout` of `None` waits for as long
+    /// as it takes.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the thread couldn't be created or
+    /// `Error::Timeout` if the thread didn't exit in time, the thread is left
+    /// running in that case.
+    pub fn call_remote_with_attributes(
+        &self,
+        function: usize,
+        argument: usize,
+        attributes: &mut ThreadAttributeList,
+        timeout: Option<Duration>,
+    ) -> Result<u32, Error> {
+        self.call_remote_inner(function, argument, Some(attributes.as_raw()), timeout)
+    }
+
+    /// Copies `code` into executable memory inside of the process and runs it
+    /// on a new thread with `argument` as its parameter, returning the exit
+    /// code of the thread once it is done.
+    ///
+    /// `code` is called like a thread start routine, so it has to return with
+    /// `ret` on x64 and `ret 4` on x86. The memory is freed once the thread
+    /// exits or if anything fails before it started.
+    ///
+    /// The memory is never writable and executable at the same time, it is
+    /// written while it is read write and then made read execute, which is
+    /// what processes that enforce W^X require. Use `execute_shellcode_rwx`
+    /// for code that modifies itself.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the memory couldn't be allocated, written or
+    /// protected, or `Error::ProcessError` if the thread couldn't be created.
+    pub fn execute_shellcode(&self, code: &[u8], argument: Option<usize>) -> Result<u32, Error> {
+        let shellcode = self.alloc_write(code)?;
+        MemoryAccess::protect(self, shellcode.address(), code.len(), Protection::ReadExecute)?;
+        flush_instruction_cache(self.open_handle()?, shellcode.address() as LPCVOID, code.len())?;
+
+        self.call_remote(shellcode.address(), argument.unwrap_or(0))
+    }
+
+    /// Like `execute_shellcode` but the memory stays readable, writable and
+    /// executable while the code runs, for code that modifies itself.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the memory couldn't be allocated or written, or
+    /// `Error::ProcessError` if the thread couldn't be created.
+    pub fn execute_shellcode_rwx(&self, code: &[u8], argument: Option<usize>) -> Result<u32, Error> {
+        let shellcode = self.alloc_write_with_protection(code, PAGE_EXECUTE_READWRITE)?;
+
+        self.call_remote(shellcode.address(), argument.unwrap_or(0))
+    }
+
+    /// Unloads the module named `module_name` from the process by calling
+    /// `FreeLibrary` on it from a new thread, which is how a DLL that was
+    /// injected with `LoadLibrary` is ejected again.
+    ///
+    /// The address of `FreeLibrary` is taken from our own `kernel32.dll`, so
+    /// the process has to have the same architecture as us. A module that was
+    /// loaded more than once is only unloaded once every load was freed.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have the module or
+    /// `FreeLibrary` failed inside of it, or `Error::ProcessError` if the
+    /// thread couldn't be created.
+    pub fn free_library(&self, module_name: &str) -> Result<(), Error> {
+        let (base, _) = self.module_bounds(module_name)?;
+        let free_library = get_proc_address(get_module_handle("kernel32.dll")?, "FreeLibrary")?;
+
+        // FreeLibrary returns a BOOL
+        if self.call_remote(free_library, base)? == 0 {
+            return Err(Error::ModuleNotFound(module_name.to_owned()));
+        }
+
+        Ok(())
+    }
+
+    /// Creates a thread inside of the process that starts at `address` with
+    /// `argument` as its parameter without waiting for it, for when the thread
+    /// should keep running or be joined later.
+    ///
+    /// `address` must be the address of a thread start routine inside of the
+    /// process, like one returned from `get_proc_address`.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the thread couldn't be created.
+    pub fn create_remote_thread_at(&self, address: usize, argument: usize) -> Result<RemoteThread, Error> {
+        self.spawn_remote(address, argument, None)
+    }
+
+    /// Like `call_remote` but waits for the thread on tokio's blocking thread
+    /// pool instead of blocking the calling thread.
+    ///
+    /// The thread is created right away, the returned future only waits for
+    /// it to exit.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the thread couldn't be created.
+    #[cfg(feature = "tokio")]
+    pub fn call_remote_async(
+        &self,
+        function: usize,
+        argument: usize,
+    ) -> impl std::future::Future<Output = Result<u32, Error>> {
+        let thread = self.spawn_remote(function, argument, None);
+
+        async move {
+            let thread = thread?;
+
+            // The task only fails if waiting panicked
+            let exit_code = tokio::task::spawn_blocking(move || thread.join(None))
+                .await
+                .map_err(std::io::Error::from)??;
+
+            Ok(exit_code)
+        }
+    }
+
+    fn call_remote_inner(
+        &self,
+        function: usize,
+        argument: usize,
+        attributes: Option<ProcThreadAttributeList>,
+        timeout: Option<Duration>,
+    ) -> Result<u32, Error> {
+        self.spawn_remote(function, argument, attributes)?.join(timeout)
+    }
+
+    /// Creates a thread inside of the process that runs `function` with
+    /// `argument`, `CreateRemoteThreadEx` is only used if there are
+    /// `attributes`.
+    fn spawn_remote(
+        &self,
+        function: usize,
+        argument: usize,
+        attributes: Option<ProcThreadAttributeList>,
+    ) -> Result<RemoteThread, Error> {
+        let process = self.open_handle()?;
+        let thread = unsafe {
+            let start_routine: unsafe extern "system" fn(LPVOID) -> DWORD = std::mem::transmute(function);
+            let handle = attributes.map_or_else(
+                || create_remote_thread(process, None, 0, Some(start_routine), Some(argument as LPVOID), 0, None),
+                |attributes| {
+                    create_remote_thread_ex(
+                        process,
+                        None,
+                        0,
+                        Some(start_routine),
+                        Some(argument as LPVOID),
+                        0,
+                        Some(attributes),
+                        None,
+                    )
+                },
+            );
+
+            SafeHandle::owned(handle?)
+        };
+
+        Ok(RemoteThread::new(thread))
+    }
+}
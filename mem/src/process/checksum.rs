@@ -0,0 +1,88 @@
+use super::Process;
+use crate::error::Error;
+
+/// The lookup table of the reflected CRC-32 polynomial used by zip and png.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 { crc >> 1 } else { (crc >> 1) ^ 0xEDB8_8320 };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+impl Process {
+    /// The CRC-32 of the `.text` section of the module named `module_name`,
+    /// or of its whole image if it doesn't have one. Compare it against the
+    /// checksum of the build your offsets were made for with
+    /// `verify_module_checksum`.
+    ///
+    /// Patches and inline hooks change the code, so take the checksum before
+    /// changing anything.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have the module,
+    /// `Error::MemoryError` if the module couldn't be read, or
+    /// `Error::InvalidPe` if its headers are broken.
+    pub fn module_checksum(&self, module_name: &str) -> Result<u32, Error> {
+        let (base, size) = self.module_bounds(module_name)?;
+        let headers = self.read_headers(base, size)?;
+
+        let (offset, size) = headers
+            .sections
+            .iter()
+            .find(|section| section.name == ".text")
+            .map_or((0, headers.size_of_image as usize), |section| {
+                (section.virtual_address as usize, section.virtual_size as usize)
+            });
+
+        let mut code = vec![0; size];
+        self.read_bytes(base + offset, &mut code)?;
+
+        Ok(crc32(&code))
+    }
+
+    /// Checks that `module_checksum` of the module named `module_name` is
+    /// `expected`, so tools can refuse to run against a version of the target
+    /// their offsets weren't made for.
+    ///
+    /// # Errors
+    /// `Error::VersionMismatch` if the checksum is different, or any error of
+    /// `module_checksum`.
+    pub fn verify_module_checksum(&self, module_name: &str, expected: u32) -> Result<(), Error> {
+        let found = self.module_checksum(module_name)?;
+        if found != expected {
+            return Err(Error::VersionMismatch {
+                module: module_name.to_owned(),
+                expected,
+                found,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        CRC32_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_known_vectors() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+}
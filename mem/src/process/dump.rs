@@ -0,0 +1,189 @@
+use std::{io::Write, mem::size_of, path::Path};
+
+use super::Process;
+use crate::{
+    error::Error,
+    pe::{self, PeHeaders},
+};
+
+const PAGE_SIZE: usize = 0x1000;
+/// How many bytes `hex_dump` puts on every line.
+const BYTES_PER_LINE: usize = 16;
+
+impl Process {
+    /// Writes the memory of the module named `module_name` to `out_path`
+    /// exactly the way it is laid out in memory.
+    ///
+    /// Pages that can't be read are written as zeroes. Use
+    /// `dump_module_realigned` to get a file that can be loaded again.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have the module or
+    /// `Error::Os` if the file couldn't be written.
+    pub fn dump_module(&self, module_name: &str, out_path: &Path) -> Result<(), Error> {
+        let module = self.module(module_name)?;
+        let image = self.read_image(module.base, module.size);
+
+        std::fs::write(out_path, image)?;
+        Ok(())
+    }
+
+    /// Like `dump_module` but moves every section from its memory alignment
+    /// back to its file alignment using the section headers, so the dump is a
+    /// valid PE file.
+    ///
+    /// The image base in the headers is set to the address the module was
+    /// loaded at since the dumped code was already relocated to it.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have the module,
+    /// `Error::InvalidPe` if the headers of the module are missing or broken,
+    /// or `Error::Os` if the file couldn't be written.
+    pub fn dump_module_realigned(&self, module_name: &str, out_path: &Path) -> Result<(), Error> {
+        let module = self.module(module_name)?;
+        let image = self.read_image(module.base, module.size);
+
+        let headers = PeHeaders::parse(&image)?;
+        let mut file = pe::unmap_sections(&image, &headers)?;
+        pe::set_image_base(&mut file, module.base as u64)?;
+
+        std::fs::write(out_path, file)?;
+        Ok(())
+    }
+
+    /// Formats `len` bytes at `address` as a classic hex dump, with the address
+    /// of every line, 16 bytes in hex and the same bytes as ASCII.
+    ///
+    /// ```text
+    /// 00007FF6A1B21000: 48 8B 05 F9 3F 00 00 48 89 5C 24 08 E8 00 00 00  H...?..H.\$.....
+    /// ```
+    ///
+    /// Bytes in pages that can't be read are shown as `??`. Use `dump_to` to
+    /// write large dumps somewhere without building the whole `String`.
+    ///
+    /// # Errors
+    /// `Error::InvalidArgument` if the range wraps around the address space,
+    /// or `Error::ProcessClosed` or `Error::ProcessExited` if there is no
+    /// process to read from.
+    pub fn hex_dump(&self, address: usize, len: usize) -> Result<String, Error> {
+        let mut dump = Vec::new();
+        self.dump_to(address, len, &mut dump)?;
+
+        Ok(String::from_utf8_lossy(&dump).into_owned())
+    }
+
+    /// Like `hex_dump` but writes the lines to `writer` as they are read.
+    ///
+    /// # Errors
+    /// The errors of `hex_dump`, or `Error::Os` if writing to `writer` fails.
+    pub fn dump_to(&self, address: usize, len: usize, mut writer: impl Write) -> Result<(), Error> {
+        let end = address
+            .checked_add(len)
+            .ok_or(Error::InvalidArgument("the range wraps around the address space"))?;
+
+        // The page the last byte was in and its bytes, if it could be read
+        let mut page: Option<(usize, Option<Vec<u8>>)> = None;
+        let mut line = [None; BYTES_PER_LINE];
+        let mut line_start = address;
+        while line_start < end {
+            let line_len = (end - line_start).min(BYTES_PER_LINE);
+            for (offset, byte) in line[..line_len].iter_mut().enumerate() {
+                let byte_address = line_start + offset;
+                let page_base = byte_address - byte_address % PAGE_SIZE;
+                if page.as_ref().map(|(base, _)| *base) != Some(page_base) {
+                    let mut buffer = vec![0; PAGE_SIZE];
+                    let bytes = match self.read_bytes(page_base, &mut buffer) {
+                        Ok(()) => Some(buffer),
+                        Err(error @ (Error::ProcessClosed | Error::ProcessExited)) => return Err(error),
+                        Err(_) => None,
+                    };
+                    page = Some((page_base, bytes));
+                }
+
+                *byte = page
+                    .as_ref()
+                    .and_then(|(_, bytes)| bytes.as_ref())
+                    .map(|bytes| bytes[byte_address - page_base]);
+            }
+
+            write_line(&mut writer, line_start, &line[..line_len])?;
+            line_start += line_len;
+        }
+
+        Ok(())
+    }
+
+    /// Reads `size` bytes at `base`, pages that can't be read are left zeroed.
+    fn read_image(&self, base: usize, size: usize) -> Vec<u8> {
+        let mut image = vec![0; size];
+        if self.read_bytes(base, &mut image).is_ok() {
+            return image;
+        }
+
+        for offset in (0..size).step_by(PAGE_SIZE) {
+            let end = (offset + PAGE_SIZE).min(size);
+            if self.read_bytes(base + offset, &mut image[offset..end]).is_err() {
+                image[offset..end].fill(0);
+            }
+        }
+
+        image
+    }
+}
+
+/// Writes a single line of a hex dump, `None` is a byte that couldn't be read.
+fn write_line(writer: &mut impl Write, address: usize, bytes: &[Option<u8>]) -> std::io::Result<()> {
+    write!(writer, "{:0width$X}:", address, width = size_of::<usize>() * 2)?;
+    for index in 0..BYTES_PER_LINE {
+        match bytes.get(index) {
+            Some(Some(byte)) => write!(writer, " {byte:02X}")?,
+            Some(None) => write!(writer, " ??")?,
+            // Keeps the ASCII of a short last line lined up with the others
+            None => write!(writer, "   ")?,
+        }
+    }
+
+    write!(writer, "  ")?;
+    for byte in bytes {
+        let character = match byte {
+            Some(byte) if byte.is_ascii_graphic() || *byte == b' ' => char::from(*byte),
+            _ => '.',
+        };
+        write!(writer, "{character}")?;
+    }
+
+    writeln!(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::Access;
+
+    /// The address column is as wide as a pointer.
+    fn address(address: usize) -> String { format!("{:0width$X}", address, width = size_of::<usize>() * 2) }
+
+    #[test]
+    fn short_last_line_keeps_the_columns_lined_up() {
+        let mut line = Vec::new();
+        write_line(&mut line, 0x1000, &[Some(0x48), Some(0x41), None, Some(0x00), Some(0x20)]).unwrap();
+
+        let expected = format!("{}: 48 41 ?? 00 20{}  HA.. \n", address(0x1000), "   ".repeat(11));
+        assert_eq!(String::from_utf8(line).unwrap(), expected);
+    }
+
+    #[test]
+    fn hex_dump_of_our_own_memory() {
+        let bytes: [u8; 20] = *b"0123456789abcdef\x00xyz";
+        let start = bytes.as_ptr() as usize;
+        let process = Process::open_by_pid(std::process::id(), Access::read_only()).unwrap();
+
+        let expected = format!(
+            "{}: 30 31 32 33 34 35 36 37 38 39 61 62 63 64 65 66  0123456789abcdef\n{}: 00 78 79 7A{}  .xyz\n",
+            address(start),
+            address(start + 16),
+            "   ".repeat(12),
+        );
+        assert_eq!(process.hex_dump(start, bytes.len()).unwrap(), expected);
+    }
+}
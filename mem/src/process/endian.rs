@@ -0,0 +1,120 @@
+use super::Process;
+use crate::error::Error;
+
+/// The byte order a value is stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    /// The byte order of the machine we are running on, which is always
+    /// little endian on x86 and x64.
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: Self = Self::Little;
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: Self = Self::Big;
+}
+
+/// Numbers that can be read and written with `Process::read_with_endian` and
+/// `Process::write_with_endian`.
+pub trait Primitive: Copy {
+    /// Reverses the order of the bytes of the value.
+    #[must_use]
+    fn swap_bytes(self) -> Self;
+}
+
+macro_rules! impl_primitive {
+    ($($ty:ty),*) => {
+        $(
+            impl Primitive for $ty {
+                fn swap_bytes(self) -> Self { <$ty>::swap_bytes(self) }
+            }
+        )*
+    };
+}
+
+impl_primitive!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl Primitive for f32 {
+    fn swap_bytes(self) -> Self { Self::from_bits(self.to_bits().swap_bytes()) }
+}
+
+impl Primitive for f64 {
+    fn swap_bytes(self) -> Self { Self::from_bits(self.to_bits().swap_bytes()) }
+}
+
+impl Process {
+    /// Reads a `T` stored in the byte order `endian` from `address`, `read`
+    /// always uses the native byte order.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_with_endian<T: Primitive>(&self, address: usize, endian: Endian) -> Result<T, Error> {
+        let value = self.read::<T>(address)?;
+
+        Ok(if endian == Endian::NATIVE { value } else { value.swap_bytes() })
+    }
+
+    /// Writes `value` to `address` in the byte order `endian`, `write` always
+    /// uses the native byte order.
+    ///
+    /// # Errors
+    /// Any error `write` returns.
+    pub fn write_with_endian<T: Primitive>(&self, address: usize, value: T, endian: Endian) -> Result<(), Error> {
+        let value = if endian == Endian::NATIVE { value } else { value.swap_bytes() };
+
+        self.write(address, &value)
+    }
+
+    /// Reads a big endian `u16` from `address`.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_u16_be(&self, address: usize) -> Result<u16, Error> { self.read_with_endian(address, Endian::Big) }
+
+    /// Reads a big endian `u32` from `address`.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_u32_be(&self, address: usize) -> Result<u32, Error> { self.read_with_endian(address, Endian::Big) }
+
+    /// Reads a big endian `u64` from `address`.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_u64_be(&self, address: usize) -> Result<u64, Error> { self.read_with_endian(address, Endian::Big) }
+
+    /// Reads a big endian `i16` from `address`.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_i16_be(&self, address: usize) -> Result<i16, Error> { self.read_with_endian(address, Endian::Big) }
+
+    /// Reads a big endian `i32` from `address`.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_i32_be(&self, address: usize) -> Result<i32, Error> { self.read_with_endian(address, Endian::Big) }
+
+    /// Reads a big endian `i64` from `address`.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_i64_be(&self, address: usize) -> Result<i64, Error> { self.read_with_endian(address, Endian::Big) }
+
+    /// Reads a little endian `u32` from `address`, no matter what the native
+    /// byte order is.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_u32_le(&self, address: usize) -> Result<u32, Error> { self.read_with_endian(address, Endian::Little) }
+
+    /// Reads a little endian `u64` from `address`, no matter what the native
+    /// byte order is.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_u64_le(&self, address: usize) -> Result<u64, Error> { self.read_with_endian(address, Endian::Little) }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use windows::Win32::{Foundation::WAIT_TIMEOUT, System::Threading::WAIT_OBJECT_0};
+
+use super::Process;
+use crate::{
+    error::Error,
+    windows::wrappers::{get_exit_code_process, wait_for_single_object_dur, DWORD, STILL_ACTIVE},
+};
+
+impl Process {
+    /// Whether the process is still running, so tools can stop their loops
+    /// once it exits instead of failing every read.
+    ///
+    /// A closed `Process` is never alive. If the handle lacks
+    /// `PROCESS_QUERY_INFORMATION` we can't tell and the process counts as
+    /// alive.
+    #[must_use]
+    pub fn is_alive(&self) -> bool {
+        let Ok(handle) = self.open_handle() else {
+            return false;
+        };
+
+        match get_exit_code_process(handle) {
+            // A process can also exit with STILL_ACTIVE as its exit code, only
+            // one that has exited is signaled
+            Ok(STILL_ACTIVE) => !matches!(wait_for_single_object_dur(handle, Some(Duration::ZERO)), Ok(WAIT_OBJECT_0)),
+            Ok(_) => false,
+            Err(_) => true,
+        }
+    }
+
+    /// Waits for the process to exit and returns its exit code, `None` waits
+    /// forever. The handle needs `PROCESS_SYNCHRONIZE`, which every preset of
+    /// `Access` has.
+    ///
+    /// # Errors
+    /// `Error::Timeout` if the process is still running after `timeout`, or
+    /// `Error::ProcessError` if its exit code couldn't be retrieved.
+    pub fn wait_for_exit(&self, timeout: Option<Duration>) -> Result<DWORD, Error> {
+        let handle = self.open_handle()?;
+        if wait_for_single_object_dur(handle, timeout)? == WAIT_TIMEOUT {
+            return Err(Error::Timeout);
+        }
+
+        get_exit_code_process(handle)
+    }
+
+    /// Turns the error of a failed read or write into `Error::ProcessExited`
+    /// if it failed because the process is gone.
+    pub(super) fn exited_or(&self, error: Error) -> Error {
+        if self.is_open && !self.is_alive() {
+            Error::ProcessExited
+        } else {
+            error
+        }
+    }
+}
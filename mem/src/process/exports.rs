@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use super::Process;
+use crate::{
+    error::Error,
+    pe::{self, PeHeaders},
+};
+
+/// Enough to hold the headers of pretty much every image, the real size is
+/// read from the headers if it turns out to be larger.
+const HEADERS_SIZE: usize = 0x1000;
+
+impl Process {
+    /// Every function the module named `module_name` exports by name, mapped to
+    /// its absolute address inside of the process.
+    ///
+    /// The export directory is read straight out of the process, so this works
+    /// on modules that were changed after they were loaded. Forwarded exports
+    /// like `kernel32.HeapAlloc`, which point to a function in a different
+    /// module, are skipped since they have no code in this module.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have the module,
+    /// `Error::MemoryError` if the module couldn't be read, or
+    /// `Error::InvalidPe` if its headers or export directory are broken.
+    pub fn module_exports(&self, module_name: &str) -> Result<HashMap<String, usize>, Error> {
+        let module = self.module(module_name)?;
+
+        let headers = self.read_headers(module.base, module.size)?;
+        let Some(directory) = headers.directory(pe::DIRECTORY_EXPORT) else {
+            return Ok(HashMap::new());
+        };
+
+        // The loader mapped `SizeOfImage` bytes, a directory that claims to be
+        // larger than that is broken and would only make us allocate for it
+        let end = directory.virtual_address.checked_add(directory.size);
+        if end.is_none_or(|end| end > headers.size_of_image) {
+            return Err(Error::InvalidPe("export directory is outside of the image"));
+        }
+
+        let mut export_directory = vec![0; directory.size as usize];
+        self.read_bytes(module.base + directory.virtual_address as usize, &mut export_directory)?;
+
+        Ok(pe::exports(&export_directory, directory.virtual_address, directory)?
+            .into_iter()
+            .filter(|export| export.forwarder.is_none())
+            .filter_map(|export| Some((export.name?, module.base + export.rva as usize)))
+            .collect())
+    }
+
+    /// Reads and parses the headers of the image of `size` bytes loaded at
+    /// `base`.
+    pub(super) fn read_headers(&self, base: usize, size: usize) -> Result<PeHeaders, Error> {
+        let mut headers = vec![0; HEADERS_SIZE.min(size)];
+        self.read_bytes(base, &mut headers)?;
+        if let Ok(parsed) = PeHeaders::parse(&headers) {
+            let size_of_headers = parsed.size_of_headers as usize;
+            if size_of_headers > headers.len() && size_of_headers <= size {
+                headers.resize(size_of_headers, 0);
+                self.read_bytes(base, &mut headers)?;
+            }
+        }
+
+        PeHeaders::parse(&headers)
+    }
+}
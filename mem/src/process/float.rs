@@ -0,0 +1,83 @@
+use std::mem::size_of;
+
+use super::{Process, RegionFilter};
+use crate::error::Error;
+
+impl Process {
+    /// Reads an `f32` from `address`.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_f32(&self, address: usize) -> Result<f32, Error> { self.read(address) }
+
+    /// Reads an `f64` from `address`.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_f64(&self, address: usize) -> Result<f64, Error> { self.read(address) }
+
+    /// Writes an `f32` to `address`.
+    ///
+    /// # Errors
+    /// Any error `write` returns.
+    pub fn write_f32(&self, address: usize, value: f32) -> Result<(), Error> { self.write(address, &value) }
+
+    /// Writes an `f64` to `address`.
+    ///
+    /// # Errors
+    /// Any error `write` returns.
+    pub fn write_f64(&self, address: usize, value: f64) -> Result<(), Error> { self.write(address, &value) }
+
+    /// Whether the `f32` at `address` is within `epsilon` of `expected`, since
+    /// floats that were calculated rarely compare equal to the value you
+    /// expect.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_f32_approx(&self, address: usize, expected: f32, epsilon: f32) -> Result<bool, Error> {
+        Ok(is_close(self.read_f32(address)?, expected, epsilon))
+    }
+
+    /// Scans every committed writable region of the process for `f32`s that
+    /// are within `epsilon` of `value` and returns their addresses.
+    ///
+    /// Only addresses that are a multiple of 4 are checked, which is how
+    /// floats are aligned. Use this to find a value like health or a
+    /// coordinate that you only know roughly, then narrow the results down by
+    /// checking them again once the value changed.
+    ///
+    /// # Errors
+    /// `Error::InvalidArgument` if `value` or `epsilon` isn't a finite
+    /// number or `epsilon` is negative.
+    pub fn scan_float(&self, value: f32, epsilon: f32) -> Result<Vec<usize>, Error> {
+        const SIZE: usize = size_of::<f32>();
+
+        if !value.is_finite() || !epsilon.is_finite() || epsilon < 0.0 {
+            return Err(Error::InvalidArgument(
+                "the value and epsilon have to be finite and epsilon can't be negative",
+            ));
+        }
+
+        let mut found = Vec::new();
+        for region in self.regions_matching(RegionFilter::readable().and(RegionFilter::writable())) {
+            self.for_each_readable_run(region.base, region.size, |base, buffer| {
+                let first = (SIZE - base % SIZE) % SIZE;
+
+                for (index, bytes) in buffer.get(first..).unwrap_or_default().chunks_exact(SIZE).enumerate() {
+                    let candidate = f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    if is_close(candidate, value, epsilon) {
+                        found.push(base + first + index * SIZE);
+                    }
+                }
+
+                true
+            });
+        }
+
+        Ok(found)
+    }
+}
+
+/// Whether `value` is within `epsilon` of `expected`, `NaN`s and infinities
+/// are never close to anything.
+fn is_close(value: f32, expected: f32, epsilon: f32) -> bool { value.is_finite() && (value - expected).abs() <= epsilon }
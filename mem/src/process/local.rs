@@ -0,0 +1,174 @@
+use std::mem::size_of;
+
+use windows::Win32::Foundation::{ERROR_INVALID_PARAMETER, ERROR_NOACCESS};
+
+use super::{module_name_is, protect, walk_modules, MemoryAccess, MemoryRegion, ProtectGuard, Protection};
+use crate::{
+    error::Error,
+    module::Module,
+    scanner::Signature,
+    windows::wrappers::{
+        get_current_process, virtual_protect, virtual_query, MemoryBasicInformation, PageProtectionFlags, LPVOID,
+    },
+};
+
+/// The process we are running in, the counterpart of `Process` for code inside
+/// of an injected DLL.
+///
+/// Since we share the address space memory is accessed through pointers
+/// instead of `ReadProcessMemory`. Every access makes sure the pages are
+/// committed and have the right protection first, so a bad address returns an
+/// error instead of crashing the process. Pages that are freed by another
+/// thread while we access them still crash it.
+///
+/// Reads and writes through `MemoryAccess` are a lot faster than their
+/// `Process` versions since the only syscall they make is `VirtualQuery`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LocalProcess;
+
+impl LocalProcess {
+    /// Looks up the module named `name`, like `Process::module`.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if we don't have a module with that name.
+    pub fn module(self, name: &str) -> Result<Module, Error> {
+        walk_modules(std::process::id(), |entry| {
+            module_name_is(entry, name).then(|| Module::from(entry))
+        })?
+        .ok_or_else(|| Error::ModuleNotFound(name.to_owned()))
+    }
+
+    /// Scans the module named `module_name` for `pattern` and returns the
+    /// absolute address of the first match, like `Process::scan_module`.
+    ///
+    /// The pages of the module are scanned where they are instead of being
+    /// copied into a buffer first, any pages that can't be read are skipped.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the pattern couldn't be parsed,
+    /// `Error::ModuleNotFound` if we don't have the module or
+    /// `Error::MemoryError` if its pages couldn't be queried.
+    pub fn scan_module(self, module_name: &str, pattern: &str) -> Result<Option<usize>, Error> {
+        let signature = Signature::parse(pattern)?;
+        let module = self.module(module_name)?;
+        let end = module.base + module.size;
+
+        // Every run of readable pages is scanned on its own so a match can
+        // never span over a page we can't read
+        let scan_run = |start: usize, end: usize| {
+            if start >= end {
+                return None;
+            }
+
+            let run = unsafe { std::slice::from_raw_parts(start as *const u8, end - start) };
+            signature.scan(run).map(|offset| start + offset)
+        };
+
+        let mut run_start = module.base;
+        let mut current = module.base;
+        while current < end {
+            let region = self.query(current)?;
+            let region_end = region.end().min(end);
+
+            if !region.is_committed || !region.is_readable || region.is_guard {
+                if let Some(found) = scan_run(run_start, current) {
+                    return Ok(Some(found));
+                }
+                run_start = region_end;
+            }
+
+            current = region_end;
+        }
+
+        Ok(scan_run(run_start, end))
+    }
+
+    /// Writes `value` to `address` even if the pages there aren't writable,
+    /// like `Process::write_protected`.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the pages couldn't be queried, their
+    /// protection couldn't be changed or they couldn't be written, or
+    /// `Error::InvalidArgument` if the write would wrap around the address
+    /// space.
+    pub fn write_protected<T: Copy>(self, address: usize, value: &T) -> Result<(), Error> {
+        let bytes = unsafe { std::slice::from_raw_parts(std::ptr::from_ref(value).cast::<u8>(), size_of::<T>()) };
+
+        self.write_bytes_protected(address, bytes)
+    }
+
+    /// Writes all of `bytes` to `address`, see `write_protected`.
+    ///
+    /// # Errors
+    /// Any error `write_protected` returns.
+    pub fn write_bytes_protected(self, address: usize, bytes: &[u8]) -> Result<(), Error> {
+        protect::write_bytes_protected(&self, get_current_process(), address, bytes, ProtectGuard::local)
+    }
+
+    /// Makes sure every page of `size` bytes at `address` is committed, isn't
+    /// a guard page and passes `allowed`.
+    fn check_range(address: usize, size: usize, allowed: impl Fn(&MemoryRegion) -> bool) -> Result<(), Error> {
+        let end = address.checked_add(size).ok_or(Error::MemoryError(ERROR_INVALID_PARAMETER))?;
+
+        let mut current = address;
+        while current < end {
+            let region = Self.query(current)?;
+            if !region.is_committed || region.is_guard || !allowed(&region) {
+                return Err(Error::MemoryError(ERROR_NOACCESS));
+            }
+
+            current = region.end();
+        }
+
+        Ok(())
+    }
+}
+
+impl MemoryAccess for LocalProcess {
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        Self::check_range(address, buffer.len(), |region| region.is_readable)?;
+
+        unsafe { std::ptr::copy_nonoverlapping(address as *const u8, buffer.as_mut_ptr(), buffer.len()) };
+        Ok(())
+    }
+
+    fn write_bytes(&self, address: usize, bytes: &[u8]) -> Result<(), Error> {
+        Self::check_range(address, bytes.len(), |region| region.is_writable)?;
+
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), address as *mut u8, bytes.len()) };
+        Ok(())
+    }
+
+    fn read<T: Copy>(&self, address: usize) -> Result<T, Error> {
+        if size_of::<T>() == 0 {
+            return Err(Error::MemoryError(ERROR_INVALID_PARAMETER));
+        }
+        Self::check_range(address, size_of::<T>(), |region| region.is_readable)?;
+
+        Ok(unsafe { std::ptr::read_unaligned(address as *const T) })
+    }
+
+    fn write<T: Copy>(&self, address: usize, value: &T) -> Result<(), Error> {
+        if size_of::<T>() == 0 {
+            return Err(Error::MemoryError(ERROR_INVALID_PARAMETER));
+        }
+        Self::check_range(address, size_of::<T>(), |region| region.is_writable)?;
+
+        unsafe { std::ptr::write_unaligned(address as *mut T, *value) };
+        Ok(())
+    }
+
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<PageProtectionFlags, Error> {
+        let mut old_protection = 0;
+        virtual_protect(address as LPVOID, size, protection.flags(), &raw mut old_protection)?;
+
+        Ok(old_protection)
+    }
+
+    fn query(&self, address: usize) -> Result<MemoryRegion, Error> {
+        let mut info = MemoryBasicInformation::default();
+        virtual_query(address as _, &raw mut info, size_of::<MemoryBasicInformation>())?;
+
+        Ok(MemoryRegion::from(&info))
+    }
+}
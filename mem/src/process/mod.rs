@@ -0,0 +1,894 @@
+//! `process` is the high level way of working with another process. A
+//! `Process` bundles the opened handle together with the id and name of the
+//! process so you never have to juggle a raw `Handle` yourself.
+mod access;
+mod allocation;
+mod arch;
+mod attach;
+mod batch;
+mod bits;
+mod call;
+mod checksum;
+mod dump;
+mod endian;
+mod exit;
+mod exports;
+mod float;
+mod local;
+mod patch;
+mod peb;
+mod pointer;
+mod protect;
+mod region;
+mod relative;
+mod rights;
+mod scan;
+mod string;
+mod suspend;
+mod thread;
+mod value;
+
+use std::{
+    collections::HashMap,
+    mem::size_of,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub use access::MemoryAccess;
+// Only the inline hooks of x64 have to look for memory near their target
+#[cfg(all(target_arch = "x86_64", feature = "internal"))]
+pub(crate) use allocation::find_near;
+pub use allocation::{Protection, RemoteAlloc};
+pub use arch::Architecture;
+pub use attach::Target;
+pub use batch::ReadRequest;
+pub use endian::{Endian, Primitive};
+pub use local::LocalProcess;
+pub use patch::Patch;
+pub use protect::ProtectGuard;
+pub(crate) use region::{is_user_range, MAX_USER_ADDRESS};
+pub use region::{MemoryRegion, RegionFilter};
+pub use rights::Access;
+pub use scan::ScanMatch;
+pub use suspend::ProcessGuard;
+pub use thread::RemoteThread;
+pub use value::{Scan, ScanFilter, ScanType};
+use windows::Win32::{
+    Foundation::{ERROR_BAD_LENGTH, ERROR_INVALID_PARAMETER, ERROR_PARTIAL_COPY},
+    System::Diagnostics::ToolHelp::{TH32CS_SNAPMODULE, TH32CS_SNAPMODULE32, TH32CS_SNAPPROCESS, TH32CS_SNAPTHREAD},
+};
+
+use crate::{
+    error::Error,
+    layout::StructLayout,
+    module::{Module, ModuleInfo},
+    windows::{
+        handle::SafeHandle,
+        memory,
+        utils::windows_string_to_string,
+        wrappers::{
+            check_remote_debugger_present, create_tool_help32_snapshot, get_module_file_name_ex, get_module_information,
+            module32_first, module32_next, open_process_owned, process32_first, process32_next, read_process_memory,
+            thread32_first, thread32_next, write_process_memory, HModule, Handle, ModuleEntry32, ProcessEntry32,
+            ThreadEntry32, DWORD, LPCVOID, LPVOID,
+        },
+    },
+};
+
+const PAGE_SIZE: usize = 0x1000;
+/// How many more times a module snapshot is taken if it fails with
+/// `ERROR_BAD_LENGTH`.
+const MODULE_SNAPSHOT_RETRIES: u32 = 8;
+
+/// Information about a process running on the system, created by `enumerate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessInfo {
+    /// The id of the process.
+    pub pid: DWORD,
+    /// The executable name of the process.
+    pub name: String,
+    /// The id of the process that created this process.
+    pub parent_pid: DWORD,
+    /// How many threads the process had when it was enumerated, a process
+    /// that is exiting can have none left.
+    pub thread_count: u32,
+    /// The base priority of the threads the process creates, 8 for normal
+    /// processes.
+    pub base_priority: i32,
+}
+
+impl From<&ProcessEntry32> for ProcessInfo {
+    fn from(entry: &ProcessEntry32) -> Self {
+        Self {
+            pid: entry.th32ProcessID,
+            name: windows_string_to_string(&entry.szExeFile),
+            parent_pid: entry.th32ParentProcessID,
+            thread_count: entry.cntThreads,
+            base_priority: entry.pcPriClassBase,
+        }
+    }
+}
+
+/// Information about a thread of a process, created by `Process::threads`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadInfo {
+    /// The id of the thread.
+    pub tid: DWORD,
+    /// The id of the process that owns the thread.
+    pub owner_pid: DWORD,
+    /// The priority the thread was created with, from 0 to 31.
+    pub base_priority: i32,
+}
+
+impl From<&ThreadEntry32> for ThreadInfo {
+    fn from(entry: &ThreadEntry32) -> Self {
+        Self {
+            tid: entry.th32ThreadID,
+            owner_pid: entry.th32OwnerProcessID,
+            base_priority: entry.tpBasePri,
+        }
+    }
+}
+
+/// Every process that is currently running on the system.
+///
+/// # Errors
+/// `Error::MemoryError` if the process snapshot couldn't be created.
+pub fn enumerate() -> Result<Vec<ProcessInfo>, Error> { Ok(processes()?.collect()) }
+
+/// Iterates over the processes that were running when it was created, the
+/// lazy version of `enumerate` for when you stop at the first match.
+///
+/// # Errors
+/// `Error::MemoryError` if the process snapshot couldn't be created.
+pub fn processes() -> Result<ProcessIter, Error> {
+    Ok(ProcessIter {
+        snapshot: Some(SafeHandle::owned(create_tool_help32_snapshot(TH32CS_SNAPPROCESS, 0)?)),
+        entry: ProcessEntry32 {
+            dwSize: size_of::<ProcessEntry32>() as u32,
+            ..ProcessEntry32::default()
+        },
+        started: false,
+    })
+}
+
+/// The iterator returned by `processes`, the snapshot is closed once it is
+/// dropped or runs out of processes.
+pub struct ProcessIter {
+    snapshot: Option<SafeHandle>,
+    entry: ProcessEntry32,
+    started: bool,
+}
+
+impl Iterator for ProcessIter {
+    type Item = ProcessInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let snapshot = **self.snapshot.as_ref()?;
+
+        let result = if self.started {
+            process32_next(snapshot, &mut self.entry)
+        } else {
+            self.started = true;
+            process32_first(snapshot, &mut self.entry)
+        };
+
+        if result.is_err() {
+            self.snapshot = None;
+            return None;
+        }
+
+        Some(ProcessInfo::from(&self.entry))
+    }
+}
+
+/// Iterates over the modules that were loaded into the process `pid` when it
+/// was created, for when you only have the id of a process and not an opened
+/// `Process`.
+///
+/// # Errors
+/// `Error::MemoryError` if the module snapshot couldn't be created.
+pub fn modules(pid: DWORD) -> Result<ModuleIter, Error> {
+    Ok(ModuleIter {
+        snapshot: Some(module_snapshot(pid)?),
+        entry: ModuleEntry32 {
+            dwSize: size_of::<ModuleEntry32>() as u32,
+            ..ModuleEntry32::default()
+        },
+        started: false,
+    })
+}
+
+/// The iterator returned by `modules`, the snapshot is closed once it is
+/// dropped or runs out of modules.
+pub struct ModuleIter {
+    snapshot: Option<SafeHandle>,
+    entry: ModuleEntry32,
+    started: bool,
+}
+
+impl Iterator for ModuleIter {
+    type Item = Module;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let snapshot = **self.snapshot.as_ref()?;
+
+        let result = if self.started {
+            module32_next(snapshot, &mut self.entry)
+        } else {
+            self.started = true;
+            module32_first(snapshot, &mut self.entry)
+        };
+
+        if result.is_err() {
+            self.snapshot = None;
+            return None;
+        }
+
+        Some(Module::from(&self.entry))
+    }
+}
+
+/// The base address of the first module named `name` in the process `pid`,
+/// the comparison is case-insensitive. Unlike `Process::module_base` nothing
+/// is cached.
+///
+/// # Errors
+/// `Error::ModuleNotFound` if the process doesn't have a module with that
+/// name, or `Error::MemoryError` if the module snapshot couldn't be created.
+pub fn module_base(pid: DWORD, name: &str) -> Result<usize, Error> {
+    walk_modules(pid, |entry| module_name_is(entry, name).then_some(entry.modBaseAddr as usize))?
+        .ok_or_else(|| Error::ModuleNotFound(name.to_owned()))
+}
+
+/// Every running process whose executable name matches `name`, the
+/// comparison is case-insensitive. Programs that run more than one instance
+/// show up once per instance.
+///
+/// # Errors
+/// `Error::MemoryError` if the process snapshot couldn't be created.
+pub fn find_by_name(name: &str) -> Result<Vec<ProcessInfo>, Error> {
+    let mut processes = enumerate()?;
+    processes.retain(|process| process.name.eq_ignore_ascii_case(name));
+
+    Ok(processes)
+}
+
+/// Waits for a process whose executable name matches `name` to start and
+/// returns its id, the comparison is case-insensitive.
+///
+/// The processes are checked every `poll_interval` for up to `timeout`, `None`
+/// waits forever. A process that is already running is returned right away.
+/// Opening it right after can still fail while it is being initialized, which
+/// `Config::retries` of the injector helps with.
+///
+/// # Errors
+/// `Error::Timeout` if no process with that name started in time, or
+/// `Error::MemoryError` if the process snapshot couldn't be created.
+pub fn wait_for_process(name: &str, timeout: Option<Duration>, poll_interval: Duration) -> Result<DWORD, Error> {
+    let started = Instant::now();
+    loop {
+        let pid = walk_processes(|entry| {
+            windows_string_to_string(&entry.szExeFile)
+                .eq_ignore_ascii_case(name)
+                .then_some(entry.th32ProcessID)
+        })?;
+        if let Some(pid) = pid {
+            return Ok(pid);
+        }
+
+        if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+            return Err(Error::Timeout);
+        }
+        std::thread::sleep(poll_interval);
+    }
+}
+
+/// An opened process, the handle is closed once the `Process` is dropped or
+/// `close` is called.
+///
+/// A `Process` is `Send` and `Sync`, so a single one can be shared between
+/// threads to scan or read in parallel. Process handles can be used from any
+/// thread, and `ReadProcessMemory` and the other functions that take one are
+/// safe to call on the same handle at the same time. The only state we keep
+/// ourselves is the list of suspended threads and the cache of module bases,
+/// which are behind a `Mutex`.
+///
+/// Reads and writes that fail because the process has exited return
+/// `Error::ProcessExited`, check `is_alive` to stop before that happens.
+pub struct Process {
+    /// A borrowed null handle once the process was closed, so it is never
+    /// closed twice.
+    handle: SafeHandle,
+    is_open: bool,
+    pid: DWORD,
+    name: String,
+    /// The threads every `suspend` call suspended, so `resume` only touches
+    /// the ones of the last call. Handles are kept instead of ids since an id
+    /// can be reused once its thread exits.
+    suspended: Mutex<Vec<Vec<SafeHandle>>>,
+    /// The base addresses `module_base` looked up, keyed by the lowercase
+    /// module name.
+    module_bases: Mutex<HashMap<String, usize>>,
+    /// Whether `read_rel` and `write_rel` look the module up again once when
+    /// the cached base fails.
+    refresh_stale_modules: bool,
+}
+
+// The handle is a plain integer in windows-rs, so this keeps the reasoning
+// above from silently becoming wrong if a field is ever added that isn't
+const _: fn() = || {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Process>();
+};
+
+impl Process {
+    /// Opens the first process whose executable name matches `name`, the
+    /// comparison is case-insensitive. `access` is usually one of the `Access`
+    /// presets like `Access::read_write()`.
+    ///
+    /// # Errors
+    /// `Error::ProcessNotFound` if no process has that name,
+    /// `Error::AccessDenied` if we aren't allowed to open the process, or
+    /// `Error::ProcessError` if it couldn't be opened for any other reason.
+    pub fn open_by_name(name: &str, access: impl Into<Access>) -> Result<Self, Error> {
+        let entry = walk_processes(|entry| {
+            windows_string_to_string(&entry.szExeFile)
+                .eq_ignore_ascii_case(name)
+                .then_some(*entry)
+        })?
+        .ok_or(Error::ProcessNotFound)?;
+
+        Self::open(&entry, access.into())
+    }
+
+    /// Opens the process with the id of `pid`.
+    ///
+    /// # Errors
+    /// `Error::ProcessNotFound` if no process has that id,
+    /// `Error::AccessDenied` if we aren't allowed to open the process, or
+    /// `Error::ProcessError` if it couldn't be opened for any other reason.
+    pub fn open_by_pid(pid: DWORD, access: impl Into<Access>) -> Result<Self, Error> {
+        let entry = walk_processes(|entry| (entry.th32ProcessID == pid).then_some(*entry))?.ok_or(Error::ProcessNotFound)?;
+
+        Self::open(&entry, access.into())
+    }
+
+    fn open(entry: &ProcessEntry32, access: Access) -> Result<Self, Error> {
+        let handle = open_process_owned(access.bits(), false, entry.th32ProcessID)?;
+
+        Ok(Self {
+            handle,
+            is_open: true,
+            pid: entry.th32ProcessID,
+            name: windows_string_to_string(&entry.szExeFile),
+            suspended: Mutex::new(Vec::new()),
+            module_bases: Mutex::new(HashMap::new()),
+            refresh_stale_modules: true,
+        })
+    }
+
+    /// The raw handle of the process, it is only valid for as long as the
+    /// `Process` is alive and not closed. It is null once `close` was called.
+    #[must_use]
+    pub fn handle(&self) -> Handle { *self.handle }
+
+    /// Closes the handle of the process right away instead of when the
+    /// `Process` is dropped. Everything that needs the handle fails with
+    /// `Error::ProcessClosed` afterwards, and closing it again does nothing.
+    ///
+    /// The handle is never closed twice, which could otherwise close an
+    /// unrelated handle that was given the same value in the meantime.
+    pub fn close(&mut self) {
+        // Replacing the handle drops and closes the old one
+        self.handle = SafeHandle::borrowed(Handle::default());
+        self.is_open = false;
+    }
+
+    /// Whether the handle is still open, which is the case until `close` is
+    /// called.
+    #[must_use]
+    pub const fn is_open(&self) -> bool { self.is_open }
+
+    /// The handle of the process, for everything that shouldn't be called on
+    /// a closed process.
+    fn open_handle(&self) -> Result<Handle, Error> {
+        if self.is_open {
+            Ok(*self.handle)
+        } else {
+            Err(Error::ProcessClosed)
+        }
+    }
+
+    /// The id of the process.
+    #[must_use]
+    pub const fn pid(&self) -> DWORD { self.pid }
+
+    /// The executable name of the process.
+    #[must_use]
+    pub fn name(&self) -> &str { &self.name }
+
+    /// The full path of the executable the process was started from.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the path couldn't be retrieved, for example
+    /// because the handle lacks `PROCESS_QUERY_INFORMATION` or
+    /// `PROCESS_VM_READ`.
+    pub fn executable_path(&self) -> Result<PathBuf, Error> {
+        get_module_file_name_ex(self.open_handle()?, 0).map(PathBuf::from)
+    }
+
+    /// Whether a debugger is attached to the process, as reported by
+    /// `CheckRemoteDebuggerPresent`. See `peb_being_debugged` for the flag
+    /// the process itself sees.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the handle doesn't have
+    /// `PROCESS_QUERY_INFORMATION` access.
+    pub fn is_debugged(&self) -> Result<bool, Error> { check_remote_debugger_present(self.open_handle()?) }
+
+    /// Reads a `T` from `address` in the process.
+    ///
+    /// Whatever bytes are at `address` will be interpreted as a `T` so this is
+    /// meant for plain old data types like integers, floats and `#[repr(C)]`
+    /// structs made out of them.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the read fails, `T` is zero sized or not all of
+    /// the bytes of `T` could be read.
+    pub fn read<T: Copy>(&self, address: usize) -> Result<T, Error> {
+        memory::read(self.open_handle()?, address).map_err(|error| self.exited_or(error))
+    }
+
+    /// Like `read` but the memory is checked with `is_readable` first, so a
+    /// stale pointer fails with its own error.
+    ///
+    /// # Errors
+    /// `Error::InvalidRegion` if the memory isn't committed and readable,
+    /// otherwise see `read`.
+    pub fn read_checked<T: Copy>(&self, address: usize) -> Result<T, Error> {
+        memory::read_checked(self.open_handle()?, address).map_err(|error| self.exited_or(error))
+    }
+
+    /// Like `read` but a read that fails with `ERROR_PARTIAL_COPY` is retried
+    /// up to `retries` more times, waiting `delay` between attempts.
+    ///
+    /// Partial copies can be transient while the process is reallocating the
+    /// memory the value is in, which is common in games. It is also what
+    /// reading an address that was never valid fails with, so this is opt-in
+    /// since it makes reading a bad address take `retries * delay` longer
+    /// before it fails.
+    ///
+    /// # Errors
+    /// The error of the last attempt, see `read`.
+    pub fn read_retry<T: Copy>(&self, address: usize, retries: u32, delay: Duration) -> Result<T, Error> {
+        for _ in 0..retries {
+            match self.read(address) {
+                Err(Error::MemoryError(ERROR_PARTIAL_COPY)) => std::thread::sleep(delay),
+                result => return result,
+            }
+        }
+
+        self.read(address)
+    }
+
+    /// Writes `value` to `address` in the process.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the write fails, `T` is zero sized or not all of
+    /// the bytes of `T` could be written.
+    pub fn write<T: Copy>(&self, address: usize, value: &T) -> Result<(), Error> {
+        memory::write(self.open_handle()?, address, value).map_err(|error| self.exited_or(error))
+    }
+
+    /// Writes all of `buffer` to `address` in the process, the bytes version
+    /// of `write`.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the write fails or not all of `buffer` could be
+    /// written.
+    pub fn write_bytes(&self, address: usize, buffer: &[u8]) -> Result<(), Error> {
+        if self.write_bytes_counted(address, buffer)? != buffer.len() {
+            return Err(Error::MemoryError(ERROR_PARTIAL_COPY));
+        }
+
+        Ok(())
+    }
+
+    /// Writes `buffer` to `address` and returns how many of its bytes were
+    /// actually written, so a write that runs into a page that isn't writable
+    /// can tell how far it got.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if not even the first byte could be written.
+    pub fn write_bytes_counted(&self, address: usize, buffer: &[u8]) -> Result<usize, Error> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        let mut bytes_written = 0;
+        match write_process_memory(
+            self.open_handle()?,
+            address as LPVOID,
+            buffer.as_ptr().cast(),
+            buffer.len(),
+            Some(&raw mut bytes_written),
+        ) {
+            Ok(()) => Ok(bytes_written),
+            Err(_) if bytes_written != 0 => Ok(bytes_written),
+            Err(error) => Err(self.exited_or(error)),
+        }
+    }
+
+    /// Reads the structure at `address` into `T`, a `#[repr(C)]` mirror of it.
+    /// This is the same read as `read`, use `assert_layout!` next to the
+    /// mirror to make sure it matches the layout in the target.
+    ///
+    /// # Errors
+    /// Any error `read` returns.
+    pub fn read_struct<T: Copy>(&self, address: usize) -> Result<T, Error> { self.read(address) }
+
+    /// Reads the field named `field` of the structure at `base`, its offset is
+    /// taken from `layout`.
+    ///
+    /// # Errors
+    /// `Error::FieldNotFound` if `layout` doesn't have the field, or any error
+    /// `read` returns.
+    pub fn read_field<T: Copy>(&self, base: usize, layout: &StructLayout, field: &str) -> Result<T, Error> {
+        let offset = layout.field(field).ok_or_else(|| Error::FieldNotFound(field.to_owned()))?;
+
+        self.read(base.wrapping_add(offset))
+    }
+
+    /// Reads `count` consecutive `T`s starting at `address` with a single read,
+    /// which is a lot faster than reading them one by one.
+    ///
+    /// # Errors
+    /// `Error::InvalidArgument` if `count` `T`s don't fit in a `usize`, or
+    /// `Error::MemoryError` if the read fails, `T` is zero sized or not all of
+    /// the bytes could be read.
+    pub fn read_array<T: Copy>(&self, address: usize, count: usize) -> Result<Vec<T>, Error> {
+        if size_of::<T>() == 0 {
+            return Err(Error::MemoryError(ERROR_INVALID_PARAMETER));
+        }
+        let size = count
+            .checked_mul(size_of::<T>())
+            .ok_or(Error::InvalidArgument("array size overflows a usize"))?;
+
+        let mut values = Vec::<T>::with_capacity(count);
+        if count == 0 {
+            return Ok(values);
+        }
+
+        let mut bytes_read = 0;
+        read_process_memory(
+            self.open_handle()?,
+            address as LPCVOID,
+            values.as_mut_ptr().cast(),
+            size,
+            &raw mut bytes_read,
+        )
+        .map_err(|error| self.exited_or(error))?;
+
+        if bytes_read != size {
+            return Err(Error::MemoryError(ERROR_PARTIAL_COPY));
+        }
+
+        // Every byte of the `count` values was just written by the read
+        unsafe { values.set_len(count) };
+        Ok(values)
+    }
+
+    /// Like `read_array` but reads into `out` instead of allocating a new
+    /// `Vec`, so the same buffer can be reused every time, for example when
+    /// reading an entity list every frame.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the read fails, `T` is zero sized or not all of
+    /// the bytes could be read.
+    pub fn read_into_slice<T: Copy>(&self, address: usize, out: &mut [T]) -> Result<(), Error> {
+        if size_of::<T>() == 0 {
+            return Err(Error::MemoryError(ERROR_INVALID_PARAMETER));
+        }
+        if out.is_empty() {
+            return Ok(());
+        }
+
+        // A slice never takes up more than `isize::MAX` bytes so this can't
+        // overflow
+        let size = std::mem::size_of_val(out);
+        let mut bytes_read = 0;
+        read_process_memory(
+            self.open_handle()?,
+            address as LPCVOID,
+            out.as_mut_ptr().cast(),
+            size,
+            &raw mut bytes_read,
+        )
+        .map_err(|error| self.exited_or(error))?;
+
+        if bytes_read != size {
+            return Err(Error::MemoryError(ERROR_PARTIAL_COPY));
+        }
+
+        Ok(())
+    }
+
+    /// Writes every value of `values` to consecutive addresses starting at
+    /// `address` with a single write.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the write fails, `T` is zero sized or not all of
+    /// the bytes could be written.
+    pub fn write_array<T: Copy>(&self, address: usize, values: &[T]) -> Result<(), Error> {
+        if size_of::<T>() == 0 {
+            return Err(Error::MemoryError(ERROR_INVALID_PARAMETER));
+        }
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        // A slice can never be larger than `isize::MAX` bytes
+        let size = std::mem::size_of_val(values);
+        let mut bytes_written = 0;
+
+        write_process_memory(
+            self.open_handle()?,
+            address as LPVOID,
+            values.as_ptr().cast(),
+            size,
+            Some(&raw mut bytes_written),
+        )
+        .map_err(|error| self.exited_or(error))?;
+
+        if bytes_written != size {
+            return Err(Error::MemoryError(ERROR_PARTIAL_COPY));
+        }
+
+        Ok(())
+    }
+
+    /// Reads as many of the `buffer.len()` bytes at `address` as possible into
+    /// `buffer` and returns how many were read, so a read that runs into an
+    /// unreadable page still gets everything before it.
+    ///
+    /// The bytes that were read always start at `address`, anything after the
+    /// first page that couldn't be read is left untouched.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if not even the first byte could be read, or
+    /// `Error::InvalidArgument` if the read would wrap around the address
+    /// space.
+    pub fn read_into(&self, address: usize, buffer: &mut [u8]) -> Result<usize, Error> {
+        address
+            .checked_add(buffer.len())
+            .ok_or(Error::InvalidArgument("the read wraps around the address space"))?;
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        let mut bytes_read = 0;
+        let error = match read_process_memory(
+            self.open_handle()?,
+            address as LPCVOID,
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+            &raw mut bytes_read,
+        ) {
+            Ok(()) => return Ok(bytes_read),
+            // Windows reports how far it got on some versions
+            Err(_) if bytes_read != 0 => return Ok(bytes_read),
+            Err(error) => error,
+        };
+
+        // Other versions don't report anything, so read up to the first page
+        // that can't be read ourselves
+        let mut read = 0;
+        while read < buffer.len() {
+            let page_left = PAGE_SIZE - (address + read) % PAGE_SIZE;
+            let end = (read + page_left).min(buffer.len());
+            if self.read_bytes(address + read, &mut buffer[read..end]).is_err() {
+                break;
+            }
+
+            read = end;
+        }
+
+        if read == 0 {
+            Err(self.exited_or(error))
+        } else {
+            Ok(read)
+        }
+    }
+
+    /// Iterates over every module that is loaded into the process. The
+    /// iterator owns the module snapshot, which is closed once it is dropped
+    /// or runs out of modules.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the module snapshot couldn't be created.
+    pub fn modules(&self) -> Result<ModuleIter, Error> { modules(self.pid) }
+
+    /// The first module loaded into the process named `name`, the comparison
+    /// is case-insensitive.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have a module named
+    /// `name`.
+    pub fn module(&self, name: &str) -> Result<Module, Error> {
+        walk_modules(self.pid, |entry| module_name_is(entry, name).then(|| Module::from(entry)))?
+            .ok_or_else(|| Error::ModuleNotFound(name.to_owned()))
+    }
+
+    /// The base address and size of the module named `name`, like `module` but
+    /// without building a `Module` so it is cheap enough to call in a loop.
+    ///
+    /// The size is the larger of what toolhelp and the windows loader report,
+    /// so scanning the module never misses the end of it.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have a module with that
+    /// name.
+    pub fn module_bounds(&self, name: &str) -> Result<(usize, usize), Error> {
+        let (base, size) = walk_modules(self.pid, |entry| {
+            module_name_is(entry, name).then_some((entry.modBaseAddr as usize, entry.modBaseSize as usize))
+        })?
+        .ok_or_else(|| Error::ModuleNotFound(name.to_owned()))?;
+
+        let size = get_module_information(self.open_handle()?, base as HModule).map_or(size, |info| size.max(info.size));
+        Ok((base, size))
+    }
+
+    /// The base address, image size and entry point of the module named
+    /// `name` as the windows loader sees them.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have a module with that
+    /// name, or `Error::ProcessError` if the loader couldn't be queried.
+    pub fn module_info(&self, name: &str) -> Result<ModuleInfo, Error> {
+        let base = walk_modules(self.pid, |entry| {
+            module_name_is(entry, name).then_some(entry.modBaseAddr as usize)
+        })?
+        .ok_or_else(|| Error::ModuleNotFound(name.to_owned()))?;
+
+        get_module_information(self.open_handle()?, base as HModule)
+    }
+
+    /// Every thread of the process at the time of the call, threads can exit
+    /// or be created right after.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the thread snapshot couldn't be created.
+    pub fn threads(&self) -> Result<Vec<ThreadInfo>, Error> {
+        let mut threads = Vec::new();
+
+        walk_threads(self.pid, |entry| {
+            threads.push(ThreadInfo::from(entry));
+            None::<()>
+        })?;
+
+        Ok(threads)
+    }
+
+    /// Fills all of `buffer` with the bytes at `address`.
+    pub(crate) fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        let mut bytes_read = 0;
+
+        read_process_memory(
+            self.open_handle()?,
+            address as LPCVOID,
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+            &raw mut bytes_read,
+        )
+        .map_err(|error| self.exited_or(error))?;
+
+        if bytes_read != buffer.len() {
+            return Err(Error::MemoryError(ERROR_PARTIAL_COPY));
+        }
+
+        Ok(())
+    }
+}
+
+/// Walks a process snapshot calling `f` on every process until it returns
+/// `Some`.
+fn walk_processes<T>(mut f: impl FnMut(&ProcessEntry32) -> Option<T>) -> Result<Option<T>, Error> {
+    let snapshot = SafeHandle::owned(create_tool_help32_snapshot(TH32CS_SNAPPROCESS, 0)?);
+
+    let mut entry = ProcessEntry32 {
+        dwSize: size_of::<ProcessEntry32>() as u32,
+        ..ProcessEntry32::default()
+    };
+
+    if process32_first(*snapshot, &mut entry).is_err() {
+        return Ok(None);
+    }
+
+    loop {
+        if let Some(value) = f(&entry) {
+            return Ok(Some(value));
+        }
+
+        if process32_next(*snapshot, &mut entry).is_err() {
+            return Ok(None);
+        }
+    }
+}
+
+/// Walks a module snapshot of the process `pid` calling `f` on every module
+/// until it returns `Some`.
+fn walk_modules<T>(pid: DWORD, mut f: impl FnMut(&ModuleEntry32) -> Option<T>) -> Result<Option<T>, Error> {
+    let snapshot = module_snapshot(pid)?;
+
+    let mut entry = ModuleEntry32 {
+        dwSize: size_of::<ModuleEntry32>() as u32,
+        ..ModuleEntry32::default()
+    };
+
+    if module32_first(*snapshot, &mut entry).is_err() {
+        return Ok(None);
+    }
+
+    loop {
+        if let Some(value) = f(&entry) {
+            return Ok(Some(value));
+        }
+
+        if module32_next(*snapshot, &mut entry).is_err() {
+            return Ok(None);
+        }
+    }
+}
+
+/// Takes a module snapshot of the process `pid`. Creating it fails with
+/// `ERROR_BAD_LENGTH` while the process is loading or unloading a module, so
+/// that is retried a few times.
+fn module_snapshot(pid: DWORD) -> Result<SafeHandle, Error> {
+    let mut retries = 0;
+    loop {
+        match create_tool_help32_snapshot(TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32, pid) {
+            Err(Error::MemoryError(ERROR_BAD_LENGTH)) if retries < MODULE_SNAPSHOT_RETRIES => retries += 1,
+            result => return result.map(SafeHandle::owned),
+        }
+    }
+}
+
+/// Whether the module of `entry` is named `name` ignoring case, without
+/// allocating a `String` for the name of the module.
+fn module_name_is(entry: &ModuleEntry32, name: &str) -> bool {
+    let length = entry.szModule.iter().position(|&c| c == 0).unwrap_or(entry.szModule.len());
+
+    entry.szModule[..length].eq_ignore_ascii_case(name.as_bytes())
+}
+
+/// Walks a thread snapshot calling `f` on every thread owned by the process
+/// `pid` until it returns `Some`.
+pub(crate) fn walk_threads<T>(pid: DWORD, mut f: impl FnMut(&ThreadEntry32) -> Option<T>) -> Result<Option<T>, Error> {
+    // Thread snapshots always contain every thread on the system
+    let snapshot = SafeHandle::owned(create_tool_help32_snapshot(TH32CS_SNAPTHREAD, 0)?);
+
+    let mut entry = ThreadEntry32 {
+        dwSize: size_of::<ThreadEntry32>() as u32,
+        ..ThreadEntry32::default()
+    };
+
+    if thread32_first(*snapshot, &mut entry).is_err() {
+        return Ok(None);
+    }
+
+    loop {
+        if entry.th32OwnerProcessID == pid {
+            if let Some(value) = f(&entry) {
+                return Ok(Some(value));
+            }
+        }
+
+        if thread32_next(*snapshot, &mut entry).is_err() {
+            return Ok(None);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::convert::TryFrom;
+
+use super::{Architecture, Process, Protection};
+use crate::{
+    disasm::{instruction_length, MAX_LENGTH},
+    error::Error,
+    scanner::relative_target,
+    windows::wrappers::{flush_instruction_cache, LPCVOID},
+};
+
+/// The bytes at an address that were overwritten by `Process::patch` or
+/// `Process::nop`, they are written back once the `Patch` is dropped.
+pub struct Patch<'a> {
+    process: &'a Process,
+    address: usize,
+    original: Vec<u8>,
+}
+
+impl Patch<'_> {
+    /// The address that was patched.
+    #[must_use]
+    pub const fn address(&self) -> usize { self.address }
+
+    /// The bytes that were there before the patch.
+    #[must_use]
+    pub fn original(&self) -> &[u8] { &self.original }
+
+    /// Restores the original bytes, unlike dropping the patch this lets you
+    /// know if it failed.
+    ///
+    /// # Errors
+    /// `Error::Allocation` if the protection couldn't be changed or
+    /// `Error::MemoryError` if the bytes couldn't be written.
+    pub fn restore(mut self) -> Result<(), Error> {
+        let original = std::mem::take(&mut self.original);
+
+        self.process.write_code(self.address, &original)
+    }
+}
+
+impl Drop for Patch<'_> {
+    fn drop(&mut self) {
+        // Empty if `restore` already wrote the original bytes back
+        if !self.original.is_empty() {
+            // Nothing useful can be done if restoring fails inside of drop
+            let _ = self.process.write_code(self.address, &self.original);
+        }
+    }
+}
+
+impl Process {
+    /// Overwrites the memory at `address` with `bytes` even if it isn't
+    /// writable, the original bytes are restored once the returned `Patch` is
+    /// dropped.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the original bytes couldn't be read or the new
+    /// ones couldn't be written, or `Error::Allocation` if the protection
+    /// couldn't be changed.
+    pub fn patch(&self, address: usize, bytes: &[u8]) -> Result<Patch<'_>, Error> {
+        let mut original = vec![0; bytes.len()];
+        self.read_bytes(address, &mut original)?;
+
+        self.write_code(address, bytes)?;
+
+        Ok(Patch {
+            process: self,
+            address,
+            original,
+        })
+    }
+
+    /// Overwrites `len` bytes at `address` with `nop` instructions, see
+    /// `patch`.
+    ///
+    /// # Errors
+    /// Any error `patch` returns.
+    pub fn nop(&self, address: usize, len: usize) -> Result<Patch<'_>, Error> { self.patch(address, &vec![0x90; len]) }
+
+    /// Overwrites the `count` whole instructions at `address` with `nop`
+    /// instructions and returns how many bytes were overwritten, so the
+    /// instruction after them is never cut in half like it can be when
+    /// guessing the length for `nop`.
+    ///
+    /// Unlike `nop` the original bytes aren't kept, so nothing is restored
+    /// afterwards.
+    ///
+    /// # Errors
+    /// `Error::InvalidArgument` if one of the instructions couldn't be
+    /// decoded, `Error::MemoryError` if they couldn't be read or written, or
+    /// `Error::Allocation` if their protection couldn't be changed.
+    pub fn nop_instructions(&self, address: usize, count: usize) -> Result<usize, Error> {
+        let architecture = self.architecture()?;
+
+        let mut code = vec![0; count.saturating_mul(MAX_LENGTH)];
+        let read = self.read_into(address, &mut code)?;
+        code.truncate(read);
+
+        let mut len = 0;
+        for _ in 0..count {
+            len += code
+                .get(len..)
+                .and_then(|bytes| instruction_length(bytes, architecture))
+                .ok_or(Error::InvalidArgument("an instruction at the address couldn't be decoded"))?;
+        }
+
+        self.write_code(address, &vec![0x90; len])?;
+        Ok(len)
+    }
+
+    /// Points the `call rel32` or `jmp rel32` at `instruction_address` at
+    /// `new_target` by rewriting its displacement, and returns where it
+    /// pointed before so it can be pointed back.
+    ///
+    /// Only the 4 bytes of the displacement are written, which is a lot less
+    /// than an inline hook when a single call site should go somewhere else.
+    ///
+    /// # Errors
+    /// `Error::InvalidArgument` if there is no `E8` or `E9` instruction at
+    /// `instruction_address` or `new_target` is more than 2GB away from it,
+    /// `Error::MemoryError` if the instruction couldn't be read or written, or
+    /// `Error::Allocation` if its protection couldn't be changed.
+    pub fn redirect_call(&self, instruction_address: usize, new_target: usize) -> Result<usize, Error> {
+        /// The opcode byte followed by the displacement.
+        const INSTRUCTION_SIZE: usize = 5;
+
+        let instruction: [u8; INSTRUCTION_SIZE] = self.read(instruction_address)?;
+        if !matches!(instruction[0], 0xE8 | 0xE9) {
+            return Err(Error::InvalidArgument("the instruction isn't a call rel32 or jmp rel32"));
+        }
+
+        let end = instruction_address.wrapping_add(INSTRUCTION_SIZE);
+        let old_displacement = i32::from_le_bytes([instruction[1], instruction[2], instruction[3], instruction[4]]);
+        let mut old_target = relative_target(instruction_address, INSTRUCTION_SIZE, old_displacement);
+
+        let displacement = if self.architecture()? == Architecture::X86 {
+            // rel32 reaches the whole address space of a 32-bit process, the
+            // displacement just wraps around
+            old_target &= 0xFFFF_FFFF;
+            (new_target as u32).wrapping_sub(end as u32) as i32
+        } else {
+            i32::try_from((new_target as i64).wrapping_sub(end as i64))
+                .map_err(|_| Error::InvalidArgument("the new target is out of reach of a rel32"))?
+        };
+
+        self.write_code(instruction_address + 1, &displacement.to_le_bytes())?;
+        Ok(old_target)
+    }
+
+    /// Writes `bytes` to `address` after making it writable, then puts the
+    /// protection back to what it was before and flushes the instruction
+    /// cache in case we wrote over code.
+    fn write_code(&self, address: usize, bytes: &[u8]) -> Result<(), Error> {
+        let guard = self.protect_guard(address, bytes.len(), Protection::ExecuteReadWrite)?;
+        let written = self.write_bytes(address, bytes);
+
+        guard.restore()?;
+        written?;
+
+        flush_instruction_cache(self.open_handle()?, address as LPCVOID, bytes.len())
+    }
+}
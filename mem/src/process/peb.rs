@@ -0,0 +1,202 @@
+use std::mem::size_of;
+
+use windows::Win32::System::Threading::{ProcessBasicInformation, ProcessWow64Information};
+
+use super::{Architecture, Process};
+use crate::{
+    error::Error,
+    module::Module,
+    windows::wrappers::{nt_query_information_process, ProcessBasicInformation as BasicInformation},
+};
+
+/// Offsets into the PEB and the loader structures of one architecture.
+struct Layout {
+    /// `PEB::BeingDebugged`
+    being_debugged: usize,
+    /// `PEB::Ldr`
+    ldr: usize,
+    /// `PEB::ProcessParameters`
+    process_parameters: usize,
+    /// `RTL_USER_PROCESS_PARAMETERS::CommandLine`
+    command_line: usize,
+    /// `PEB_LDR_DATA::InLoadOrderModuleList`
+    in_load_order_module_list: usize,
+    /// `LDR_DATA_TABLE_ENTRY::DllBase`
+    dll_base: usize,
+    /// `LDR_DATA_TABLE_ENTRY::SizeOfImage`
+    size_of_image: usize,
+    /// `LDR_DATA_TABLE_ENTRY::FullDllName`
+    full_dll_name: usize,
+    /// `LDR_DATA_TABLE_ENTRY::BaseDllName`
+    base_dll_name: usize,
+    /// `UNICODE_STRING::Buffer`
+    string_buffer: usize,
+}
+
+const LAYOUT_64: Layout = Layout {
+    being_debugged: 0x2,
+    ldr: 0x18,
+    process_parameters: 0x20,
+    command_line: 0x70,
+    in_load_order_module_list: 0x10,
+    dll_base: 0x30,
+    size_of_image: 0x40,
+    full_dll_name: 0x48,
+    base_dll_name: 0x58,
+    string_buffer: 0x8,
+};
+
+const LAYOUT_32: Layout = Layout {
+    being_debugged: 0x2,
+    ldr: 0xC,
+    process_parameters: 0x10,
+    command_line: 0x40,
+    in_load_order_module_list: 0xC,
+    dll_base: 0x18,
+    size_of_image: 0x20,
+    full_dll_name: 0x24,
+    base_dll_name: 0x2C,
+    string_buffer: 0x4,
+};
+
+const fn layout(architecture: Architecture) -> &'static Layout {
+    match architecture {
+        Architecture::X86 => &LAYOUT_32,
+        Architecture::X64 => &LAYOUT_64,
+    }
+}
+
+/// Stops walking a module list that loops without ever getting back to its
+/// head, which can happen if it is read while the loader changes it.
+const MAX_MODULES: usize = 4096;
+
+impl Process {
+    /// The address of the process environment block (PEB) of the process,
+    /// which holds things like the image base, the command line and the list
+    /// of loaded modules.
+    ///
+    /// This is the PEB of the architecture of the process. A 32 bit process
+    /// running under WOW64 also has a 64 bit PEB, but its loader only keeps
+    /// the 32 bit modules in the 32 bit one so that is the one returned.
+    ///
+    /// # Errors
+    /// `Error::Windows` if `NtQueryInformationProcess` failed, the handle
+    /// needs `PROCESS_QUERY_INFORMATION` or
+    /// `PROCESS_QUERY_LIMITED_INFORMATION`, or `Error::ProcessError` if the
+    /// architecture of the process couldn't be determined.
+    pub fn peb_base(&self) -> Result<usize, Error> {
+        if Architecture::HOST == Architecture::X64 && self.architecture()? == Architecture::X86 {
+            let mut peb: usize = 0;
+            nt_query_information_process(
+                self.open_handle()?,
+                ProcessWow64Information,
+                (&raw mut peb).cast(),
+                size_of::<usize>() as u32,
+                None,
+            )?;
+
+            return Ok(peb);
+        }
+
+        let mut information = BasicInformation::default();
+        nt_query_information_process(
+            self.open_handle()?,
+            ProcessBasicInformation,
+            (&raw mut information).cast(),
+            size_of::<BasicInformation>() as u32,
+            None,
+        )?;
+
+        Ok(information.PebBaseAddress as usize)
+    }
+
+    /// The PEB of the process together with the layout of its structures.
+    fn peb(&self) -> Result<(usize, &'static Layout), Error> { Ok((self.peb_base()?, layout(self.architecture()?))) }
+
+    /// Whether the `BeingDebugged` flag in the PEB of the process is set,
+    /// which is what `IsDebuggerPresent` checks inside of the process.
+    ///
+    /// Debuggers set it when they attach, but anything that can write to the
+    /// process can clear it, so comparing it with `is_debugged` can tell you
+    /// if someone is hiding a debugger.
+    ///
+    /// # Errors
+    /// Any error `peb_base` returns, or `Error::MemoryError` if the flag
+    /// couldn't be read.
+    pub fn peb_being_debugged(&self) -> Result<bool, Error> {
+        let (peb, layout) = self.peb()?;
+
+        Ok(self.read::<u8>(peb + layout.being_debugged)? != 0)
+    }
+
+    /// The command line the process was started with, including the path of
+    /// the executable, read from the process parameters in its PEB.
+    ///
+    /// The process can overwrite its own command line, in which case this is
+    /// what it was changed to.
+    ///
+    /// # Errors
+    /// Any error `peb_base` returns, or `Error::MemoryError` if the process
+    /// parameters couldn't be read.
+    pub fn command_line(&self) -> Result<String, Error> {
+        let (peb, layout) = self.peb()?;
+        let parameters = self.read_ptr(peb + layout.process_parameters)?;
+        // The parameters are only set up once the process started running
+        if parameters == 0 {
+            return Ok(String::new());
+        }
+
+        self.read_unicode_string(parameters + layout.command_line, layout)
+    }
+
+    /// Every module of the process read straight from the loader's
+    /// `InLoadOrderModuleList` in the PEB instead of from a toolhelp
+    /// snapshot, for when creating a snapshot fails or is blocked.
+    ///
+    /// Like `peb_base` this reads the PEB of the architecture of the process,
+    /// so the modules of a 32 bit process are its 32 bit ones. The list is
+    /// read while the process keeps running so modules that are loaded or
+    /// unloaded at the same time can be missed.
+    ///
+    /// # Errors
+    /// Any error `peb_base` returns, or `Error::MemoryError` if the loader
+    /// structures couldn't be read.
+    pub fn modules_via_peb(&self) -> Result<Vec<Module>, Error> {
+        let (peb, layout) = self.peb()?;
+        let ldr = self.read_ptr(peb + layout.ldr)?;
+        // The loader data is only set up once the process started running
+        if ldr == 0 {
+            return Ok(Vec::new());
+        }
+
+        let head = ldr + layout.in_load_order_module_list;
+        let mut modules = Vec::new();
+        // InLoadOrderLinks is the first field of an entry, so a link is also
+        // the address of its entry
+        let mut entry = self.read_ptr(head)?;
+        while entry != head && entry != 0 && modules.len() < MAX_MODULES {
+            modules.push(Module {
+                base: self.read_ptr(entry + layout.dll_base)?,
+                size: self.read::<u32>(entry + layout.size_of_image)? as usize,
+                name: self.read_unicode_string(entry + layout.base_dll_name, layout)?,
+                path: self.read_unicode_string(entry + layout.full_dll_name, layout)?,
+            });
+
+            entry = self.read_ptr(entry)?;
+        }
+
+        Ok(modules)
+    }
+
+    /// Reads the `UNICODE_STRING` at `address`, its length is in bytes and
+    /// doesn't include a null terminator.
+    fn read_unicode_string(&self, address: usize, layout: &Layout) -> Result<String, Error> {
+        let length = self.read::<u16>(address)?;
+        if length == 0 {
+            return Ok(String::new());
+        }
+
+        let buffer = self.read_ptr(address + layout.string_buffer)?;
+        self.read_wstring(buffer, usize::from(length) / 2)
+    }
+}
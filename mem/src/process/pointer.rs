@@ -0,0 +1,179 @@
+use std::convert::TryFrom;
+
+use windows::Win32::Foundation::ERROR_INVALID_ADDRESS;
+
+use super::{batch::ReadRequest, region::is_user_range, Architecture, Process};
+use crate::{error::Error, scanner::relative_target};
+
+impl Process {
+    /// Reads a pointer of the process at `address`, which is 4 bytes in a 32
+    /// bit process and 8 bytes in a 64 bit one, even if we are 64 bit.
+    ///
+    /// The architecture is looked up on every call, `read_pointer_chain` only
+    /// looks it up once for the whole chain.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the read fails, or `Error::ProcessError` if the
+    /// architecture of the process couldn't be determined.
+    pub fn read_ptr(&self, address: usize) -> Result<usize, Error> {
+        let architecture = self.architecture()?;
+
+        let mut bytes = [0; 8];
+        self.read_bytes(address, &mut bytes[..architecture.pointer_size()])?;
+
+        pointer_from_bytes(bytes, architecture)
+    }
+
+    /// Follows a chain of pointers starting at `base` and returns the final
+    /// address.
+    ///
+    /// The pointer at `base` is read first, then every offset except for the
+    /// last is added to the current pointer which is dereferenced again. The
+    /// last offset is only added, so the result is the address of the value
+    /// the chain points to just like a pointer in cheat engine.
+    ///
+    /// Pointers are read as 4 or 8 bytes depending on the architecture of the
+    /// process, so a 64 bit build can follow chains in a 32 bit process. Like
+    /// in `is_readable`, addresses that can never be valid fail right away
+    /// without a read.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if any of the reads fail or a pointer along the
+    /// chain is null, or `Error::ProcessError` if the architecture of the
+    /// process couldn't be determined.
+    pub fn read_pointer_chain(&self, base: usize, offsets: &[usize]) -> Result<usize, Error> {
+        let architecture = self.architecture()?;
+        let mut address = self.read_non_null(base, architecture)?;
+
+        if let Some((last, offsets)) = offsets.split_last() {
+            for offset in offsets {
+                address = self.read_non_null(address.wrapping_add(*offset), architecture)?;
+            }
+
+            address = address.wrapping_add(*last);
+        }
+
+        Ok(address)
+    }
+
+    /// Follows the same chain of `offsets` from every address in `bases`, see
+    /// `read_pointer_chain`, returning the result of each chain in the same
+    /// order as `bases`.
+    ///
+    /// The chains are followed one level at a time and every level is read
+    /// with a single `read_batch`, which is a lot faster than following every
+    /// chain on its own when the pointers are close to each other, like the
+    /// entries of an entity list. A chain that fails stops there without
+    /// affecting the others.
+    ///
+    /// # Errors
+    /// Every chain fails with the error `read_pointer_chain` would return, if
+    /// the architecture of the process couldn't be determined all of them
+    /// fail.
+    pub fn read_pointer_chains(&self, bases: &[usize], offsets: &[usize]) -> Vec<Result<usize, Error>> {
+        let Ok(architecture) = self.architecture() else {
+            return bases.iter().map(|&base| self.read_pointer_chain(base, offsets)).collect();
+        };
+
+        let mut addresses: Vec<_> = bases.iter().map(|&base| Ok(base)).collect();
+        self.read_non_null_all(&mut addresses, architecture);
+
+        if let Some((last, offsets)) = offsets.split_last() {
+            for offset in offsets {
+                for address in addresses.iter_mut().flatten() {
+                    *address = address.wrapping_add(*offset);
+                }
+                self.read_non_null_all(&mut addresses, architecture);
+            }
+
+            for address in addresses.iter_mut().flatten() {
+                *address = address.wrapping_add(*last);
+            }
+        }
+
+        addresses
+    }
+
+    /// Resolves a relative operand, like the displacement of a
+    /// `mov rax, [rip+disp32]` or the target of a `call rel32`, found with
+    /// `scan_module`.
+    ///
+    /// The 4 byte displacement is read at `address + offset` and added to the
+    /// end of the instruction, which is `instruction_size` bytes long.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the displacement couldn't be read.
+    pub fn resolve_relative(&self, address: usize, offset: usize, instruction_size: usize) -> Result<usize, Error> {
+        let displacement = self.read::<i32>(address.wrapping_add(offset))?;
+
+        Ok(relative_target(address, instruction_size, displacement))
+    }
+
+    /// Reads a pointer of the size `architecture` uses at `address` making sure
+    /// that it isn't null.
+    fn read_non_null(&self, address: usize, architecture: Architecture) -> Result<usize, Error> {
+        if !is_user_range(address, architecture.pointer_size()) {
+            return Err(Error::MemoryError(ERROR_INVALID_ADDRESS));
+        }
+
+        let mut bytes = [0; 8];
+        self.read_bytes(address, &mut bytes[..architecture.pointer_size()])?;
+
+        non_null_pointer(bytes, architecture)
+    }
+
+    /// Replaces every address in `addresses` that is still `Ok` with the
+    /// pointer it points to, like `read_non_null`, using a single
+    /// `read_batch`.
+    fn read_non_null_all(&self, addresses: &mut [Result<usize, Error>], architecture: Architecture) {
+        let size = architecture.pointer_size();
+        for address in addresses.iter_mut() {
+            if matches!(address, Ok(address) if !is_user_range(*address, size)) {
+                *address = Err(Error::MemoryError(ERROR_INVALID_ADDRESS));
+            }
+        }
+        let mut buffers: Vec<[u8; 8]> = vec![[0; 8]; addresses.len()];
+
+        let mut requests: Vec<_> = addresses
+            .iter()
+            .zip(buffers.iter_mut())
+            .filter_map(|(address, buffer)| {
+                address
+                    .as_ref()
+                    .ok()
+                    .map(move |&address| ReadRequest::new(address, &mut buffer[..size]))
+            })
+            .collect();
+        let results = self.read_batch(&mut requests);
+
+        // There is a result for every address that was still Ok
+        for ((address, buffer), result) in addresses
+            .iter_mut()
+            .zip(buffers)
+            .filter(|(address, _)| address.is_ok())
+            .zip(results)
+        {
+            *address = result.and_then(|()| non_null_pointer(buffer, architecture));
+        }
+    }
+}
+
+/// Turns the first 4 or 8 bytes of `bytes`, depending on `architecture`, into a
+/// pointer.
+fn pointer_from_bytes(bytes: [u8; 8], architecture: Architecture) -> Result<usize, Error> {
+    match architecture {
+        Architecture::X86 => Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize),
+        // A 32 bit build can't use a pointer that doesn't fit into a usize
+        Architecture::X64 => {
+            usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| Error::MemoryError(ERROR_INVALID_ADDRESS))
+        },
+    }
+}
+
+/// Like `pointer_from_bytes` but making sure that the pointer isn't null.
+fn non_null_pointer(bytes: [u8; 8], architecture: Architecture) -> Result<usize, Error> {
+    match pointer_from_bytes(bytes, architecture)? {
+        0 => Err(Error::MemoryError(ERROR_INVALID_ADDRESS)),
+        pointer => Ok(pointer),
+    }
+}
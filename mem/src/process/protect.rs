@@ -0,0 +1,193 @@
+use super::{MemoryAccess, Process, Protection};
+use crate::{
+    error::Error,
+    windows::wrappers::{
+        flush_instruction_cache, virtual_protect, virtual_protect_ex, Handle, PageProtectionFlags, LPCVOID, LPVOID,
+    },
+};
+
+/// Changed page protection that is put back the way it was once the
+/// `ProtectGuard` is dropped.
+///
+/// Created with `Process::protect_guard` for another process or
+/// `ProtectGuard::local` for our own.
+pub struct ProtectGuard<'a> {
+    /// `None` for our own process.
+    process: Option<&'a Process>,
+    address: usize,
+    size: usize,
+    old_protection: PageProtectionFlags,
+    restored: bool,
+}
+
+impl ProtectGuard<'static> {
+    /// Changes the protection of `size` bytes at `address` in our own process
+    /// to `protection` until the guard is dropped.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the protection couldn't be changed.
+    pub fn local(address: usize, size: usize, protection: Protection) -> Result<Self, Error> {
+        let mut old_protection = 0;
+        virtual_protect(address as LPVOID, size, protection.flags(), &raw mut old_protection)?;
+
+        Ok(Self {
+            process: None,
+            address,
+            size,
+            old_protection,
+            restored: false,
+        })
+    }
+}
+
+impl ProtectGuard<'_> {
+    /// The start of the pages whose protection was changed.
+    #[must_use]
+    pub const fn address(&self) -> usize { self.address }
+
+    /// How many bytes from `address` had their protection changed.
+    #[must_use]
+    pub const fn size(&self) -> usize { self.size }
+
+    /// The raw `PAGE_*` flags the pages had before, if the range covered pages
+    /// with different protections this is the protection of the first page.
+    #[must_use]
+    pub const fn old_protection(&self) -> PageProtectionFlags { self.old_protection }
+
+    /// Puts the old protection back, unlike dropping the guard this lets you
+    /// know if it failed.
+    ///
+    /// # Errors
+    /// `Error::Allocation` or `Error::MemoryError` if the protection couldn't
+    /// be changed back.
+    pub fn restore(mut self) -> Result<(), Error> { self.restore_protection() }
+
+    fn restore_protection(&mut self) -> Result<(), Error> {
+        if self.restored {
+            return Ok(());
+        }
+        self.restored = true;
+
+        let mut ignored = 0;
+        match self.process {
+            Some(process) => virtual_protect_ex(
+                process.handle(),
+                self.address as LPVOID,
+                self.size,
+                self.old_protection,
+                &raw mut ignored,
+            ),
+            None => virtual_protect(self.address as LPVOID, self.size, self.old_protection, &raw mut ignored),
+        }
+    }
+}
+
+impl Drop for ProtectGuard<'_> {
+    fn drop(&mut self) {
+        // Nothing useful can be done if restoring fails inside of drop
+        let _ = self.restore_protection();
+    }
+}
+
+impl Process {
+    /// Changes the protection of `size` bytes at `address` to `protection`
+    /// until the returned guard is dropped, which puts the old protection
+    /// back.
+    ///
+    /// # Errors
+    /// `Error::Allocation` if the protection couldn't be changed.
+    pub fn protect_guard(&self, address: usize, size: usize, protection: Protection) -> Result<ProtectGuard<'_>, Error> {
+        let mut old_protection = 0;
+        virtual_protect_ex(
+            self.open_handle()?,
+            address as LPVOID,
+            size,
+            protection.flags(),
+            &raw mut old_protection,
+        )?;
+
+        Ok(ProtectGuard {
+            process: Some(self),
+            address,
+            size,
+            old_protection,
+            restored: false,
+        })
+    }
+
+    /// Writes `value` to `address` even if the pages there aren't writable,
+    /// their protection is changed for the write and restored right after.
+    ///
+    /// This is the easy way to patch code or read only globals, pages that
+    /// are already writable are written to directly.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the pages couldn't be queried or written,
+    /// `Error::Allocation` if their protection couldn't be changed, or
+    /// `Error::InvalidArgument` if the write would wrap around the address
+    /// space.
+    pub fn write_protected<T: Copy>(&self, address: usize, value: &T) -> Result<(), Error> {
+        let bytes = unsafe { std::slice::from_raw_parts(std::ptr::from_ref(value).cast::<u8>(), std::mem::size_of::<T>()) };
+
+        self.write_bytes_protected(address, bytes)
+    }
+
+    /// Writes all of `bytes` to `address`, see `write_protected`.
+    pub(crate) fn write_bytes_protected(&self, address: usize, bytes: &[u8]) -> Result<(), Error> {
+        write_bytes_protected(self, self.open_handle()?, address, bytes, |address, size, protection| {
+            self.protect_guard(address, size, protection)
+        })
+    }
+}
+
+/// Writes all of `bytes` to `address` through `memory`, the protection of every
+/// region that isn't writable is changed with `protect` for the write and
+/// restored right after. `process` is the handle the instruction cache is
+/// flushed for if any of the bytes are executable.
+///
+/// This is `write_bytes_protected` of both `Process` and `LocalProcess`, which
+/// only differ in how they create a `ProtectGuard`.
+pub(super) fn write_bytes_protected<'a>(
+    memory: &impl MemoryAccess,
+    process: Handle,
+    address: usize,
+    bytes: &[u8],
+    protect: impl Fn(usize, usize, Protection) -> Result<ProtectGuard<'a>, Error>,
+) -> Result<(), Error> {
+    let end = address
+        .checked_add(bytes.len())
+        .ok_or(Error::InvalidArgument("the write wraps around the address space"))?;
+
+    // Every region the write touches keeps its own protection
+    let mut guards = Vec::new();
+    let mut executable = false;
+    let mut current = address;
+    while current < end {
+        let region = memory.query(current)?;
+        executable |= region.is_executable;
+
+        if !region.is_writable {
+            let protection = if region.is_executable {
+                Protection::ExecuteReadWrite
+            } else {
+                Protection::ReadWrite
+            };
+            guards.push(protect(current, region.end().min(end) - current, protection)?);
+        }
+
+        current = region.end();
+    }
+
+    let written = memory.write_bytes(address, bytes);
+
+    // The guards that aren't restored here are restored once they drop
+    let restored = guards.into_iter().try_for_each(ProtectGuard::restore);
+    written?;
+    restored?;
+
+    if executable {
+        flush_instruction_cache(process, address as LPCVOID, bytes.len())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use std::path::PathBuf;
+
+use windows::Win32::System::Memory::{
+    MEM_COMMIT, MEM_PRIVATE, PAGE_EXECUTE, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE, PAGE_EXECUTE_WRITECOPY, PAGE_GUARD,
+    PAGE_READONLY, PAGE_READWRITE, PAGE_WRITECOPY,
+};
+
+use super::Process;
+use crate::{
+    error::Error,
+    windows::{
+        memory::{self, memory_regions, RegionIter},
+        utils::device_path_to_dos_path,
+        wrappers::{get_mapped_file_name, MemoryBasicInformation, LPCVOID},
+    },
+};
+
+/// The highest address user mode code can use, anything above it belongs to
+/// the kernel.
+#[cfg(target_pointer_width = "64")]
+pub const MAX_USER_ADDRESS: usize = 0x7FFF_FFFF_0000;
+#[cfg(target_pointer_width = "32")]
+pub const MAX_USER_ADDRESS: usize = 0xFFFF_0000;
+/// The lowest address memory can be allocated at, the first 64KB are always
+/// reserved so accesses through null pointers fault.
+const MIN_USER_ADDRESS: usize = 0x1_0000;
+
+/// Whether all `size` bytes at `address` are in the part of the address space
+/// a process can allocate memory in, which rules out null pointers, null
+/// pointers plus an offset and kernel addresses without asking windows.
+pub const fn is_user_range(address: usize, size: usize) -> bool {
+    address >= MIN_USER_ADDRESS && matches!(address.checked_add(size), Some(end) if end <= MAX_USER_ADDRESS)
+}
+
+/// A range of pages in a process that share the same state and protection,
+/// created by `Process::query` or `Process::regions`.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryRegion {
+    /// The address the region starts at.
+    pub base: usize,
+    /// The size of the region in bytes.
+    pub size: usize,
+    /// Whether the pages are backed by memory, reserved and free regions
+    /// can't be accessed at all.
+    pub is_committed: bool,
+    pub is_readable: bool,
+    /// Copy on write pages count as writable.
+    pub is_writable: bool,
+    pub is_executable: bool,
+    /// Guard pages raise an exception the first time they are accessed, so
+    /// they shouldn't be read even if they are readable.
+    pub is_guard: bool,
+    /// Whether the memory belongs to the process alone, which is the case for
+    /// heaps and stacks but not for mapped images and files.
+    pub is_private: bool,
+}
+
+impl MemoryRegion {
+    /// Whether `address` is inside of the region.
+    #[must_use]
+    pub const fn contains(&self, address: usize) -> bool { address >= self.base && address - self.base < self.size }
+
+    /// The address right after the end of the region.
+    #[must_use]
+    pub const fn end(&self) -> usize { self.base.saturating_add(self.size) }
+}
+
+impl From<&MemoryBasicInformation> for MemoryRegion {
+    fn from(info: &MemoryBasicInformation) -> Self {
+        let protect = info.Protect;
+        let has = |flags: &[u32]| flags.iter().any(|&flag| protect & flag != 0);
+
+        Self {
+            base: info.BaseAddress as usize,
+            size: info.RegionSize,
+            is_committed: info.State == MEM_COMMIT,
+            is_readable: has(&[
+                PAGE_READONLY,
+                PAGE_READWRITE,
+                PAGE_WRITECOPY,
+                PAGE_EXECUTE_READ,
+                PAGE_EXECUTE_READWRITE,
+                PAGE_EXECUTE_WRITECOPY,
+            ]),
+            is_writable: has(&[PAGE_READWRITE, PAGE_WRITECOPY, PAGE_EXECUTE_READWRITE, PAGE_EXECUTE_WRITECOPY]),
+            is_executable: has(&[
+                PAGE_EXECUTE,
+                PAGE_EXECUTE_READ,
+                PAGE_EXECUTE_READWRITE,
+                PAGE_EXECUTE_WRITECOPY,
+            ]),
+            is_guard: protect & PAGE_GUARD != 0,
+            is_private: info.Type == MEM_PRIVATE,
+        }
+    }
+}
+
+/// Decides which regions `Process::regions_matching` yields. Every filter
+/// only matches committed memory, filters can be combined with `and`.
+///
+/// ```ignore
+/// // Every region that is writable and executable at the same time
+/// let filter = RegionFilter::executable().and(RegionFilter::writable());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionFilter {
+    readable: Option<bool>,
+    writable: Option<bool>,
+    executable: Option<bool>,
+    guard: Option<bool>,
+    private: Option<bool>,
+}
+
+impl RegionFilter {
+    /// Matches every committed region.
+    #[must_use]
+    pub const fn committed() -> Self {
+        Self {
+            readable: None,
+            writable: None,
+            executable: None,
+            guard: None,
+            private: None,
+        }
+    }
+
+    /// Matches regions that can be read without running into a guard page.
+    #[must_use]
+    pub const fn readable() -> Self {
+        Self {
+            readable: Some(true),
+            guard: Some(false),
+            ..Self::committed()
+        }
+    }
+
+    /// Matches writable regions.
+    #[must_use]
+    pub const fn writable() -> Self {
+        Self {
+            writable: Some(true),
+            ..Self::committed()
+        }
+    }
+
+    /// Matches executable regions.
+    #[must_use]
+    pub const fn executable() -> Self {
+        Self {
+            executable: Some(true),
+            ..Self::committed()
+        }
+    }
+
+    /// Matches private memory, like heaps and stacks.
+    #[must_use]
+    pub const fn private() -> Self {
+        Self {
+            private: Some(true),
+            ..Self::committed()
+        }
+    }
+
+    /// Matches regions both filters match, if they disagree `other` wins.
+    #[must_use]
+    pub const fn and(self, other: Self) -> Self {
+        const fn pick(ours: Option<bool>, theirs: Option<bool>) -> Option<bool> {
+            match theirs {
+                Some(_) => theirs,
+                None => ours,
+            }
+        }
+
+        Self {
+            readable: pick(self.readable, other.readable),
+            writable: pick(self.writable, other.writable),
+            executable: pick(self.executable, other.executable),
+            guard: pick(self.guard, other.guard),
+            private: pick(self.private, other.private),
+        }
+    }
+
+    /// Whether `region` passes the filter.
+    #[must_use]
+    pub const fn matches(&self, region: &MemoryRegion) -> bool {
+        const fn check(expected: Option<bool>, actual: bool) -> bool {
+            match expected {
+                Some(expected) => expected == actual,
+                None => true,
+            }
+        }
+
+        region.is_committed
+            && check(self.readable, region.is_readable)
+            && check(self.writable, region.is_writable)
+            && check(self.executable, region.is_executable)
+            && check(self.guard, region.is_guard)
+            && check(self.private, region.is_private)
+    }
+}
+
+impl Process {
+    /// Queries the region of pages `address` is in.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the region couldn't be queried, which happens
+    /// when `address` is above the highest address the process can use.
+    pub fn query(&self, address: usize) -> Result<MemoryRegion, Error> { memory::query(self.open_handle()?, address) }
+
+    /// The path of the file that backs the region `address` is in, like the
+    /// DLL of a mapped image, or `None` for private memory like heaps and
+    /// stacks.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the region couldn't be queried or
+    /// `Error::ProcessError` if the name of the file couldn't be retrieved.
+    pub fn region_file(&self, address: usize) -> Result<Option<PathBuf>, Error> {
+        let region = self.query(address)?;
+        if region.is_private || !region.is_committed {
+            return Ok(None);
+        }
+
+        let path = get_mapped_file_name(self.open_handle()?, address as LPCVOID)?;
+        Ok(Some(device_path_to_dos_path(&path)))
+    }
+
+    /// Whether all `size` bytes at `address` are committed and readable, so
+    /// reading them can only fail if the process frees or protects them right
+    /// after.
+    ///
+    /// Addresses that can never be valid, like null pointers, are rejected
+    /// without querying the process. Guard pages don't count as readable.
+    #[must_use]
+    pub fn is_readable(&self, address: usize, size: usize) -> bool {
+        self.open_handle()
+            .is_ok_and(|handle| memory::is_address_readable(handle, address, size))
+    }
+
+    /// Every region of the address space of the process from the bottom up,
+    /// including free and reserved ones.
+    pub fn regions(&self) -> RegionIter {
+        // A closed process has a null handle which fails the first query
+        memory_regions(*self.handle)
+    }
+
+    /// Every region that passes `filter`, see `regions`.
+    pub fn regions_matching(&self, filter: RegionFilter) -> impl Iterator<Item = MemoryRegion> + '_ {
+        self.regions().filter(move |region| filter.matches(region))
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::PoisonError;
+
+use super::Process;
+use crate::error::Error;
+
+impl Process {
+    /// The base address of the module named `name`, which is looked up once
+    /// and then cached until `refresh_modules` is called.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have a module with that
+    /// name.
+    pub fn module_base(&self, name: &str) -> Result<usize, Error> {
+        let key = name.to_ascii_lowercase();
+        if let Some(&base) = self.module_bases.lock().unwrap_or_else(PoisonError::into_inner).get(&key) {
+            return Ok(base);
+        }
+
+        let (base, _) = self.module_bounds(name)?;
+        self.module_bases
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, base);
+
+        Ok(base)
+    }
+
+    /// Forgets every base address `module_base` cached, call it after a module
+    /// was unloaded and loaded again since it is likely at a different address
+    /// now.
+    pub fn refresh_modules(&self) { self.module_bases.lock().unwrap_or_else(PoisonError::into_inner).clear(); }
+
+    /// Sets whether `read_rel` and `write_rel` should look the module up again
+    /// when an access through its cached base fails, which is on by default.
+    ///
+    /// A module that was unloaded and loaded again is usually at a different
+    /// address, so the cached base is stale. Turn this off if a failed access
+    /// should fail right away instead.
+    pub const fn set_refresh_stale_modules(&mut self, enabled: bool) { self.refresh_stale_modules = enabled; }
+
+    /// Reads a `T` at `offset` bytes into the module named `module`, which is
+    /// how offsets like `game.exe+0x1A2B3C` are usually written down.
+    ///
+    /// If the read fails the module is looked up again and the read is retried
+    /// once if it moved, see `set_refresh_stale_modules`.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have the module, or
+    /// `Error::MemoryError` if the read fails.
+    pub fn read_rel<T: Copy>(&self, module: &str, offset: usize) -> Result<T, Error> {
+        self.with_module_base(module, |base| self.read(base + offset))
+    }
+
+    /// Writes `value` at `offset` bytes into the module named `module`, see
+    /// `read_rel`. A failed write is retried the same way.
+    ///
+    /// # Errors
+    /// `Error::ModuleNotFound` if the process doesn't have the module, or
+    /// `Error::MemoryError` if the write fails.
+    pub fn write_rel<T: Copy>(&self, module: &str, offset: usize, value: &T) -> Result<(), Error> {
+        self.with_module_base(module, |base| self.write(base + offset, value))
+    }
+
+    /// Calls `f` with the base of `module`, and once more with its new base if
+    /// that fails because the cached one is stale.
+    fn with_module_base<T>(&self, module: &str, f: impl Fn(usize) -> Result<T, Error>) -> Result<T, Error> {
+        let base = self.module_base(module)?;
+        match f(base) {
+            Err(error @ Error::MemoryError(_)) if self.refresh_stale_modules => {
+                self.module_bases
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .remove(&module.to_ascii_lowercase());
+
+                let fresh = self.module_base(module)?;
+                if fresh == base {
+                    return Err(error);
+                }
+
+                f(fresh)
+            },
+            result => result,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::ops::BitOr;
+
+use windows::Win32::System::Threading::{
+    PROCESS_ALL_ACCESS, PROCESS_CREATE_THREAD, PROCESS_QUERY_INFORMATION, PROCESS_SYNCHRONIZE, PROCESS_VM_OPERATION,
+    PROCESS_VM_READ, PROCESS_VM_WRITE,
+};
+
+use crate::windows::wrappers::ProcessAccessRights;
+
+/// The rights a `Process` is opened with, the presets cover what the methods
+/// of `Process` need so you don't have to find the right `PROCESS_*` flags
+/// yourself.
+///
+/// Presets can be combined with `|`, and anything that takes an `Access` also
+/// takes raw `PROCESS_*` flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Access(ProcessAccessRights);
+
+impl Access {
+    /// Exactly the rights in `rights`.
+    #[must_use]
+    pub const fn from_raw(rights: ProcessAccessRights) -> Self { Self(rights) }
+
+    /// Enough for reading memory, querying regions, walking modules and
+    /// waiting for the process to exit.
+    #[must_use]
+    pub const fn read_only() -> Self { Self(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ | PROCESS_SYNCHRONIZE) }
+
+    /// `read_only` plus writing memory and changing page protections.
+    #[must_use]
+    pub const fn read_write() -> Self { Self(Self::read_only().0 | PROCESS_VM_WRITE | PROCESS_VM_OPERATION) }
+
+    /// Everything the injector needs, `read_write` plus creating threads.
+    #[must_use]
+    pub const fn inject() -> Self { Self(Self::read_write().0 | PROCESS_CREATE_THREAD) }
+
+    /// `PROCESS_ALL_ACCESS`, which is usually more than you need and is more
+    /// likely to be denied.
+    #[must_use]
+    pub const fn full() -> Self { Self(PROCESS_ALL_ACCESS) }
+
+    /// The raw `PROCESS_*` flags.
+    #[must_use]
+    pub const fn bits(self) -> ProcessAccessRights { self.0 }
+
+    /// Whether every right in `other` is also in `self`.
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool { self.0 & other.0 == other.0 }
+}
+
+impl BitOr for Access {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self { Self(self.0 | rhs.0) }
+}
+
+impl From<ProcessAccessRights> for Access {
+    fn from(rights: ProcessAccessRights) -> Self { Self(rights) }
+}
+
+impl From<Access> for ProcessAccessRights {
+    fn from(access: Access) -> Self { access.0 }
+}
@@ -0,0 +1,414 @@
+#[cfg(not(feature = "rayon"))]
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use super::{MemoryRegion, Process, RegionFilter};
+use crate::{error::Error, scanner::Signature};
+
+const PAGE_SIZE: usize = 0x1000;
+
+/// Where `Process::scan_module` found a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScanMatch {
+    /// The address of the match in the process.
+    pub absolute: usize,
+    /// The address the module is loaded at.
+    pub module_base: usize,
+    /// The offset of the match from the start of the module, which stays the
+    /// same when the module is loaded at a different address so it is what
+    /// you want to save.
+    pub rva: usize,
+}
+
+impl Process {
+    /// Scans the module named `module_name` for `pattern` and returns where
+    /// the first match is.
+    ///
+    /// The module is read one region at a time and regions that can't be
+    /// read, like guard pages, are skipped. A match can still span over the
+    /// boundary of two readable regions that are next to each other.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the pattern couldn't be parsed or
+    /// `Error::ModuleNotFound` if the process doesn't have the module.
+    pub fn scan_module(&self, module_name: &str, pattern: &str) -> Result<Option<ScanMatch>, Error> {
+        let signature = Signature::parse(pattern)?;
+        let (base, size) = self.module_bounds(module_name)?;
+
+        Ok(self
+            .scan_regions(base, size, &signature, false)
+            .first()
+            .map(|&absolute| ScanMatch {
+                absolute,
+                module_base: base,
+                rva: absolute - base,
+            }))
+    }
+
+    /// Like `scan_module` but only returns the offset of the match from the
+    /// start of the module.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the pattern couldn't be parsed or
+    /// `Error::ModuleNotFound` if the process doesn't have the module.
+    pub fn scan_module_rva(&self, module_name: &str, pattern: &str) -> Result<Option<usize>, Error> {
+        Ok(self.scan_module(module_name, pattern)?.map(|found| found.rva))
+    }
+
+    /// Like `scan_module` but only returns the address of the match.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the pattern couldn't be parsed or
+    /// `Error::ModuleNotFound` if the process doesn't have the module.
+    pub fn pattern_scan(&self, module_name: &str, pattern: &str) -> Result<Option<usize>, Error> {
+        Ok(self.scan_module(module_name, pattern)?.map(|found| found.absolute))
+    }
+
+    /// Scans the addresses from `start` up to but not including `end` for
+    /// `pattern` and returns the address of the first match, which is a lot
+    /// faster than scanning a whole module once you know roughly where to
+    /// look.
+    ///
+    /// Parts of the range that aren't committed or can't be read are skipped,
+    /// a match has to end before `end` to be found.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the pattern couldn't be parsed or
+    /// `Error::InvalidArgument` if `end` is below `start`.
+    pub fn scan_range(&self, start: usize, end: usize, pattern: &str) -> Result<Option<usize>, Error> {
+        Ok(self.scan_range_inner(start, end, pattern, false)?.first().copied())
+    }
+
+    /// Like `scan_range` but returns the addresses of every match in the range
+    /// instead of just the first one.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the pattern couldn't be parsed or
+    /// `Error::InvalidArgument` if `end` is below `start`.
+    pub fn scan_range_all(&self, start: usize, end: usize, pattern: &str) -> Result<Vec<usize>, Error> {
+        self.scan_range_inner(start, end, pattern, true)
+    }
+
+    fn scan_range_inner(&self, start: usize, end: usize, pattern: &str, all: bool) -> Result<Vec<usize>, Error> {
+        let signature = Signature::parse(pattern)?;
+        let size = end
+            .checked_sub(start)
+            .ok_or(Error::InvalidArgument("the end of the range is below its start"))?;
+
+        Ok(self.scan_regions(start, size, &signature, all))
+    }
+
+    /// Scans every committed region of the process for `pattern` and returns
+    /// the absolute addresses of all matches.
+    ///
+    /// Regions that are `PAGE_NOACCESS` or guard pages are skipped, as well as
+    /// any pages inside of a region that couldn't be read.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the pattern couldn't be parsed.
+    pub fn scan_all_memory(&self, pattern: &str) -> Result<Vec<usize>, Error> {
+        let signature = Signature::parse(pattern)?;
+
+        let mut matches = Vec::new();
+        for region in self.regions_matching(RegionFilter::readable()) {
+            matches.extend(self.scan_block(region.base, region.size, &signature, true));
+        }
+
+        Ok(matches)
+    }
+
+    /// Does the same as `scan_all_memory` but reads and scans the regions on
+    /// one thread per core, which is a lot faster for large processes with a
+    /// lot of regions.
+    ///
+    /// The regions are collected up front and handed out to the threads one
+    /// at a time, the matches are returned sorted by address just like
+    /// `scan_all_memory` returns them no matter how many threads there are.
+    /// With the `rayon` feature the regions are scanned on the rayon thread
+    /// pool instead of threads of our own.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the pattern couldn't be parsed.
+    pub fn scan_all_memory_parallel(&self, pattern: &str) -> Result<Vec<usize>, Error> {
+        let signature = Signature::parse(pattern)?;
+        let regions: Vec<_> = self.regions_matching(RegionFilter::readable()).collect();
+
+        let mut found = self.scan_each_region(&regions, &signature);
+
+        // Regions never overlap so sorting them sorts every match
+        found.sort_unstable_by_key(|(base, _)| *base);
+        Ok(found.into_iter().flat_map(|(_, matches)| matches).collect())
+    }
+
+    /// Scans every region on the rayon thread pool and returns the matches of
+    /// every region that has any together with its base, in no particular
+    /// order.
+    #[cfg(feature = "rayon")]
+    fn scan_each_region(&self, regions: &[MemoryRegion], signature: &Signature) -> Vec<(usize, Vec<usize>)> {
+        regions
+            .par_iter()
+            .filter_map(|region| {
+                let matches = self.scan_block(region.base, region.size, signature, true);
+                (!matches.is_empty()).then_some((region.base, matches))
+            })
+            .collect()
+    }
+
+    /// Scans every region on one thread per core and returns the matches of
+    /// every region that has any together with its base, in no particular
+    /// order.
+    #[cfg(not(feature = "rayon"))]
+    fn scan_each_region(&self, regions: &[MemoryRegion], signature: &Signature) -> Vec<(usize, Vec<usize>)> {
+        let threads = thread::available_parallelism()
+            .map_or(1, std::num::NonZeroUsize::get)
+            .min(regions.len());
+        let next = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut found = Vec::new();
+                        while let Some(region) = regions.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            let matches = self.scan_block(region.base, region.size, signature, true);
+                            if !matches.is_empty() {
+                                found.push((region.base, matches));
+                            }
+                        }
+                        found
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect()
+        })
+    }
+
+    /// Overwrites every match of `pattern` in the committed writable regions
+    /// of the process with `replacement` and returns how many were replaced.
+    ///
+    /// `replacement` has to be as long as the pattern and the bytes under the
+    /// wildcards of the pattern are left untouched. Matches that overlap an
+    /// earlier match are skipped since the earlier one already changed them.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the pattern couldn't be parsed,
+    /// `Error::InvalidArgument` if `replacement` isn't as long as the pattern
+    /// or any error `write_protected` returns, the matches that were already
+    /// replaced stay replaced.
+    pub fn replace_pattern(&self, pattern: &str, replacement: &[u8]) -> Result<usize, Error> {
+        let signature = Signature::parse(pattern)?;
+        let pattern = signature.bytes();
+        if replacement.len() != pattern.len() {
+            return Err(Error::InvalidArgument("the replacement has to be as long as the pattern"));
+        }
+
+        let mut replaced = 0;
+        let mut bytes = vec![0; pattern.len()];
+        for region in self.regions_matching(RegionFilter::readable().and(RegionFilter::writable())) {
+            let mut next = region.base;
+            for address in self.scan_block(region.base, region.size, &signature, true) {
+                if address < next {
+                    continue;
+                }
+                next = address + pattern.len();
+
+                self.read_bytes(address, &mut bytes)?;
+                for ((byte, expected), new) in bytes.iter_mut().zip(pattern).zip(replacement) {
+                    if expected.is_some() {
+                        *byte = *new;
+                    }
+                }
+
+                self.write_bytes_protected(address, &bytes)?;
+                replaced += 1;
+            }
+        }
+
+        Ok(replaced)
+    }
+
+    /// Scans every committed readable region of the process for pointers to
+    /// `target` and returns the addresses holding them, the first step of
+    /// finding a static pointer path to a value.
+    ///
+    /// Only addresses that are a multiple of `alignment` are checked, `0`
+    /// checks the addresses pointers are normally aligned to. Pointers are 4
+    /// or 8 bytes depending on the architecture of the process, like in
+    /// `read_ptr`.
+    ///
+    /// # Errors
+    /// `Error::InvalidArgument` if `alignment` is larger than a page, or
+    /// `Error::ProcessError` if the architecture of the process couldn't be
+    /// determined.
+    pub fn find_pointers_to(&self, target: usize, alignment: usize) -> Result<Vec<usize>, Error> {
+        let pointer_size = self.architecture()?.pointer_size();
+
+        let alignment = if alignment == 0 { pointer_size } else { alignment };
+        if alignment > PAGE_SIZE {
+            return Err(Error::InvalidArgument("the alignment can't be larger than a page"));
+        }
+
+        // A 32 bit process can't hold a pointer that needs more than 4 bytes
+        let target = (target as u64).to_le_bytes();
+        if target[pointer_size..].iter().any(|&byte| byte != 0) {
+            return Ok(Vec::new());
+        }
+        let target = &target[..pointer_size];
+
+        let mut pointers = Vec::new();
+        for region in self.regions_matching(RegionFilter::readable()) {
+            self.for_each_readable_run(region.base, region.size, |base, buffer| {
+                let first = (alignment - base % alignment) % alignment;
+                let last = buffer.len().saturating_sub(pointer_size);
+
+                for offset in (first..=last).step_by(alignment) {
+                    let Some(bytes) = buffer.get(offset..offset + pointer_size) else {
+                        break;
+                    };
+
+                    if bytes == target {
+                        pointers.push(base + offset);
+                    }
+                }
+
+                true
+            });
+        }
+
+        Ok(pointers)
+    }
+
+    /// Scans `size` bytes at `base` for `signature`, only the first match is
+    /// returned unless `all` is set.
+    fn scan_block(&self, base: usize, size: usize, signature: &Signature, all: bool) -> Vec<usize> {
+        let mut matches = Vec::new();
+        self.for_each_readable_run(base, size, |run_base, buffer| {
+            find_in(buffer, signature, run_base, all, &mut matches);
+            all || matches.is_empty()
+        });
+
+        matches
+    }
+
+    /// Scans the readable regions in `size` bytes at `base` for `signature`,
+    /// like `scan_block` but every region is read on its own.
+    ///
+    /// The last `pattern.len() - 1` bytes of every run of readable pages are
+    /// kept around, so matches that start in one run and end in the next one
+    /// are still found if the two are next to each other.
+    fn scan_regions(&self, base: usize, size: usize, signature: &Signature, all: bool) -> Vec<usize> {
+        let overlap = signature.bytes().len() - 1;
+        let end = base.saturating_add(size);
+
+        let mut matches = Vec::new();
+        let mut carry = Vec::with_capacity(overlap * 2);
+        let mut carry_end = 0;
+        let mut boundary = Vec::with_capacity(overlap * 2);
+
+        let mut current = base;
+        while current < end && (all || matches.is_empty()) {
+            let Ok(region) = self.query(current) else {
+                break;
+            };
+            let region_end = region.end().min(end);
+
+            if region.is_committed && region.is_readable && !region.is_guard {
+                self.for_each_readable_run(current, region_end - current, |run_base, run| {
+                    if carry_end != run_base {
+                        carry.clear();
+                    }
+
+                    // Only matches that start in the carried over bytes span
+                    // the boundary, the rest are found in the run itself
+                    if !carry.is_empty() {
+                        boundary.clear();
+                        boundary.extend_from_slice(&carry);
+                        boundary.extend_from_slice(&run[..overlap.min(run.len())]);
+
+                        let mut start = 0;
+                        while let Some(offset) = signature.find_from(&boundary, start) {
+                            if offset >= carry.len() {
+                                break;
+                            }
+                            matches.push(run_base - carry.len() + offset);
+                            if !all {
+                                return false;
+                            }
+                            start = offset + 1;
+                        }
+                    }
+
+                    find_in(run, signature, run_base, all, &mut matches);
+                    if !all && !matches.is_empty() {
+                        return false;
+                    }
+
+                    carry.extend_from_slice(&run[run.len().saturating_sub(overlap)..]);
+                    carry.drain(..carry.len().saturating_sub(overlap));
+                    carry_end = run_base + run.len();
+                    true
+                });
+            }
+
+            current = region_end;
+        }
+
+        matches
+    }
+
+    /// Reads `size` bytes at `base` and calls `f` with the address and the
+    /// bytes of every run of pages that could be read, until it returns
+    /// `false`.
+    ///
+    /// The range is read into a buffer on the heap in one go, if that fails it
+    /// is read page by page instead and any pages that can't be read are
+    /// skipped. Every run is passed on its own so nothing found in it can span
+    /// over a page we weren't able to read.
+    pub(super) fn for_each_readable_run(&self, base: usize, size: usize, mut f: impl FnMut(usize, &[u8]) -> bool) {
+        let mut buffer = vec![0; size];
+        if self.read_bytes(base, &mut buffer).is_ok() {
+            f(base, &buffer);
+            return;
+        }
+
+        let mut run_start = 0;
+        for offset in (0..size).step_by(PAGE_SIZE) {
+            let end = (offset + PAGE_SIZE).min(size);
+            if self.read_bytes(base + offset, &mut buffer[offset..end]).is_ok() {
+                continue;
+            }
+
+            if run_start < offset && !f(base + run_start, &buffer[run_start..offset]) {
+                return;
+            }
+            run_start = end;
+        }
+
+        if run_start < size {
+            f(base + run_start, &buffer[run_start..]);
+        }
+    }
+}
+
+/// Pushes the address of every match of `signature` in `buffer`, which was read
+/// from `base`, onto `matches`, stopping after the first one unless `all` is
+/// set.
+fn find_in(buffer: &[u8], signature: &Signature, base: usize, all: bool, matches: &mut Vec<usize>) {
+    let mut start = 0;
+    while let Some(offset) = signature.find_from(buffer, start) {
+        matches.push(base + offset);
+        if !all {
+            return;
+        }
+        start = offset + 1;
+    }
+}
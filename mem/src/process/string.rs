@@ -0,0 +1,31 @@
+use super::Process;
+use crate::{error::Error, windows::memory};
+
+impl Process {
+    /// Reads a null terminated string at `address`, decoding it as UTF-8 and
+    /// replacing any invalid sequences.
+    ///
+    /// At most `max_len` bytes are read, if no null terminator was found by
+    /// then the string is cut off at `max_len` so a bad pointer can't make us
+    /// read forever. A string that runs into memory that can't be read is cut
+    /// off there.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if not even the first byte could be read.
+    pub fn read_cstring(&self, address: usize, max_len: usize) -> Result<String, Error> {
+        memory::read_c_string(self.open_handle()?, address, max_len).map_err(|error| self.exited_or(error))
+    }
+
+    /// Reads a null terminated UTF-16 string at `address`, which is what most
+    /// windows functions hand back, replacing any invalid sequences.
+    ///
+    /// At most `max_len` characters are read, if no null terminator was found
+    /// by then the string is cut off at `max_len`. A string that runs into
+    /// memory that can't be read is cut off there.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if not even the first character could be read.
+    pub fn read_wstring(&self, address: usize, max_len: usize) -> Result<String, Error> {
+        memory::read_wide_string(self.open_handle()?, address, max_len).map_err(|error| self.exited_or(error))
+    }
+}
@@ -0,0 +1,120 @@
+use windows::Win32::System::Threading::THREAD_SUSPEND_RESUME;
+
+use super::{walk_threads, Process};
+use crate::{
+    error::Error,
+    windows::{
+        handle::SafeHandle,
+        wrappers::{open_thread, resume_thread, suspend_thread},
+    },
+};
+
+impl Process {
+    /// Suspends every thread of the process, which is useful to keep values
+    /// from changing while you read them.
+    ///
+    /// Threads that exit before they could be opened are skipped. Every call
+    /// has to be paired with a `resume`, which only resumes the threads of the
+    /// last `suspend` that wasn't resumed yet, so calls can be nested.
+    ///
+    /// # Errors
+    /// `Error::MemoryError` if the thread snapshot couldn't be created, or
+    /// `Error::ProcessError` if a thread couldn't be suspended. The threads
+    /// that were already suspended by this call are resumed again on error.
+    pub fn suspend(&self) -> Result<(), Error> {
+        let suspended = self.suspend_threads()?;
+
+        self.suspended
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push(suspended);
+
+        Ok(())
+    }
+
+    /// Resumes the threads that were suspended by the last call to `suspend`,
+    /// does nothing if there is none.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if a thread couldn't be resumed, the other threads
+    /// are still resumed.
+    pub fn resume(&self) -> Result<(), Error> {
+        let suspended = self
+            .suspended
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .pop()
+            .unwrap_or_default();
+
+        resume_threads(&suspended).map_or(Ok(()), Err)
+    }
+
+    /// Suspends the process until the returned `ProcessGuard` is dropped.
+    ///
+    /// The guard resumes exactly the threads it suspended, so guards can be
+    /// nested and dropped in any order without touching the threads of another
+    /// guard or of `suspend`.
+    ///
+    /// # Errors
+    /// Any error `suspend` returns.
+    pub fn freeze(&self) -> Result<ProcessGuard, Error> {
+        Ok(ProcessGuard {
+            threads: self.suspend_threads()?,
+        })
+    }
+
+    /// Suspends every thread of the process and returns their handles, see
+    /// `suspend`.
+    fn suspend_threads(&self) -> Result<Vec<SafeHandle>, Error> {
+        let mut suspended = Vec::new();
+
+        let failed = walk_threads(self.pid, |entry| {
+            let thread = open_thread(THREAD_SUSPEND_RESUME, false, entry.th32ThreadID).ok()?;
+
+            match suspend_thread(*thread) {
+                Ok(_) => {
+                    suspended.push(thread);
+                    None
+                },
+                Err(error) => Some(error),
+            }
+        });
+
+        match failed {
+            Ok(None) => Ok(suspended),
+            Ok(Some(error)) | Err(error) => {
+                resume_threads(&suspended);
+                Err(error)
+            },
+        }
+    }
+}
+
+/// `ProcessGuard` keeps a process suspended for as long as it is alive, it is
+/// created with `Process::freeze`.
+pub struct ProcessGuard {
+    threads: Vec<SafeHandle>,
+}
+
+impl Drop for ProcessGuard {
+    fn drop(&mut self) {
+        // Nothing useful can be done if resuming fails inside of drop
+        let _ = resume_threads(&self.threads);
+    }
+}
+
+/// Resumes every thread in `threads`, returning the first error.
+///
+/// The handles keep their threads alive even if they were terminated while
+/// suspended, so this can never resume a thread that reused their id.
+fn resume_threads(threads: &[SafeHandle]) -> Option<Error> {
+    let mut first_error = None;
+
+    for thread in threads {
+        if let Err(error) = resume_thread(**thread) {
+            first_error.get_or_insert(error);
+        }
+    }
+
+    first_error
+}
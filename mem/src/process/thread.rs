@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use windows::Win32::Foundation::WAIT_TIMEOUT;
+
+use crate::{
+    error::Error,
+    windows::{
+        handle::SafeHandle,
+        wrappers::{get_exit_code_thread, wait_for_single_object_dur, Handle, STILL_ACTIVE},
+    },
+};
+
+/// A thread created inside of another process, returned by
+/// `Process::create_remote_thread_at`.
+///
+/// Like `std::thread::JoinHandle` the thread keeps running when this is
+/// dropped, only the handle to it is closed.
+pub struct RemoteThread {
+    handle: SafeHandle,
+}
+
+impl RemoteThread {
+    pub(super) const fn new(handle: SafeHandle) -> Self { Self { handle } }
+
+    /// The raw handle of the thread, it is only valid for as long as the
+    /// `RemoteThread` is alive.
+    #[must_use]
+    pub fn handle(&self) -> Handle { *self.handle }
+
+    /// Whether the thread has exited.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        !matches!(
+            wait_for_single_object_dur(*self.handle, Some(Duration::ZERO)),
+            Ok(WAIT_TIMEOUT)
+        )
+    }
+
+    /// Waits for the thread to exit and returns its exit code, `None` waits
+    /// forever. The handle is closed either way.
+    ///
+    /// # Errors
+    /// `Error::Timeout` if the thread didn't exit in time, it is left running
+    /// in that case, or `Error::ProcessError` if its exit code couldn't be
+    /// retrieved.
+    pub fn join(self, timeout: Option<Duration>) -> Result<u32, Error> { self.wait(timeout) }
+
+    /// Like `join` but keeps the handle, so the thread can be waited on again
+    /// after a timeout.
+    ///
+    /// # Errors
+    /// The errors of `join`.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<u32, Error> {
+        if wait_for_single_object_dur(*self.handle, timeout)? == WAIT_TIMEOUT {
+            return Err(Error::Timeout);
+        }
+
+        let exit_code = get_exit_code_thread(*self.handle)?;
+        // A thread that is still running has the same exit code as one that
+        // returned STILL_ACTIVE, only the latter is signaled
+        if exit_code == STILL_ACTIVE && !self.is_finished() {
+            return Err(Error::Timeout);
+        }
+
+        Ok(exit_code)
+    }
+}
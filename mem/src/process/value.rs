@@ -0,0 +1,232 @@
+use std::mem::size_of;
+
+use super::{Primitive, Process, ReadRequest, RegionFilter};
+
+/// How `Process::new_scan` picks the first candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanType<T> {
+    /// Every value that is equal to this one.
+    Exact(T),
+    /// Every value, for when you don't know what it is yet but know how it
+    /// changes.
+    Unknown,
+}
+
+/// Which candidates `Scan::next` keeps, every filter compares the value a
+/// candidate has now to the value it had during the previous scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanFilter<T> {
+    Unchanged,
+    Changed,
+    Increased,
+    Decreased,
+    /// The value is equal to this one now.
+    Exact(T),
+}
+
+impl<T: PartialOrd> ScanFilter<T> {
+    fn keeps(&self, old: &T, new: &T) -> bool {
+        match self {
+            Self::Unchanged => new == old,
+            Self::Changed => new != old,
+            Self::Increased => new > old,
+            Self::Decreased => new < old,
+            Self::Exact(value) => new == value,
+        }
+    }
+}
+
+/// The candidates of a scan for a value of type `T`, which are narrowed down
+/// with every call to `next` until only the address you are looking for is
+/// left. Created with `Process::new_scan`.
+///
+/// ```ignore
+/// let mut scan = process.new_scan(ScanType::Exact(100_u32));
+/// // Take some damage in the game
+/// scan.next(ScanFilter::Decreased);
+/// // Wait for a bit without doing anything
+/// scan.next(ScanFilter::Unchanged);
+/// println!("{:X?}", scan.addresses());
+/// ```
+pub struct Scan<'a, T> {
+    process: &'a Process,
+    candidates: Candidates<T>,
+}
+
+enum Candidates<T> {
+    /// A copy of every region after an `Unknown` first scan, turning every
+    /// value into a candidate right away would take several times the memory.
+    Snapshot(Vec<(usize, Vec<u8>)>),
+    /// The address and last value of every candidate.
+    Values(Vec<(usize, T)>),
+}
+
+impl Process {
+    /// Starts a new scan for values of type `T` in every committed writable
+    /// region of the process, see `Scan`.
+    ///
+    /// Only addresses that are a multiple of the size of `T` are candidates,
+    /// which is how the compiler aligns them. Pages that can't be read are
+    /// skipped.
+    pub fn new_scan<T: Primitive + PartialOrd>(&self, scan_type: ScanType<T>) -> Scan<'_, T> {
+        let regions = self.regions_matching(RegionFilter::readable().and(RegionFilter::writable()));
+
+        let candidates = match scan_type {
+            ScanType::Unknown => {
+                let mut snapshot = Vec::new();
+                for region in regions {
+                    self.for_each_readable_run(region.base, region.size, |base, buffer| {
+                        snapshot.push((base, buffer.to_vec()));
+                        true
+                    });
+                }
+
+                Candidates::Snapshot(snapshot)
+            },
+            ScanType::Exact(value) => {
+                let mut values = Vec::new();
+                for region in regions {
+                    self.for_each_readable_run(region.base, region.size, |base, buffer| {
+                        for_each_aligned(base, buffer, |address, candidate: T| {
+                            if candidate == value {
+                                values.push((address, candidate));
+                            }
+                        });
+                        true
+                    });
+                }
+
+                Candidates::Values(values)
+            },
+        };
+
+        Scan {
+            process: self,
+            candidates,
+        }
+    }
+}
+
+impl<T: Primitive + PartialOrd> Scan<'_, T> {
+    /// Reads every candidate again and keeps the ones that pass `filter`,
+    /// returning how many are left.
+    pub fn next(&mut self, filter: ScanFilter<T>) -> usize { self.next_by(|old, new| filter.keeps(&old, &new)) }
+
+    /// Like `next` but keeps the candidates for which `keep` returns `true`,
+    /// it is called with the previous and the current value.
+    ///
+    /// Candidates that can't be read anymore are always dropped.
+    pub fn next_by(&mut self, mut keep: impl FnMut(T, T) -> bool) -> usize {
+        let values = match &self.candidates {
+            Candidates::Snapshot(snapshot) => {
+                let mut values = Vec::new();
+                for (base, old) in snapshot {
+                    self.process.for_each_readable_run(*base, old.len(), |run_base, new| {
+                        let old = &old[run_base - base..][..new.len()];
+                        for_each_aligned(run_base, new, |address, new: T| {
+                            let old = read_value(&old[address - run_base..]);
+                            if keep(old, new) {
+                                values.push((address, new));
+                            }
+                        });
+                        true
+                    });
+                }
+
+                values
+            },
+            Candidates::Values(values) => {
+                let size = size_of::<T>();
+                let mut buffer = vec![0; values.len() * size];
+                let mut requests: Vec<_> = values
+                    .iter()
+                    .zip(buffer.chunks_exact_mut(size))
+                    .map(|((address, _), bytes)| ReadRequest::new(*address, bytes))
+                    .collect();
+                let results = self.process.read_batch(&mut requests);
+
+                values
+                    .iter()
+                    .zip(results)
+                    .zip(buffer.chunks_exact(size))
+                    .filter_map(|((&(address, old), result), bytes)| {
+                        let new = read_value(bytes);
+                        (result.is_ok() && keep(old, new)).then_some((address, new))
+                    })
+                    .collect()
+            },
+        };
+
+        self.candidates = Candidates::Values(values);
+        self.len()
+    }
+
+    /// How many candidates are left.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match &self.candidates {
+            Candidates::Snapshot(snapshot) => snapshot
+                .iter()
+                .map(|(base, bytes)| aligned_count::<T>(*base, bytes.len()))
+                .sum(),
+            Candidates::Values(values) => values.len(),
+        }
+    }
+
+    /// Whether no candidates are left.
+    #[must_use]
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// The addresses of every candidate that is left.
+    #[must_use]
+    pub fn addresses(&self) -> Vec<usize> {
+        match &self.candidates {
+            Candidates::Snapshot(snapshot) => {
+                let mut addresses = Vec::new();
+                for (base, bytes) in snapshot {
+                    for_each_aligned(*base, bytes, |address, _: T| addresses.push(address));
+                }
+                addresses
+            },
+            Candidates::Values(values) => values.iter().map(|(address, _)| *address).collect(),
+        }
+    }
+
+    /// Every candidate that is left together with the value it had during the
+    /// last scan, `None` until the first call to `next` after an `Unknown`
+    /// scan since every value would be a candidate.
+    #[must_use]
+    pub fn values(&self) -> Option<&[(usize, T)]> {
+        match &self.candidates {
+            Candidates::Snapshot(_) => None,
+            Candidates::Values(values) => Some(values),
+        }
+    }
+}
+
+/// Calls `f` with the address and value of every `T` in `buffer`, which was
+/// read from `base`, that is aligned to its size.
+fn for_each_aligned<T: Copy>(base: usize, buffer: &[u8], mut f: impl FnMut(usize, T)) {
+    let size = size_of::<T>();
+    let first = (size - base % size) % size;
+
+    for (index, bytes) in buffer.get(first..).unwrap_or_default().chunks_exact(size).enumerate() {
+        f(base + first + index * size, read_value(bytes));
+    }
+}
+
+/// How many `T`s `for_each_aligned` finds in `size` bytes at `base`.
+const fn aligned_count<T>(base: usize, size: usize) -> usize {
+    let first = (size_of::<T>() - base % size_of::<T>()) % size_of::<T>();
+
+    size.saturating_sub(first) / size_of::<T>()
+}
+
+/// Reads a `T` from the start of `bytes`, which has to be at least as long as
+/// a `T`.
+fn read_value<T: Copy>(bytes: &[u8]) -> T {
+    assert!(bytes.len() >= size_of::<T>());
+
+    // Only numbers implement `Primitive`, so any bytes are a valid `T`
+    unsafe { std::ptr::read_unaligned(bytes.as_ptr().cast()) }
+}
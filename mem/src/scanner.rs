@@ -0,0 +1,451 @@
+//! `scanner` finds byte patterns (also known as signatures or AOBs) inside of
+//! a buffer of memory.
+//!
+//! Patterns are written in the IDA style where every byte is two hex digits
+//! separated by whitespace and a `?` or `??` is a wildcard that matches any
+//! byte.
+//!
+//! ```rust
+//! use mem::scanner::scan;
+//!
+//! let haystack = [0x90, 0x48, 0x8B, 0x05, 0x10, 0x20, 0x30, 0x40, 0x48, 0x89];
+//! assert_eq!(
+//!     scan(&haystack, "48 8B 05 ?? ?? ?? ?? 48 89").unwrap(),
+//!     Some(1)
+//! );
+//! ```
+mod constant;
+mod diff;
+
+use std::{borrow::Cow, fmt, str::FromStr};
+
+pub use constant::{const_parse_pattern, const_pattern_len};
+pub use diff::{diff, map_offset, Diff};
+
+use crate::error::Error;
+
+/// A parsed pattern, which can be scanned for over and over again without
+/// parsing it each time.
+///
+/// ```rust
+/// use mem::scanner::Signature;
+///
+/// let signature: Signature = "48 8B 05 ?? ?? ?? ?? 48 89".parse().unwrap();
+///
+/// let haystack = [0x90, 0x48, 0x8B, 0x05, 0x10, 0x20, 0x30, 0x40, 0x48, 0x89];
+/// assert_eq!(signature.scan(&haystack), Some(1));
+/// assert_eq!(signature.scan(&haystack[2..]), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Borrowed for signatures made with `signature!`.
+    pattern: Cow<'static, [Option<u8>]>,
+    shifts: [usize; 256],
+}
+
+impl Signature {
+    /// Parses an IDA style pattern, see `parse_pattern`.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the pattern couldn't be parsed.
+    pub fn parse(pattern: &str) -> Result<Self, Error> { parse_pattern(pattern).map(Self::from_parsed) }
+
+    /// Parses a byte array and mask signature, see `parse_masked`.
+    ///
+    /// # Errors
+    /// `Error::InvalidPattern` if the mask couldn't be parsed.
+    pub fn from_masked(bytes: &[u8], mask: &str) -> Result<Self, Error> { parse_masked(bytes, mask).map(Self::from_parsed) }
+
+    /// Creates a signature out of an already parsed pattern without copying
+    /// it, this is what `signature!` expands to.
+    ///
+    /// # Panics
+    /// If `pattern` is empty.
+    #[must_use]
+    pub const fn from_static(pattern: &'static [Option<u8>]) -> Self {
+        assert!(!pattern.is_empty(), "the pattern is empty");
+
+        Self {
+            pattern: Cow::Borrowed(pattern),
+            shifts: shift_table(pattern),
+        }
+    }
+
+    /// A pattern is never empty, both parsers reject empty patterns.
+    fn from_parsed(pattern: Vec<Option<u8>>) -> Self {
+        let shifts = shift_table(&pattern);
+
+        Self {
+            pattern: Cow::Owned(pattern),
+            shifts,
+        }
+    }
+
+    /// The bytes of the pattern, wildcards are `None`.
+    #[must_use]
+    pub fn bytes(&self) -> &[Option<u8>] { &self.pattern }
+
+    /// Returns the offset of the first match inside of `haystack`.
+    #[must_use]
+    pub fn scan(&self, haystack: &[u8]) -> Option<usize> { self.find_from(haystack, 0) }
+
+    /// Returns the offsets of every match inside of `haystack`, matches never
+    /// overlap like with `scan_all`.
+    #[must_use]
+    pub fn scan_all(&self, haystack: &[u8]) -> Vec<usize> { self.find_all(haystack, self.pattern.len()) }
+
+    /// Returns the offsets of every match inside of `haystack`, including
+    /// matches that overlap each other.
+    #[must_use]
+    pub fn scan_all_overlapping(&self, haystack: &[u8]) -> Vec<usize> { self.find_all(haystack, 1) }
+
+    /// Returns the offset of the first match at or after `start`.
+    pub(crate) fn find_from(&self, haystack: &[u8], start: usize) -> Option<usize> {
+        find_with(haystack, &self.pattern, &self.shifts, start)
+    }
+
+    /// Finds every match, moving `step` bytes past the start of each match
+    /// before looking for the next one.
+    fn find_all(&self, haystack: &[u8], step: usize) -> Vec<usize> {
+        let mut matches = Vec::new();
+        let mut start = 0;
+        while let Some(offset) = self.find_from(haystack, start) {
+            matches.push(offset);
+            start = offset + step;
+        }
+
+        matches
+    }
+}
+
+impl FromStr for Signature {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> { Self::parse(pattern) }
+}
+
+/// Formats the signature as a canonical IDA style pattern with upper case
+/// bytes and `??` wildcards, which parses back into the same signature. This
+/// is the form to store signatures in, since it stays easy to edit by hand.
+///
+/// ```rust
+/// use mem::scanner::Signature;
+///
+/// let signature = Signature::from_masked(&[0x48, 0x8b, 0x00, 0xe8], "xx?x").unwrap();
+/// assert_eq!(signature.to_string(), "48 8B ?? E8");
+/// assert_eq!(
+///     signature.to_string().parse::<Signature>().unwrap(),
+///     signature
+/// );
+/// ```
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, byte) in self.pattern.iter().enumerate() {
+            if index != 0 {
+                f.write_str(" ")?;
+            }
+
+            match byte {
+                Some(byte) => write!(f, "{byte:02X}")?,
+                None => f.write_str("??")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Signatures are serialized as their IDA style pattern, see `Display`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Signature {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> { serializer.collect_str(self) }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Signature {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+
+        pattern.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Parses an IDA style pattern into its bytes, wildcards are `None`.
+///
+/// # Errors
+/// `Error::InvalidPattern` if the pattern is empty or contains something that
+/// isn't a two digit hex byte or a wildcard.
+pub fn parse_pattern(pattern: &str) -> Result<Vec<Option<u8>>, Error> {
+    let bytes = pattern
+        .split_whitespace()
+        .map(|token| match token {
+            "?" | "??" => Ok(None),
+            // from_str_radix would also take a sign like in `+F`
+            _ if token.len() == 2 && token.bytes().all(|byte| byte.is_ascii_hexdigit()) => u8::from_str_radix(token, 16)
+                .map(Some)
+                .map_err(|_| Error::InvalidPattern(token.to_owned())),
+            _ => Err(Error::InvalidPattern(token.to_owned())),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if bytes.is_empty() {
+        return Err(Error::InvalidPattern(pattern.to_owned()));
+    }
+
+    Ok(bytes)
+}
+
+/// Parses a code style signature, which is a byte array together with a mask
+/// like `"xxx????xx"` where `x` is a byte that has to match and `?` is a
+/// wildcard. The bytes under wildcards are ignored.
+///
+/// # Errors
+/// `Error::InvalidPattern` if the mask is empty, isn't as long as `bytes` or
+/// contains something other than `x` and `?`.
+pub fn parse_masked(bytes: &[u8], mask: &str) -> Result<Vec<Option<u8>>, Error> {
+    if mask.is_empty() || bytes.len() != mask.len() {
+        return Err(Error::InvalidPattern(mask.to_owned()));
+    }
+
+    bytes
+        .iter()
+        .zip(mask.chars())
+        .map(|(&byte, mask)| match mask {
+            'x' => Ok(Some(byte)),
+            '?' => Ok(None),
+            _ => Err(Error::InvalidPattern(mask.to_string())),
+        })
+        .collect()
+}
+
+/// Builds an IDA style pattern out of `bytes` with the bytes at the offsets in
+/// `wildcards` replaced by `??`.
+///
+/// This is meant for taking a signature from a dumped function where the
+/// operands that get relocated have to be wildcards. Offsets past the end of
+/// `bytes` are ignored.
+///
+/// ```rust
+/// use mem::scanner::{pattern_from_bytes, scan};
+///
+/// let code = [0x48, 0x8B, 0x05, 0x10, 0x20, 0x30, 0x40, 0x48, 0x89];
+/// let pattern = pattern_from_bytes(&code, &[3, 4, 5, 6]);
+/// assert_eq!(pattern, "48 8B 05 ?? ?? ?? ?? 48 89");
+///
+/// let haystack = [0x90, 0x48, 0x8B, 0x05, 0xAA, 0xBB, 0xCC, 0xDD, 0x48, 0x89];
+/// assert_eq!(scan(&haystack, &pattern).unwrap(), Some(1));
+/// ```
+#[must_use]
+pub fn pattern_from_bytes(bytes: &[u8], wildcards: &[usize]) -> String {
+    bytes
+        .iter()
+        .enumerate()
+        .map(|(offset, byte)| {
+            if wildcards.contains(&offset) {
+                "??".to_owned()
+            } else {
+                format!("{byte:02X}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the offset of the first match of `pattern` inside of `haystack`.
+///
+/// # Errors
+/// `Error::InvalidPattern` if the pattern couldn't be parsed.
+pub fn scan(haystack: &[u8], pattern: &str) -> Result<Option<usize>, Error> { Ok(Signature::parse(pattern)?.scan(haystack)) }
+
+/// Returns the offset of the first match of a byte array and mask signature
+/// inside of `haystack`, see `parse_masked`.
+///
+/// ```rust
+/// use mem::scanner::scan_masked;
+///
+/// let haystack = [0x90, 0x48, 0x8B, 0x05, 0x10, 0x20, 0x30, 0x40, 0x48, 0x89];
+/// let bytes = [0x48, 0x8B, 0x05, 0x00, 0x00, 0x00, 0x00, 0x48, 0x89];
+/// assert_eq!(
+///     scan_masked(&haystack, &bytes, "xxx????xx").unwrap(),
+///     Some(1)
+/// );
+/// ```
+///
+/// # Errors
+/// `Error::InvalidPattern` if the mask couldn't be parsed.
+pub fn scan_masked(haystack: &[u8], bytes: &[u8], mask: &str) -> Result<Option<usize>, Error> {
+    Ok(Signature::from_masked(bytes, mask)?.scan(haystack))
+}
+
+/// Returns the offsets of every match of `pattern` inside of `haystack`.
+///
+/// Matches never overlap, the search continues after the end of each match.
+/// `AA AA` is only found once in `AA AA AA`, use `scan_all_overlapping` to find
+/// it at both offsets.
+///
+/// # Errors
+/// `Error::InvalidPattern` if the pattern couldn't be parsed.
+pub fn scan_all(haystack: &[u8], pattern: &str) -> Result<Vec<usize>, Error> {
+    Ok(Signature::parse(pattern)?.scan_all(haystack))
+}
+
+/// Returns the offsets of every match of `pattern` inside of `haystack`,
+/// including matches that overlap each other.
+///
+/// ```rust
+/// use mem::scanner::{scan_all, scan_all_overlapping};
+///
+/// let haystack = [0xAA, 0xAA, 0xAA];
+/// assert_eq!(
+///     scan_all_overlapping(&haystack, "AA AA").unwrap(),
+///     vec![0, 1]
+/// );
+/// assert_eq!(scan_all(&haystack, "AA AA").unwrap(), vec![0]);
+/// ```
+///
+/// # Errors
+/// `Error::InvalidPattern` if the pattern couldn't be parsed.
+pub fn scan_all_overlapping(haystack: &[u8], pattern: &str) -> Result<Vec<usize>, Error> {
+    Ok(Signature::parse(pattern)?.scan_all_overlapping(haystack))
+}
+
+/// The absolute address a relative operand points to, `displacement` is
+/// relative to the end of the instruction at `address` which is
+/// `instruction_size` bytes long.
+///
+/// ```rust
+/// use mem::scanner::relative_target;
+///
+/// // mov rax, [rip+0x10] at 0x1000 is 7 bytes long
+/// assert_eq!(relative_target(0x1000, 7, 0x10), 0x1017);
+/// // Negative displacements point backwards
+/// assert_eq!(relative_target(0x1000, 7, -0x10), 0xFF7);
+/// ```
+#[must_use]
+#[allow(clippy::cast_sign_loss)]
+pub const fn relative_target(address: usize, instruction_size: usize, displacement: i32) -> usize {
+    address
+        .wrapping_add(instruction_size)
+        .wrapping_add(displacement as isize as usize)
+}
+
+/// Finds the first match of an already parsed pattern that isn't empty at or
+/// after `start`, `shifts` is the `shift_table` of the pattern.
+///
+/// This uses Boyer-Moore-Horspool, the byte under the end of the window
+/// decides how far the window can move. A wildcard matches any byte, so the
+/// window can never move past the last wildcard of the pattern.
+fn find_with(haystack: &[u8], pattern: &[Option<u8>], shifts: &[usize; 256], start: usize) -> Option<usize> {
+    if start >= haystack.len() || haystack.len() - start < pattern.len() {
+        return None;
+    }
+
+    let last = pattern.len() - 1;
+
+    let mut position = start;
+    while position + last < haystack.len() {
+        let window = &haystack[position..=position + last];
+        if matches(window, pattern) {
+            return Some(position);
+        }
+
+        position += shifts[window[last] as usize];
+    }
+
+    None
+}
+
+/// How far the window can move when the byte at its end is the index into the
+/// table.
+///
+/// This is a `const fn` for `Signature::from_static`, hence the loops.
+const fn shift_table(pattern: &[Option<u8>]) -> [usize; 256] {
+    let last = pattern.len() - 1;
+
+    // Everything up to and including the last wildcard before the end of the
+    // pattern matches any byte
+    let mut start = last;
+    while start > 0 && pattern[start - 1].is_some() {
+        start -= 1;
+    }
+
+    let mut shifts = [last + 1 - start; 256];
+    let mut index = start;
+    while index < last {
+        if let Some(byte) = pattern[index] {
+            shifts[byte as usize] = last - index;
+        }
+        index += 1;
+    }
+
+    shifts
+}
+
+fn matches(window: &[u8], pattern: &[Option<u8>]) -> bool {
+    window
+        .iter()
+        .zip(pattern)
+        .all(|(byte, expected)| expected.is_none() || *expected == Some(*byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tiny xorshift generator so the test doesn't need any dependencies.
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, max: u64) -> u64 { self.next() % max }
+    }
+
+    fn naive_scan_all(haystack: &[u8], pattern: &[Option<u8>]) -> Vec<usize> {
+        haystack
+            .windows(pattern.len())
+            .enumerate()
+            .filter(|(_, window)| matches(window, pattern))
+            .map(|(offset, _)| offset)
+            .collect()
+    }
+
+    fn to_pattern_string(pattern: &[Option<u8>]) -> String {
+        pattern
+            .iter()
+            .map(|byte| byte.map_or_else(|| "??".to_owned(), |byte| format!("{byte:02X}")))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn random_patterns_match_the_naive_scan() {
+        let mut random = Random(0x2545_F491_4F6C_DD1D);
+
+        // Small alphabets make partial matches common, which is where skipping
+        // goes wrong if it is going to
+        for _ in 0..100_000 {
+            let alphabet = random.below(4) + 1;
+            let haystack: Vec<u8> = (0..random.below(64)).map(|_| random.below(alphabet) as u8).collect();
+            let pattern: Vec<Option<u8>> = (0..=random.below(6))
+                .map(|_| (random.below(4) != 0).then(|| random.below(alphabet) as u8))
+                .collect();
+            if pattern.iter().all(Option::is_none) {
+                continue;
+            }
+
+            let expected = naive_scan_all(&haystack, &pattern);
+            let found = scan_all_overlapping(&haystack, &to_pattern_string(&pattern)).unwrap();
+            assert_eq!(found, expected, "{pattern:?} in {haystack:?}");
+            assert_eq!(
+                scan(&haystack, &to_pattern_string(&pattern)).unwrap(),
+                expected.first().copied()
+            );
+        }
+    }
+}
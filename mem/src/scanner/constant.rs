@@ -0,0 +1,116 @@
+//! `constant` parses IDA style patterns inside of const contexts, which is
+//! what lets `signature!` reject a malformed pattern at compile time.
+
+/// Builds a `Signature` out of an IDA style pattern at compile time, so a
+/// malformed pattern is a compile error instead of an `Error` at runtime and
+/// the pattern is never parsed again.
+///
+/// The result can be stored in a `const` or a `static`.
+///
+/// ```rust
+/// use mem::{scanner::Signature, signature};
+///
+/// const LOCAL_PLAYER: Signature = signature!("48 8B 05 ?? ?? ?? ?? 48 89");
+///
+/// let haystack = [0x90, 0x48, 0x8B, 0x05, 0x10, 0x20, 0x30, 0x40, 0x48, 0x89];
+/// assert_eq!(LOCAL_PLAYER.scan(&haystack), Some(1));
+/// ```
+///
+/// ```compile_fail
+/// const BROKEN: mem::scanner::Signature = mem::signature!("48 8G ??");
+/// ```
+#[macro_export]
+macro_rules! signature {
+    ($pattern:expr) => {{
+        const PATTERN: [::core::option::Option<u8>; $crate::scanner::const_pattern_len($pattern)] =
+            $crate::scanner::const_parse_pattern($pattern);
+
+        $crate::scanner::Signature::from_static(&PATTERN)
+    }};
+}
+
+/// How many bytes the IDA style `pattern` has, like `parse_pattern(pattern)`
+/// followed by `len` but usable in const contexts.
+///
+/// # Panics
+/// If the pattern is empty or contains something that isn't a two digit hex
+/// byte or a wildcard, which fails the build when evaluated at compile time.
+#[must_use]
+pub const fn const_pattern_len(pattern: &str) -> usize {
+    let bytes = pattern.as_bytes();
+
+    let mut len = 0;
+    let mut position = 0;
+    while let Some((start, end)) = next_token(bytes, position) {
+        // Only parsed to make sure that it is valid
+        parse_token(bytes, start, end);
+
+        len += 1;
+        position = end;
+    }
+
+    assert!(len != 0, "the pattern is empty");
+    len
+}
+
+/// Parses the IDA style `pattern` into its `N` bytes, wildcards are `None`,
+/// like `parse_pattern` but usable in const contexts. `N` has to be
+/// `const_pattern_len(pattern)`.
+///
+/// # Panics
+/// If the pattern contains something that isn't a two digit hex byte or a
+/// wildcard, or doesn't have exactly `N` bytes.
+#[must_use]
+pub const fn const_parse_pattern<const N: usize>(pattern: &str) -> [Option<u8>; N] {
+    let bytes = pattern.as_bytes();
+
+    let mut parsed = [None; N];
+    let mut index = 0;
+    let mut position = 0;
+    while let Some((start, end)) = next_token(bytes, position) {
+        assert!(index < N, "the pattern has more bytes than the array");
+        parsed[index] = parse_token(bytes, start, end);
+
+        index += 1;
+        position = end;
+    }
+
+    assert!(index == N, "the pattern has fewer bytes than the array");
+    parsed
+}
+
+/// The start and end of the first token at or after `position`, tokens are
+/// separated by whitespace.
+const fn next_token(bytes: &[u8], mut position: usize) -> Option<(usize, usize)> {
+    while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+        position += 1;
+    }
+    if position == bytes.len() {
+        return None;
+    }
+
+    let start = position;
+    while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+        position += 1;
+    }
+
+    Some((start, position))
+}
+
+const fn parse_token(bytes: &[u8], start: usize, end: usize) -> Option<u8> {
+    match end - start {
+        1 if bytes[start] == b'?' => None,
+        2 if bytes[start] == b'?' && bytes[start + 1] == b'?' => None,
+        2 => Some((hex_digit(bytes[start]) << 4) | hex_digit(bytes[start + 1])),
+        _ => panic!("pattern bytes have to be two hex digits or a ?? wildcard"),
+    }
+}
+
+const fn hex_digit(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        b'A'..=b'F' => digit - b'A' + 10,
+        _ => panic!("pattern bytes have to be two hex digits or a ?? wildcard"),
+    }
+}
@@ -0,0 +1,116 @@
+//! `diff` compares two dumps of the same module from different versions of
+//! the target, which helps with finding where offsets and signatures moved to
+//! after an update.
+use super::Signature;
+
+/// How many bytes of context `map_offset` starts with.
+const MIN_CONTEXT: usize = 8;
+/// The most bytes of context `map_offset` grows to before it settles for the
+/// closest of several matches.
+const MAX_CONTEXT: usize = 64;
+
+/// A run of bytes that changed between two dumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Diff {
+    /// The offset of the first changed byte.
+    pub offset: usize,
+    /// How many bytes in a row changed.
+    pub len: usize,
+}
+
+impl Diff {
+    /// The offset right after the last changed byte.
+    #[must_use]
+    pub const fn end(&self) -> usize { self.offset + self.len }
+}
+
+/// Returns every run of bytes that is different between `old` and `new`,
+/// comparing the bytes at the same offsets.
+///
+/// If one dump is longer than the other the bytes past the end of the shorter
+/// one count as changed.
+///
+/// ```rust
+/// use mem::scanner::{diff, Diff};
+///
+/// let old = [0x48, 0x8B, 0x05, 0x10, 0x20, 0x90];
+/// let new = [0x48, 0x8B, 0x05, 0x18, 0x20, 0x90, 0xCC];
+/// assert_eq!(
+///     diff(&old, &new),
+///     [Diff { offset: 3, len: 1 }, Diff { offset: 6, len: 1 }]
+/// );
+/// ```
+#[must_use]
+pub fn diff(old: &[u8], new: &[u8]) -> Vec<Diff> {
+    let common = old.len().min(new.len());
+
+    let mut diffs = Vec::new();
+    let mut offset = 0;
+    while offset < common {
+        if old[offset] == new[offset] {
+            offset += 1;
+            continue;
+        }
+
+        let start = offset;
+        while offset < common && old[offset] != new[offset] {
+            offset += 1;
+        }
+        diffs.push(Diff {
+            offset: start,
+            len: offset - start,
+        });
+    }
+
+    let longest = old.len().max(new.len());
+    if longest > common {
+        diffs.push(Diff {
+            offset: common,
+            len: longest - common,
+        });
+    }
+
+    diffs
+}
+
+/// Guesses where the code or data at `offset` in `old` ended up in `new` by
+/// looking for the bytes starting at `offset`.
+///
+/// The bytes after `offset` are used as context, and more of them are used
+/// while they match in more than one place. If they still aren't unique the
+/// match closest to `offset` is returned. Bytes that changed between the
+/// versions, like relative operands, make it less likely that anything is
+/// found, so pick an offset at the start of the code you are looking for.
+///
+/// ```rust
+/// use mem::scanner::map_offset;
+///
+/// let old = [0x00, 0x00, 0x48, 0x8B, 0x05, 0x10, 0x20, 0x30, 0x40, 0xC3];
+/// let new = [
+///     0x00, 0x00, 0x90, 0x90, 0x48, 0x8B, 0x05, 0x10, 0x20, 0x30, 0x40, 0xC3,
+/// ];
+/// assert_eq!(map_offset(&old, &new, 2), Some(4));
+/// ```
+#[must_use]
+pub fn map_offset(old: &[u8], new: &[u8], offset: usize) -> Option<usize> {
+    let available = old.len().checked_sub(offset).filter(|&available| available > 0)?;
+
+    let mut context = MIN_CONTEXT.min(available);
+    let mut closest = None;
+    loop {
+        let pattern = old[offset..offset + context].iter().copied().map(Some).collect();
+        let matches = Signature::from_parsed(pattern).scan_all_overlapping(new);
+
+        match matches.as_slice() {
+            [] => return closest,
+            [found] => return Some(*found),
+            _ => {
+                closest = matches.iter().copied().min_by_key(|found| found.abs_diff(offset));
+                if context >= MAX_CONTEXT.min(available) {
+                    return closest;
+                }
+                context = (context * 2).min(MAX_CONTEXT).min(available);
+            },
+        }
+    }
+}
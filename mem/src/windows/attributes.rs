@@ -0,0 +1,91 @@
+//! `attributes` builds the attribute lists `create_remote_thread_ex` takes to
+//! control how the new thread is created, like which processors it may run on.
+use std::{marker::PhantomData, mem::size_of};
+
+use crate::{
+    error::Error,
+    windows::wrappers::{
+        delete_proc_thread_attribute_list, initialize_proc_thread_attribute_list, update_proc_thread_attribute,
+        ProcThreadAttributeList,
+    },
+};
+
+/// Sets the processor group and the processors in it a new thread may run on,
+/// the value is a `GroupAffinity`.
+pub const PROC_THREAD_ATTRIBUTE_GROUP_AFFINITY: usize = 0x0003_0003;
+/// Sets the processor a new thread prefers to run on, the value is a
+/// `PROCESSOR_NUMBER`.
+pub const PROC_THREAD_ATTRIBUTE_IDEAL_PROCESSOR: usize = 0x0003_0005;
+
+/// The `GROUP_AFFINITY` windows expects for
+/// `PROC_THREAD_ATTRIBUTE_GROUP_AFFINITY`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GroupAffinity {
+    /// Bit `n` allows the thread to run on processor `n` of the group.
+    pub mask: usize,
+    /// The processor group, machines with 64 or fewer processors only have
+    /// group 0.
+    pub group: u16,
+    reserved: [u16; 3],
+}
+
+impl GroupAffinity {
+    #[must_use]
+    pub const fn new(mask: usize, group: u16) -> Self {
+        Self {
+            mask,
+            group,
+            reserved: [0; 3],
+        }
+    }
+}
+
+/// An initialized attribute list that is deleted once it is dropped.
+///
+/// The values passed to `set` are only referenced by the list, so they have to
+/// outlive it.
+pub struct ThreadAttributeList<'a> {
+    /// `usize`s keep the list pointer aligned, windows only tells us the size
+    /// in bytes.
+    buffer: Vec<usize>,
+    values: PhantomData<&'a ()>,
+}
+
+impl<'a> ThreadAttributeList<'a> {
+    /// Creates a list with room for `count` attributes.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the list couldn't be initialized.
+    pub fn new(count: u32) -> Result<Self, Error> {
+        let mut size = 0;
+        initialize_proc_thread_attribute_list(std::ptr::null_mut(), count, &mut size)?;
+
+        let mut buffer = vec![0_usize; size.div_ceil(size_of::<usize>())];
+        // The list is only deleted on drop once it was initialized
+        initialize_proc_thread_attribute_list(buffer.as_mut_ptr().cast(), count, &mut size)?;
+
+        Ok(Self {
+            buffer,
+            values: PhantomData,
+        })
+    }
+
+    /// Sets `attribute`, one of the `PROC_THREAD_ATTRIBUTE_*` constants, to
+    /// `value`. `T` has to be the type windows expects for that attribute.
+    ///
+    /// # Errors
+    /// `Error::ProcessError` if the list is full or windows doesn't accept the
+    /// attribute.
+    pub fn set<T>(&mut self, attribute: usize, value: &'a T) -> Result<(), Error> {
+        update_proc_thread_attribute(self.as_raw(), attribute, std::ptr::from_ref(value).cast(), size_of::<T>())
+    }
+
+    /// The raw list to pass into `create_remote_thread_ex`.
+    #[must_use]
+    pub const fn as_raw(&mut self) -> ProcThreadAttributeList { self.buffer.as_mut_ptr().cast() }
+}
+
+impl Drop for ThreadAttributeList<'_> {
+    fn drop(&mut self) { delete_proc_thread_attribute_list(self.as_raw()); }
+}
@@ -0,0 +1,40 @@
+//! `console` gives a process, usually the one our DLL was injected into, a
+//! console that `println!` and friends actually print to.
+use windows::Win32::System::Console::{STD_ERROR_HANDLE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE};
+
+use crate::{
+    error::Error,
+    windows::wrappers::{alloc_console, free_console, open_console_buffer, set_std_handle},
+};
+
+/// Allocates a console for the calling process and points its standard
+/// handles at it, so `println!`, `eprintln!` and `stdin` use the new console
+/// right away.
+///
+/// `alloc_console` on its own leaves a process that was started without a
+/// console with no standard handles, so anything printed goes nowhere. The
+/// console handles are never closed since they stay the standard handles for
+/// as long as the console exists.
+///
+/// # Errors
+/// `Error::ConsoleAllocation` if the console couldn't be allocated or its
+/// buffers couldn't be opened, the console is freed again in the latter case.
+pub fn alloc_console_with_stdio() -> Result<(), Error> {
+    alloc_console()?;
+
+    let bound = bind_stdio();
+    if bound.is_err() {
+        let _ = free_console();
+    }
+
+    bound
+}
+
+fn bind_stdio() -> Result<(), Error> {
+    let output = open_console_buffer("CONOUT$")?;
+    let input = open_console_buffer("CONIN$")?;
+
+    set_std_handle(STD_OUTPUT_HANDLE, output)?;
+    set_std_handle(STD_ERROR_HANDLE, output)?;
+    set_std_handle(STD_INPUT_HANDLE, input)
+}
@@ -0,0 +1,75 @@
+//! `handle` contains `SafeHandle`, a RAII wrapper around a raw `Handle` so
+//! callers no longer have to remember to call `close_handle` themselves.
+use std::ops::Deref;
+
+use crate::windows::wrappers::{close_handle, get_current_process, Handle};
+
+/// `SafeHandle` owns a `Handle` and closes it once it is dropped.
+///
+/// It derefs to the raw `Handle` so it can still be passed into any of the
+/// functions in `wrappers`. Pseudo handles like the one returned from
+/// `get_current_process` must never be closed, those should be created with
+/// `SafeHandle::borrowed` or `SafeHandle::current_process`.
+pub struct SafeHandle {
+    handle: Handle,
+    owned: bool,
+}
+
+impl SafeHandle {
+    /// Takes ownership of `handle`, if the handle is invalid or a pseudo handle
+    /// `None` is returned instead.
+    #[must_use]
+    pub fn from_raw(handle: Handle) -> Option<Self> {
+        if handle.is_invalid() || is_pseudo_handle(handle) {
+            None
+        } else {
+            Some(Self { handle, owned: true })
+        }
+    }
+
+    /// Takes ownership of a handle that a wrapper has already checked for being
+    /// invalid.
+    pub(crate) const fn owned(handle: Handle) -> Self { Self { handle, owned: true } }
+
+    /// Wraps a handle that we don't own, it will never be closed when dropped.
+    #[must_use]
+    pub const fn borrowed(handle: Handle) -> Self { Self { handle, owned: false } }
+
+    /// Wraps the pseudo handle of the current process, this handle is never
+    /// closed.
+    #[must_use]
+    pub fn current_process() -> Self { Self::borrowed(get_current_process()) }
+
+    /// Whether this handle will be closed when it is dropped.
+    #[must_use]
+    pub const fn is_owned(&self) -> bool { self.owned }
+
+    /// Gives the raw handle back to the caller without closing it, the caller
+    /// is now responsible for closing it.
+    #[must_use]
+    pub const fn into_raw(self) -> Handle {
+        let handle = self.handle;
+        std::mem::forget(self);
+        handle
+    }
+}
+
+impl Deref for SafeHandle {
+    type Target = Handle;
+
+    fn deref(&self) -> &Self::Target { &self.handle }
+}
+
+impl Drop for SafeHandle {
+    fn drop(&mut self) {
+        if self.owned {
+            // Nothing useful can be done if closing fails inside of drop
+            let _ = close_handle(self.handle);
+        }
+    }
+}
+
+/// Whether `handle` is one of the pseudo handles of the current process,
+/// thread or their tokens, which go from -1 down to -6. -1 is also
+/// `INVALID_HANDLE_VALUE`.
+const fn is_pseudo_handle(handle: Handle) -> bool { matches!(handle.0, -6..=-1) }
@@ -0,0 +1,161 @@
+//! `input` makes the value returned by `get_async_key_state` easier to work
+//! with, so nobody has to remember which bit means what.
+use std::convert::TryFrom;
+
+use crate::windows::wrappers::get_async_key_state;
+
+/// Commonly used virtual key codes. Letters and digits don't have a variant,
+/// their code is the same as their uppercase ASCII value, so `'A' as i32` can
+/// be passed instead.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VirtualKey {
+    LeftMouse = 0x01,
+    RightMouse = 0x02,
+    MiddleMouse = 0x04,
+    /// The first side button of the mouse, usually the back button.
+    Mouse4 = 0x05,
+    /// The second side button of the mouse, usually the forward button.
+    Mouse5 = 0x06,
+    Backspace = 0x08,
+    Tab = 0x09,
+    Enter = 0x0D,
+    Shift = 0x10,
+    Control = 0x11,
+    Alt = 0x12,
+    Pause = 0x13,
+    CapsLock = 0x14,
+    Escape = 0x1B,
+    Space = 0x20,
+    PageUp = 0x21,
+    PageDown = 0x22,
+    End = 0x23,
+    Home = 0x24,
+    Left = 0x25,
+    Up = 0x26,
+    Right = 0x27,
+    Down = 0x28,
+    Insert = 0x2D,
+    Delete = 0x2E,
+    Numpad0 = 0x60,
+    Numpad1 = 0x61,
+    Numpad2 = 0x62,
+    Numpad3 = 0x63,
+    Numpad4 = 0x64,
+    Numpad5 = 0x65,
+    Numpad6 = 0x66,
+    Numpad7 = 0x67,
+    Numpad8 = 0x68,
+    Numpad9 = 0x69,
+    F1 = 0x70,
+    F2 = 0x71,
+    F3 = 0x72,
+    F4 = 0x73,
+    F5 = 0x74,
+    F6 = 0x75,
+    F7 = 0x76,
+    F8 = 0x77,
+    F9 = 0x78,
+    F10 = 0x79,
+    F11 = 0x7A,
+    F12 = 0x7B,
+}
+
+impl VirtualKey {
+    /// The virtual key code windows uses for the key.
+    #[must_use]
+    pub const fn code(self) -> i32 { self as i32 }
+}
+
+impl From<VirtualKey> for i32 {
+    fn from(key: VirtualKey) -> Self { key.code() }
+}
+
+/// Whether `key` is held down right now, `key` is either a `VirtualKey` or a
+/// raw virtual key code.
+#[must_use]
+pub fn is_key_down(key: impl Into<i32>) -> bool { get_async_key_state(key.into()) < 0 }
+
+/// Whether `key` was pressed since the last time its state was queried.
+///
+/// Windows shares this bit between every application, so another program
+/// checking the key state can swallow the press. Use `KeyStateTracker` if you
+/// need to reliably catch every press.
+#[must_use]
+pub fn was_key_pressed(key: impl Into<i32>) -> bool { get_async_key_state(key.into()) & 1 != 0 }
+
+/// Virtual key codes go from 1 to 254.
+const KEY_COUNT: usize = 256;
+
+/// `KeyStateTracker` remembers the state of every key between calls to
+/// `update`, so presses and releases can be told apart from a key being held.
+///
+/// ```ignore
+/// let mut tracker = KeyStateTracker::new();
+/// loop {
+///     tracker.update();
+///     if tracker.just_pressed(VirtualKey::F5) {
+///         // Toggle something once per press
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct KeyStateTracker {
+    previous: [bool; KEY_COUNT],
+    current: [bool; KEY_COUNT],
+}
+
+impl KeyStateTracker {
+    /// Creates a tracker with every key up, call `update` once before using it
+    /// so keys that are already held don't count as pressed.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            previous: [false; KEY_COUNT],
+            current: [false; KEY_COUNT],
+        }
+    }
+
+    /// Polls the state of every key, call this once per frame or loop
+    /// iteration.
+    pub fn update(&mut self) {
+        self.previous = self.current;
+
+        for (key, down) in self.current.iter_mut().enumerate() {
+            *down = is_key_down(key as i32);
+        }
+    }
+
+    /// Whether `key` was down during the last `update`.
+    #[must_use]
+    pub fn is_down(&self, key: impl Into<i32>) -> bool { self.state(key.into()).1 }
+
+    /// Whether `key` went from up to down between the last two calls to
+    /// `update`.
+    #[must_use]
+    pub fn just_pressed(&self, key: impl Into<i32>) -> bool {
+        let (previous, current) = self.state(key.into());
+        !previous && current
+    }
+
+    /// Whether `key` went from down to up between the last two calls to
+    /// `update`.
+    #[must_use]
+    pub fn just_released(&self, key: impl Into<i32>) -> bool {
+        let (previous, current) = self.state(key.into());
+        previous && !current
+    }
+
+    /// The previous and current state of `key`, codes that aren't a valid key
+    /// are always up.
+    fn state(&self, key: i32) -> (bool, bool) {
+        usize::try_from(key)
+            .ok()
+            .filter(|&key| key < KEY_COUNT)
+            .map_or((false, false), |key| (self.previous[key], self.current[key]))
+    }
+}
+
+impl Default for KeyStateTracker {
+    fn default() -> Self { Self::new() }
+}
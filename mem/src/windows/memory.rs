@@ -0,0 +1,301 @@
+//! `memory` has typed versions of `read_process_memory` and
+//! `write_process_memory` for code that works with a raw `Handle` instead of
+//! a `Process`.
+use std::mem::{size_of, MaybeUninit};
+
+use windows::Win32::Foundation::{ERROR_INVALID_ADDRESS, ERROR_INVALID_PARAMETER, ERROR_PARTIAL_COPY};
+
+use crate::{
+    error::Error,
+    process::{is_user_range, MemoryRegion, MAX_USER_ADDRESS},
+    windows::wrappers::{
+        read_process_memory, virtual_query_ex, write_process_memory, Handle, MemoryBasicInformation, LPCVOID, LPVOID, WCHAR,
+    },
+};
+
+/// How many bytes are read at a time while looking for the null terminator of
+/// a string.
+const STRING_CHUNK_SIZE: usize = 64;
+const PAGE_SIZE: usize = 0x1000;
+
+/// Reads a `T` from `address` in `process`.
+///
+/// Whatever bytes are at `address` will be interpreted as a `T`, so `T` has to
+/// be valid for any bit pattern. That is the case for plain old data types like
+/// integers, floats and `#[repr(C)]` structs made out of them, but not for
+/// `bool`, `char`, enums or references.
+///
+/// # Errors
+/// `Error::MemoryError` if the read fails, `T` is zero sized or not all of the
+/// bytes of `T` could be read.
+pub fn read<T: Copy>(process: Handle, address: usize) -> Result<T, Error> {
+    let size = size_of::<T>();
+    if size == 0 {
+        return Err(Error::MemoryError(ERROR_INVALID_PARAMETER));
+    }
+
+    let mut buffer = MaybeUninit::<T>::uninit();
+    let mut bytes_read = 0;
+
+    read_process_memory(
+        process,
+        address as LPCVOID,
+        buffer.as_mut_ptr().cast(),
+        size,
+        &raw mut bytes_read,
+    )?;
+
+    if bytes_read != size {
+        return Err(Error::MemoryError(ERROR_PARTIAL_COPY));
+    }
+
+    Ok(unsafe { buffer.assume_init() })
+}
+
+/// Like `read` but the memory is checked with `is_address_readable` first,
+/// which costs a `VirtualQueryEx` call for every region the value is in.
+///
+/// # Errors
+/// `Error::InvalidRegion` if the memory isn't committed and readable,
+/// otherwise see `read`.
+pub fn read_checked<T: Copy>(process: Handle, address: usize) -> Result<T, Error> {
+    if !is_address_readable(process, address, size_of::<T>()) {
+        return Err(Error::InvalidRegion(address));
+    }
+
+    read(process, address)
+}
+
+/// Whether all `size` bytes at `address` are committed and readable, so
+/// reading them can only fail if the process frees or protects them right
+/// after.
+///
+/// Addresses that can never be valid, like null pointers, are rejected
+/// without querying the process. Guard pages don't count as readable.
+#[must_use]
+pub fn is_address_readable(process: Handle, address: usize, size: usize) -> bool {
+    let size = size.max(1);
+    if !is_user_range(address, size) {
+        return false;
+    }
+
+    let end = address + size;
+    let mut current = address;
+    while current < end {
+        let Ok(region) = query(process, current) else {
+            return false;
+        };
+        if !region.is_committed || !region.is_readable || region.is_guard {
+            return false;
+        }
+
+        current = region.end();
+    }
+
+    true
+}
+
+/// Reads a null terminated string at `address`, decoding it as UTF-8 and
+/// replacing any invalid sequences, see `Process::read_cstring`.
+///
+/// At most `max_len` bytes are read, if no null terminator was found by then
+/// the string is cut off at `max_len` so a bad pointer can't make us read
+/// forever. A string that runs into memory that can't be read is cut off
+/// there.
+///
+/// # Errors
+/// `Error::MemoryError` if not even the first byte could be read.
+pub fn read_c_string(process: Handle, address: usize, max_len: usize) -> Result<String, Error> {
+    let bytes = read_until_null(process, address, max_len, 1)?;
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Reads a null terminated UTF-16 string at `address`, replacing any invalid
+/// sequences, see `Process::read_wstring`.
+///
+/// At most `max_chars` characters are read, if no null terminator was found by
+/// then the string is cut off at `max_chars`. A string that runs into memory
+/// that can't be read is cut off at the last character that could be read.
+///
+/// # Errors
+/// `Error::MemoryError` if not even the first character could be read.
+pub fn read_wide_string(process: Handle, address: usize, max_chars: usize) -> Result<String, Error> {
+    let bytes = read_until_null(process, address, max_chars.saturating_mul(2), 2)?;
+
+    let characters: Vec<WCHAR> = bytes
+        .chunks_exact(2)
+        .map(|pair| WCHAR::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+
+    Ok(String::from_utf16_lossy(&characters))
+}
+
+/// Reads characters of `width` bytes until a null character or `max_len`
+/// bytes, the null character isn't included.
+///
+/// Chunks never cross a page boundary so a string right before an unreadable
+/// page can still be read, reading stops at the first chunk that fails.
+fn read_until_null(process: Handle, address: usize, max_len: usize, width: usize) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    let mut chunk = [0; STRING_CHUNK_SIZE];
+
+    while bytes.len() < max_len {
+        let current = address.wrapping_add(bytes.len());
+        let page_left = PAGE_SIZE - current % PAGE_SIZE;
+        let size = STRING_CHUNK_SIZE.min(page_left).min(max_len - bytes.len());
+
+        if let Err(error) = read_bytes(process, current, &mut chunk[..size]) {
+            if bytes.len() < width {
+                return Err(error);
+            }
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..size]);
+
+        // Only look at whole characters that were read completely
+        let start = (bytes.len() - size) / width * width;
+        let end = bytes.len() / width * width;
+        let null = bytes[start..end]
+            .chunks_exact(width)
+            .position(|character| character.iter().all(|&byte| byte == 0));
+
+        if let Some(position) = null {
+            bytes.truncate(start + position * width);
+            return Ok(bytes);
+        }
+    }
+
+    bytes.truncate(bytes.len().min(max_len) / width * width);
+    Ok(bytes)
+}
+
+fn read_bytes(process: Handle, address: usize, buffer: &mut [u8]) -> Result<(), Error> {
+    let mut bytes_read = 0;
+    read_process_memory(
+        process,
+        address as LPCVOID,
+        buffer.as_mut_ptr().cast(),
+        buffer.len(),
+        &raw mut bytes_read,
+    )?;
+
+    if bytes_read != buffer.len() {
+        return Err(Error::MemoryError(ERROR_PARTIAL_COPY));
+    }
+
+    Ok(())
+}
+
+/// Follows a chain of pointers starting at `base` and returns the final
+/// address, see `Process::read_pointer_chain`.
+///
+/// Pointers are read as `usize`, so they are 4 bytes in a 32 bit build and 8
+/// bytes in a 64 bit one. Use `Process::read_pointer_chain` to follow chains in
+/// a process with a different architecture.
+///
+/// # Errors
+/// `Error::MemoryError` if any of the reads fail, or with
+/// `ERROR_INVALID_ADDRESS` if a pointer along the chain is null.
+pub fn read_pointer_chain(process: Handle, base: usize, offsets: &[usize]) -> Result<usize, Error> {
+    let mut address = read_non_null(process, base)?;
+
+    if let Some((last, offsets)) = offsets.split_last() {
+        for offset in offsets {
+            address = read_non_null(process, address.wrapping_add(*offset))?;
+        }
+
+        address = address.wrapping_add(*last);
+    }
+
+    Ok(address)
+}
+
+fn read_non_null(process: Handle, address: usize) -> Result<usize, Error> {
+    match read(process, address)? {
+        0 => Err(Error::MemoryError(ERROR_INVALID_ADDRESS)),
+        pointer => Ok(pointer),
+    }
+}
+
+/// Writes `value` to `address` in `process`.
+///
+/// # Errors
+/// `Error::MemoryError` if the write fails, `T` is zero sized or not all of the
+/// bytes of `T` could be written.
+pub fn write<T: Copy>(process: Handle, address: usize, value: &T) -> Result<(), Error> {
+    if size_of::<T>() == 0 {
+        return Err(Error::MemoryError(ERROR_INVALID_PARAMETER));
+    }
+
+    write_raw(process, address, std::ptr::from_ref(value).cast(), size_of::<T>())
+}
+
+/// Writes all of `bytes` to `address` in `process`, the bytes version of
+/// `write`. Writing nothing always succeeds.
+///
+/// # Errors
+/// `Error::MemoryError` if the write fails or not all of `bytes` could be
+/// written.
+pub fn write_bytes(process: Handle, address: usize, bytes: &[u8]) -> Result<(), Error> {
+    if bytes.is_empty() {
+        return Ok(());
+    }
+
+    write_raw(process, address, bytes.as_ptr().cast(), bytes.len())
+}
+
+fn write_raw(process: Handle, address: usize, buffer: LPCVOID, size: usize) -> Result<(), Error> {
+    let mut bytes_written = 0;
+    write_process_memory(process, address as LPVOID, buffer, size, Some(&raw mut bytes_written))?;
+
+    if bytes_written != size {
+        return Err(Error::MemoryError(ERROR_PARTIAL_COPY));
+    }
+
+    Ok(())
+}
+
+/// Queries the region of pages `address` is in, see `Process::query`.
+///
+/// # Errors
+/// `Error::MemoryError` if the region couldn't be queried, which happens when
+/// `address` is above the highest address the process can use.
+pub fn query(process: Handle, address: usize) -> Result<MemoryRegion, Error> {
+    let mut info = MemoryBasicInformation::default();
+    virtual_query_ex(
+        process,
+        address as LPCVOID,
+        &raw mut info,
+        size_of::<MemoryBasicInformation>(),
+    )?;
+
+    Ok(MemoryRegion::from(&info))
+}
+
+/// Iterates over every region of the address space of `process` from the
+/// bottom up, including free and reserved ones, see `Process::regions`.
+#[must_use]
+pub const fn memory_regions(process: Handle) -> RegionIter { RegionIter { process, address: 0 } }
+
+/// The iterator returned by `memory_regions`, it ends at the top of the user
+/// mode address space or at the first region that couldn't be queried.
+pub struct RegionIter {
+    process: Handle,
+    address: usize,
+}
+
+impl Iterator for RegionIter {
+    type Item = MemoryRegion;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.address >= MAX_USER_ADDRESS {
+            return None;
+        }
+
+        let region = query(self.process, self.address).ok().filter(|region| region.size != 0)?;
+        self.address = region.end();
+
+        Some(region)
+    }
+}
@@ -1,3 +1,15 @@
+pub mod attributes;
+
+#[cfg(feature = "console")]
+pub mod console;
+
+pub mod handle;
+
+#[cfg(feature = "input")]
+pub mod input;
+
+pub mod memory;
+
 pub mod wrappers;
 
 pub mod utils;
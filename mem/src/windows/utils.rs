@@ -1,25 +1,114 @@
-use std::{ffi::CStr, mem::size_of};
+use std::{
+    ffi::{CStr, OsStr, OsString},
+    mem::size_of,
+    os::windows::ffi::{OsStrExt, OsStringExt},
+    path::PathBuf,
+};
 
 use windows::Win32::{
     Foundation::CHAR,
-    System::Diagnostics::ToolHelp::{TH32CS_SNAPMODULE, TH32CS_SNAPPROCESS},
+    Security::{LUID_AND_ATTRIBUTES, SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES, TOKEN_QUERY},
+    System::Diagnostics::ToolHelp::TH32CS_SNAPPROCESS,
 };
 
 use crate::{
     error::Error,
-    windows::wrappers::{
-        close_handle, create_tool_help32_snapshot, module32_first, module32_next, process32_first, process32_next,
-        ModuleEntry32, ProcessEntry32, DWORD, DWORD_PTR,
+    process::enumerate,
+    windows::{
+        handle::SafeHandle,
+        wrappers::{
+            adjust_token_privileges, close_handle, create_tool_help32_snapshot, disable_thread_library_calls, exit_thread,
+            find_window, free_library_and_exit_thread, get_current_process, get_module_handle_from_address,
+            get_module_handle_w, get_module_information, get_window_thread_process_id, lookup_privilege_value,
+            open_process_token, process32_first, process32_next, query_dos_device, HandleInstance, Hwnd, ProcessEntry32,
+            TokenPrivileges, DWORD, DWORD_PTR,
+        },
     },
 };
 
-//// This will convert a pointer to a string into a string.
+/// This will convert a pointer to a string into a string.
 /// # Errors
 /// `std::ffi::Error` if an error occurs.
-pub fn convert_windows_string<'a, const N: usize>(string: [CHAR; N]) -> Result<&'a str, Error> {
+pub fn convert_windows_string<const N: usize>(string: &[CHAR; N]) -> Result<&str, Error> {
     unsafe { Ok(CStr::from_ptr(string.as_ptr().cast::<i8>()).to_str()?) }
 }
 
+/// Converts a fixed size null terminated string like the ones found in
+/// `ProcessEntry32` into an owned `String`.
+///
+/// Everything after the first null is ignored and invalid UTF-8 is replaced
+/// rather than returning an error.
+#[must_use]
+pub fn windows_string_to_string(string: &[CHAR]) -> String {
+    let length = string.iter().position(|&c| c == 0).unwrap_or(string.len());
+
+    String::from_utf8_lossy(&string[..length]).into_owned()
+}
+
+/// Converts `string` into null terminated UTF-16 for the `W` versions of
+/// windows functions.
+#[must_use]
+pub fn to_wide(string: &str) -> Vec<u16> { string.encode_utf16().chain(std::iter::once(0)).collect() }
+
+/// Converts UTF-16 from a windows function into a `String`, everything after
+/// the first null is ignored and invalid UTF-16 is replaced rather than
+/// returning an error.
+#[must_use]
+pub fn from_wide(string: &[u16]) -> String {
+    let length = string.iter().position(|&c| c == 0).unwrap_or(string.len());
+
+    String::from_utf16_lossy(&string[..length])
+}
+
+/// Like `from_wide` but reads up to the null terminator at `pointer`, a null
+/// pointer gives an empty string.
+///
+/// # Safety
+/// `pointer` has to be null or point to a null terminated UTF-16 string in our
+/// own process.
+#[must_use]
+pub unsafe fn from_wide_ptr(pointer: *const u16) -> String {
+    if pointer.is_null() {
+        return String::new();
+    }
+
+    let mut length = 0;
+    while *pointer.add(length) != 0 {
+        length += 1;
+    }
+
+    String::from_utf16_lossy(std::slice::from_raw_parts(pointer, length))
+}
+
+/// Turns a native path like `\Device\HarddiskVolume3\Windows\notepad.exe`,
+/// which is what `get_mapped_file_name` returns, into the usual
+/// `C:\Windows\notepad.exe`.
+///
+/// Paths on devices that aren't mounted as a drive are returned unchanged.
+#[must_use]
+pub fn device_path_to_dos_path(path: &OsStr) -> PathBuf {
+    let wide: Vec<u16> = path.encode_wide().collect();
+
+    for letter in b'A'..=b'Z' {
+        let drive = format!("{}:", char::from(letter));
+        let Ok(device) = query_dos_device(&drive) else {
+            continue;
+        };
+
+        let device: Vec<u16> = device.encode_wide().collect();
+        // The device has to be followed by a separator so
+        // \Device\HarddiskVolume1 doesn't match
+        // \Device\HarddiskVolume10
+        if wide.starts_with(&device) && wide.get(device.len()) == Some(&u16::from(b'\\')) {
+            let mut dos_path: Vec<u16> = drive.encode_utf16().collect();
+            dos_path.extend_from_slice(&wide[device.len()..]);
+            return PathBuf::from(OsString::from_wide(&dos_path));
+        }
+    }
+
+    PathBuf::from(path)
+}
+
 /// `get_process_id` returns the ID of the process name.
 /// # Errors
 /// `error::Error` if an error occurs.
@@ -35,7 +124,7 @@ pub fn get_process_id(process_name: &str) -> Result<DWORD, Error> {
 
     if process32_first(snapshot, &mut entry).is_ok() {
         process_id = loop {
-            let current_name = convert_windows_string(entry.szExeFile)?;
+            let current_name = convert_windows_string(&entry.szExeFile)?;
 
             if current_name == process_name {
                 break entry.th32ProcessID;
@@ -56,38 +145,138 @@ pub fn get_process_id(process_name: &str) -> Result<DWORD, Error> {
     Ok(process_id)
 }
 
-/// `get_module_base` returns the base address of the module name.
+/// `get_process_id_by_name` returns the ID of the first process whose
+/// executable name matches `name`, the comparison is case-insensitive.
+///
+/// Not to be confused with `wrappers::get_process_id` which returns the ID of
+/// an already opened `Handle`.
+///
 /// # Errors
-/// `error::Error` if an error occurs.
-pub fn get_module_base(process_id: DWORD, module_name: &str) -> Result<DWORD_PTR, Error> {
-    let mut module_base_address: DWORD_PTR = 0x0;
+/// `Error::ProcessNotFound` if no process has that name.
+pub fn get_process_id_by_name(name: &str) -> Result<DWORD, Error> {
+    enumerate()?
+        .into_iter()
+        .find(|process| process.name.eq_ignore_ascii_case(name))
+        .map(|process| process.pid)
+        .ok_or(Error::ProcessNotFound)
+}
 
-    let snapshot = create_tool_help32_snapshot(TH32CS_SNAPMODULE, process_id)?;
+/// Returns the id of the process that owns the top level window titled
+/// `title`, for targets that are easier to find by their window than by
+/// their executable name.
+///
+/// # Errors
+/// `Error::ProcessNotFound` if no window has that title.
+pub fn find_process_by_window(title: &str) -> Result<DWORD, Error> { window_process_id(find_window(None, Some(title))) }
 
-    let mut entry = ModuleEntry32 {
-        dwSize: size_of::<ModuleEntry32>() as u32,
-        ..ModuleEntry32::default()
-    };
+/// Like `find_process_by_window` but finds the window by its window class,
+/// which usually stays the same while the title changes.
+///
+/// # Errors
+/// `Error::ProcessNotFound` if no window has that class.
+pub fn find_process_by_window_class(class_name: &str) -> Result<DWORD, Error> {
+    window_process_id(find_window(Some(class_name), None))
+}
 
-    if module32_first(snapshot, &mut entry).is_ok() {
-        module_base_address = loop {
-            let current_name = convert_windows_string(entry.szModule)?;
+fn window_process_id(window: Option<Hwnd>) -> Result<DWORD, Error> {
+    let window = window.ok_or(Error::ProcessNotFound)?;
+    // The window could have been closed since we found it
+    let (_, pid) = get_window_thread_process_id(window).map_err(|_| Error::ProcessNotFound)?;
 
-            if current_name == module_name {
-                break entry.modBaseAddr as DWORD_PTR;
-            }
+    Ok(pid)
+}
 
-            if module32_next(snapshot, &mut entry).is_ok() {
-                break 0;
-            }
-        };
+/// `get_module_base` returns the base address of the module name, see
+/// `process::module_base`.
+/// # Errors
+/// `Error::ModuleNotFound` if the process doesn't have a module with that
+/// name.
+pub fn get_module_base(process_id: DWORD, module_name: &str) -> Result<DWORD_PTR, Error> {
+    crate::process::module_base(process_id, module_name)
+}
 
-        close_handle(snapshot)?;
-    }
+/// `current_module_handle` returns the handle of the module this crate was
+/// compiled into, which is the DLL itself when called from an injected DLL.
+///
+/// Unlike `get_module_handle` this doesn't need the name of the module, it
+/// looks up the module that contains our own code so it keeps working when
+/// the DLL was renamed.
+///
+/// # Errors
+/// `Error::Handle` if the module couldn't be found, which is always the case
+/// for manually mapped DLLs since the loader doesn't know about them.
+pub fn current_module_handle() -> Result<HandleInstance, Error> {
+    get_module_handle_from_address(current_module_handle as *const std::ffi::c_void)
+}
+
+/// `local_module_base` returns the base address of the module named `name`
+/// in the current process, like `Process::module_bounds` but without taking a
+/// toolhelp snapshot of our own process.
+///
+/// The name is passed to `GetModuleHandleW`, so the `.dll` extension can be
+/// left out and unicode names work.
+///
+/// # Errors
+/// `Error::Handle` if the module isn't loaded.
+#[allow(clippy::cast_sign_loss)]
+pub fn local_module_base(name: &str) -> Result<usize, Error> { get_module_handle_w(name).map(|module| module as usize) }
+
+/// `local_module_size` returns the size of the image of the module named
+/// `name` in the current process, see `local_module_base`.
+///
+/// # Errors
+/// `Error::Handle` if the module isn't loaded, or `Error::ProcessError` if its
+/// information couldn't be retrieved.
+pub fn local_module_size(name: &str) -> Result<usize, Error> {
+    let module = get_module_handle_w(name)?;
+
+    get_module_information(get_current_process(), module).map(|info| info.size)
+}
 
-    if module_base_address == 0 {
-        return Err(std::io::Error::last_os_error().into());
+/// `unload_self` unloads the DLL this crate was compiled into and ends the
+/// calling thread, which is how an injected DLL ejects itself.
+///
+/// Call it from a thread the DLL started once it is done, never from `DllMain`
+/// since the loader lock is held there. `FreeLibrary` and `ExitThread` run
+/// back to back inside of kernel32 so the thread never returns into the code
+/// that was just unloaded. Every other thread that runs code of the DLL has to
+/// be stopped first, the host crashes as soon as one of them runs again.
+///
+/// If the module of the DLL can't be found, like when it was manually mapped,
+/// only the thread is ended.
+pub fn unload_self(exit_code: u32) -> ! {
+    if let Ok(module) = current_module_handle() {
+        // Threads that start or exit while we unload would call into DllMain
+        let _ = disable_thread_library_calls(module);
+        free_library_and_exit_thread(module, exit_code);
     }
 
-    Ok(module_base_address)
+    exit_thread(exit_code)
+}
+
+/// `enable_debug_privilege` enables `SeDebugPrivilege` for the current
+/// process, which lets it open processes of other users and services.
+///
+/// Call it once at startup before opening the target. Only administrators have
+/// the privilege, so this fails unless the process runs elevated. Protected
+/// processes still can't be opened.
+///
+/// # Errors
+/// `Error::Win32` if the access token of the process couldn't be changed, with
+/// `ERROR_NOT_ALL_ASSIGNED` if we aren't running as administrator.
+pub fn enable_debug_privilege() -> Result<(), Error> {
+    let token = SafeHandle::owned(open_process_token(
+        get_current_process(),
+        TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+    )?);
+
+    let privileges = TokenPrivileges {
+        PrivilegeCount: 1,
+        Privileges: [LUID_AND_ATTRIBUTES {
+            Luid: lookup_privilege_value("SeDebugPrivilege")?,
+            Attributes: SE_PRIVILEGE_ENABLED,
+        }],
+    };
+
+    adjust_token_privileges(*token, &privileges)
 }
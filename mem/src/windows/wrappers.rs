@@ -4,34 +4,100 @@
 //! Not all functions are designated as safe as without adding a significant
 //! amount of boilerplate will always be up to the caller to make sure UB can't
 //! happen. As time goes on we'll try to make as little functions unsafe.
-use std::{os::raw::c_void, ptr::null_mut};
+use std::{
+    convert::TryFrom,
+    ffi::OsString,
+    os::{raw::c_void, windows::ffi::OsStringExt},
+    ptr::null_mut,
+    time::Duration,
+};
 
+#[cfg(feature = "input")]
+use windows::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
 use windows::Win32::{
-    Foundation::{CloseHandle, GetLastError, HANDLE, HINSTANCE},
-    Security::SECURITY_ATTRIBUTES,
+    Foundation::{
+        CloseHandle, GetLastError, BOOL, ERROR_ACCESS_DENIED, ERROR_INSUFFICIENT_BUFFER, ERROR_NOT_ALL_ASSIGNED, HANDLE,
+        HINSTANCE, HWND, LUID, PSTR, PWSTR,
+    },
+    Security::{AdjustTokenPrivileges, LookupPrivilegeValueW, SECURITY_ATTRIBUTES, TOKEN_ACCESS_MASK, TOKEN_PRIVILEGES},
+    Storage::FileSystem::QueryDosDeviceW,
     System::{
-        Console::{AllocConsole, FreeConsole},
         Diagnostics::{
-            Debug::{ReadProcessMemory, WriteProcessMemory},
+            Debug::{
+                CheckRemoteDebuggerPresent, FlushInstructionCache, FormatMessageW, GetThreadContext, ReadProcessMemory,
+                SetThreadContext, WriteProcessMemory, CONTEXT, FORMAT_MESSAGE_FROM_SYSTEM, FORMAT_MESSAGE_IGNORE_INSERTS,
+            },
             ToolHelp::{
-                CreateToolhelp32Snapshot, Module32First, Module32Next, Process32First, Process32Next,
-                CREATE_TOOLHELP_SNAPSHOT_FLAGS, MODULEENTRY32, PROCESSENTRY32,
+                CreateToolhelp32Snapshot, Module32First, Module32Next, Process32First, Process32Next, Thread32First,
+                Thread32Next, CREATE_TOOLHELP_SNAPSHOT_FLAGS, MODULEENTRY32, PROCESSENTRY32, THREADENTRY32,
             },
         },
-        LibraryLoader::{DisableThreadLibraryCalls, FreeLibraryAndExitThread, GetModuleHandleA, GetProcAddress},
+        LibraryLoader::{
+            DisableThreadLibraryCalls, FreeLibraryAndExitThread, GetModuleHandleA, GetModuleHandleExW, GetModuleHandleW,
+            GetProcAddress, GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS, GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+        },
         Memory::{
-            VirtualAllocEx, VirtualFreeEx, VirtualProtect, VirtualProtectEx, VirtualQueryEx, MEMORY_BASIC_INFORMATION,
-            PAGE_PROTECTION_FLAGS, VIRTUAL_ALLOCATION_TYPE, VIRTUAL_FREE_TYPE,
+            VirtualAllocEx, VirtualFreeEx, VirtualProtect, VirtualProtectEx, VirtualQuery, VirtualQueryEx,
+            MEMORY_BASIC_INFORMATION, PAGE_PROTECTION_FLAGS, VIRTUAL_ALLOCATION_TYPE, VIRTUAL_FREE_TYPE,
         },
+        ProcessStatus::{K32GetMappedFileNameW, K32GetModuleFileNameExW, K32GetModuleInformation, MODULEINFO},
         Threading::{
-            CreateRemoteThread, CreateThread, GetCurrentProcess, GetProcessId, OpenProcess, WaitForSingleObject,
-            LPTHREAD_START_ROUTINE, PROCESS_ACCESS_RIGHTS, THREAD_CREATION_FLAGS,
+            CreateRemoteThread, CreateRemoteThreadEx, CreateThread, DeleteProcThreadAttributeList, ExitThread,
+            GetCurrentProcess, GetExitCodeProcess, GetExitCodeThread, GetProcessId, InitializeProcThreadAttributeList,
+            IsWow64Process, NtQueryInformationProcess, OpenProcess, OpenProcessToken, OpenThread, ResumeThread,
+            SuspendThread, UpdateProcThreadAttribute, WaitForSingleObject, LPPROC_THREAD_ATTRIBUTE_LIST,
+            LPTHREAD_START_ROUTINE, PROCESSINFOCLASS, PROCESS_ACCESS_RIGHTS, PROCESS_BASIC_INFORMATION,
+            THREAD_ACCESS_RIGHTS, THREAD_CREATION_FLAGS,
         },
     },
-    UI::Input::KeyboardAndMouse::GetAsyncKeyState,
+    UI::WindowsAndMessaging::{FindWindowW, GetWindowThreadProcessId},
+};
+#[cfg(feature = "console")]
+use windows::Win32::{
+    Storage::FileSystem::{CreateFileW, FILE_ATTRIBUTE_NORMAL, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING},
+    System::{
+        Console::{AllocConsole, FreeConsole, SetStdHandle},
+        SystemServices::{GENERIC_READ, GENERIC_WRITE},
+    },
+};
+
+use crate::{
+    error::Error,
+    module::ModuleInfo,
+    windows::{handle::SafeHandle, utils::to_wide},
 };
 
-use crate::error::Error;
+/// Pass to `wait_for_single_object` to wait without a timeout.
+pub const INFINITE: u32 = 0xFFFF_FFFF;
+/// The exit code `get_exit_code_thread` and `get_exit_code_process` return
+/// while the thread or process is still running.
+pub const STILL_ACTIVE: DWORD = 259;
+
+/// Converts the `windows::core::Result` of a windows-rs binding into our
+/// `Error`.
+///
+/// windows-rs reads the last error right after the call failed, so the code
+/// can't be overwritten by anything that runs before we look at it.
+trait OrCode<T> {
+    /// Puts the win32 error code into `variant`, like `Error::MemoryError`.
+    fn or_code(self, variant: fn(u32) -> Error) -> Result<T, Error>;
+
+    /// Creates an `Error::Win32` for the function named by `context`.
+    fn or_context(self, context: &'static str) -> Result<T, Error>;
+}
+
+impl<T> OrCode<T> for windows::core::Result<T> {
+    fn or_code(self, variant: fn(u32) -> Error) -> Result<T, Error> { self.map_err(|error| variant(win32_code(&error))) }
+
+    fn or_context(self, context: &'static str) -> Result<T, Error> {
+        self.map_err(|error| Error::win32(win32_code(&error), context))
+    }
+}
+
+/// The win32 error code of an error from windows-rs, errors that aren't win32
+/// errors keep their `HRESULT`.
+#[allow(clippy::cast_sign_loss)]
+fn win32_code(error: &windows::core::Error) -> u32 { error.win32_error().unwrap_or_else(|| error.code().0 as u32) }
 
 /// `size_t` is a usize which will be 4 bytes for x86 and 8 bytes for x64
 #[allow(non_camel_case_types)]
@@ -61,6 +127,8 @@ pub type LPCWSTR = WCHAR;
 pub type HandleInstance = HINSTANCE;
 /// `Handle` is a handle to an object, and not specifically a program.
 pub type Handle = HANDLE;
+/// A handle to a window.
+pub type Hwnd = HWND;
 /// `HModule` is the base address of a DLL.
 pub type HModule = isize;
 /// Contains information about a range of pages in the virtual address space of
@@ -84,37 +152,105 @@ pub type SecurityAttributes = SECURITY_ATTRIBUTES;
 pub type LPThreadStartRoutine = LPTHREAD_START_ROUTINE;
 /// Flags that control the creation of a thread.
 pub type ThreadCreationFlags = THREAD_CREATION_FLAGS;
+/// A pointer to an opaque list of attributes for `create_remote_thread_ex`.
+pub type ProcThreadAttributeList = LPPROC_THREAD_ATTRIBUTE_LIST;
+/// Which information `nt_query_information_process` retrieves.
+pub type ProcessInfoClass = PROCESSINFOCLASS;
+/// The information `nt_query_information_process` retrieves for
+/// `ProcessBasicInformation`, which includes the address of the PEB.
+pub type ProcessBasicInformation = PROCESS_BASIC_INFORMATION;
 /// Access rights that the system will give you to the process, this is meant to
 /// be used with the `open_process` function which will open the process with
 /// the provided access rights.
 pub type ProcessAccessRights = PROCESS_ACCESS_RIGHTS;
+/// Access rights that the system will give you to the thread, this is meant to
+/// be used with the `open_thread` function.
+pub type ThreadAccessRights = THREAD_ACCESS_RIGHTS;
+/// Access rights for an access token opened with `open_process_token`.
+pub type TokenAccessMask = TOKEN_ACCESS_MASK;
+/// A locally unique id, which is how `lookup_privilege_value` identifies a
+/// privilege.
+pub type Luid = LUID;
+/// The privileges `adjust_token_privileges` enables or disables, together with
+/// what to do with each of them.
+pub type TokenPrivileges = TOKEN_PRIVILEGES;
+/// `Context` holds the registers of a thread, which parts of it are filled in
+/// or applied is controlled by `ContextFlags`.
+///
+/// On x64 it has to be 16 byte aligned when passed to `get_thread_context` or
+/// `set_thread_context`.
+pub type Context = CONTEXT;
 /// `ProcessEntry32` is an entry from a list of the processes in the system
 /// address space when the snapshot from `create_tool_help32_snapshot` was
 /// taken.
 pub type ProcessEntry32 = PROCESSENTRY32;
 /// `CreateToolhelpSnapshotFlags` are flags to indicate which parts of the
-/// system should be included in the snapshot for example you would use the flag
-/// `TH32CS_SNAPMODULE` to include the modules of the process.
+/// system should be included in the snapshot.
+///
+/// For example you would use the flag `TH32CS_SNAPMODULE` to include the
+/// modules of the process.
 pub type CreateToolhelpSnapshotFlags = CREATE_TOOLHELP_SNAPSHOT_FLAGS;
 /// `ModuleEntry32` is used for crawling the modules of a process in most cases
 /// you will be just default its value dwSize because not initializing dwSize
 /// will make `module32_first` fail.
 pub type ModuleEntry32 = MODULEENTRY32;
+/// `ThreadEntry32` is an entry from a list of the threads in the system when
+/// the snapshot from `create_tool_help32_snapshot` was taken.
+///
+/// Like the other entries dwSize has to be initialized before calling
+/// `thread32_first`.
+pub type ThreadEntry32 = THREADENTRY32;
 
 /// `get_module_handle` will get the handle of a module.
 ///
+/// This uses the ANSI `GetModuleHandleA` which can't find modules with names
+/// that contain non ASCII characters, use `get_module_handle_w` for those.
+///
 /// # Errors
 /// If the `hInstance` returned is NULL a `Error::Handle` is returned.
 pub fn get_module_handle(module_name: &str) -> Result<HandleInstance, Error> {
     let hinstance = unsafe { GetModuleHandleA(module_name) };
 
-    if hinstance.is_negative() {
+    if hinstance == 0 {
         Err(Error::Handle(unsafe { GetLastError() }))
     } else {
         Ok(hinstance)
     }
 }
 
+/// `get_module_handle_w` will get the handle of a module, the name is passed
+/// to `GetModuleHandleW` as UTF-16 so unicode names work too.
+///
+/// # Errors
+/// If the `hInstance` returned is NULL a `Error::Handle` is returned.
+pub fn get_module_handle_w(module_name: &str) -> Result<HandleInstance, Error> {
+    let hinstance = unsafe { GetModuleHandleW(module_name) };
+
+    if hinstance == 0 {
+        Err(Error::Handle(unsafe { GetLastError() }))
+    } else {
+        Ok(hinstance)
+    }
+}
+
+/// Retrieves a module handle for the module of the calling process that
+/// contains `address`, the reference count of the module is left unchanged.
+///
+/// # Errors
+/// If no module contains `address` a `Error::Handle` is returned.
+pub fn get_module_handle_from_address(address: LPCVOID) -> Result<HandleInstance, Error> {
+    let mut hinstance = 0;
+    let result = unsafe {
+        GetModuleHandleExW(
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            PWSTR(address as *mut WCHAR),
+            &raw mut hinstance,
+        )
+    };
+
+    result.ok().map(|()| hinstance).or_code(Error::Handle)
+}
+
 /// Retrieves information about a range of pages within the virtual address
 /// space of a specified process.
 ///
@@ -134,6 +270,20 @@ pub fn virtual_query_ex(
     }
 }
 
+/// Retrieves information about a range of pages within the virtual address
+/// space of the calling process.
+///
+/// # Errors
+/// `Error::MemoryError` is returned if the function fails.
+pub fn virtual_query(address: LPCVOID, buffer: *mut MemoryBasicInformation, length: usize) -> Result<usize, Error> {
+    let num_bytes = unsafe { VirtualQuery(address, buffer, length) };
+    if num_bytes == 0 {
+        Err(Error::MemoryError(unsafe { GetLastError() }))
+    } else {
+        Ok(num_bytes)
+    }
+}
+
 /// Determines whether a key is up or down at the time the function is called,
 /// and whether the key was pressed after a previous call to
 /// `get_async_key_state`.
@@ -146,6 +296,7 @@ pub fn virtual_query_ex(
 /// previous call to `get_async_key_state`. However, you should not rely on this
 /// last behavior.
 #[must_use]
+#[cfg(feature = "input")]
 pub fn get_async_key_state(key: i32) -> i16 { unsafe { GetAsyncKeyState(key) } }
 
 /// Changes the protection on a region of committed pages in the virtual address
@@ -162,11 +313,7 @@ pub fn virtual_protect_ex(
 ) -> Result<(), Error> {
     let res = unsafe { VirtualProtectEx(process, address, size, new_protect, old_protect) };
 
-    if res.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::Allocation(unsafe { GetLastError() }))
-    }
+    res.ok().or_code(Error::Allocation)
 }
 
 /// Changes the protection on a region of committed pages in the virtual address
@@ -182,11 +329,18 @@ pub fn virtual_protect(
 ) -> Result<(), Error> {
     let res = unsafe { VirtualProtect(address, size, new_protect, old_protect) };
 
-    if res.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::MemoryError(unsafe { GetLastError() }))
-    }
+    res.ok().or_code(Error::MemoryError)
+}
+
+/// Flushes the instruction cache for a region of memory in the specified
+/// process, this should be called after modifying code.
+///
+/// # Errors
+/// If the function fails, `Error::MemoryError` is returned.
+pub fn flush_instruction_cache(process: Handle, address: LPCVOID, size: usize) -> Result<(), Error> {
+    let res = unsafe { FlushInstructionCache(process, address, size) };
+
+    res.ok().or_code(Error::MemoryError)
 }
 
 /// Waits until the specified object is in the signaled state or the time-out
@@ -207,6 +361,24 @@ pub fn wait_for_single_object(handle: Handle, milliseconds: u32) -> Result<u32,
     }
 }
 
+/// Like `wait_for_single_object` but takes the time-out as a `Duration`, `None`
+/// waits without a time-out.
+///
+/// `Duration::ZERO` only checks the state of the object and returns right
+/// away. Other time-outs are rounded up to whole milliseconds and the ones
+/// that don't fit in a `u32` wait as long as possible rather than forever.
+///
+/// # Errors
+/// If the function fails, `Error::Timeout` is returned.
+pub fn wait_for_single_object_dur(handle: Handle, timeout: Option<Duration>) -> Result<u32, Error> {
+    let milliseconds = timeout.map_or(INFINITE, |timeout| {
+        let milliseconds = timeout.as_nanos().div_ceil(1_000_000);
+        u32::try_from(milliseconds).map_or(INFINITE - 1, |milliseconds| milliseconds.min(INFINITE - 1))
+    });
+
+    wait_for_single_object(handle, milliseconds)
+}
+
 /// Creates a thread that runs in the virtual address space of another process.
 ///
 /// Use the `CreateRemoteThreadEx` function to create a thread that runs in the
@@ -239,13 +411,147 @@ pub fn create_remote_thread(
         )
     };
 
-    if handle.is_invalid() {
-        Err(Error::ProcessError(unsafe { GetLastError() }))
-    } else {
-        Ok(handle)
+    handle.ok().or_code(Error::ProcessError)
+}
+
+/// Creates a thread that runs in the virtual address space of another process
+/// with the extended attributes in `attribute_list`.
+///
+/// Providing none to `thread_attributes`, `parameter`, `attribute_list` or
+/// `thread_id` will let us default the value with a null pointer.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+#[allow(clippy::too_many_arguments)]
+pub fn create_remote_thread_ex(
+    process: Handle,
+    thread_attributes: Option<*mut SecurityAttributes>,
+    stack_size: usize,
+    start_address: LPThreadStartRoutine,
+    parameter: Option<LPVOID>,
+    creation_flags: u32,
+    attribute_list: Option<ProcThreadAttributeList>,
+    thread_id: Option<*mut u32>,
+) -> Result<Handle, Error> {
+    let handle = unsafe {
+        CreateRemoteThreadEx(
+            process,
+            thread_attributes.unwrap_or(null_mut()),
+            stack_size,
+            start_address,
+            parameter.unwrap_or(null_mut()),
+            creation_flags,
+            attribute_list.unwrap_or(null_mut()),
+            thread_id.unwrap_or(null_mut()),
+        )
+    };
+
+    handle.ok().or_code(Error::ProcessError)
+}
+
+/// Initializes an attribute list with room for `attribute_count` attributes.
+///
+/// Pass a null `attribute_list` to only get the size the list needs in `size`,
+/// windows reports that as `ERROR_INSUFFICIENT_BUFFER` which isn't an error
+/// here.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn initialize_proc_thread_attribute_list(
+    attribute_list: ProcThreadAttributeList,
+    attribute_count: u32,
+    size: &mut usize,
+) -> Result<(), Error> {
+    let result = unsafe { InitializeProcThreadAttributeList(attribute_list, attribute_count, 0, size) };
+
+    match result.ok().map_err(|error| win32_code(&error)) {
+        Err(ERROR_INSUFFICIENT_BUFFER) if attribute_list.is_null() => Ok(()),
+        result => result.map_err(Error::ProcessError),
     }
 }
 
+/// Sets `attribute` in `attribute_list` to the `size` bytes at `value`, which
+/// have to stay alive until the list is deleted.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn update_proc_thread_attribute(
+    attribute_list: ProcThreadAttributeList,
+    attribute: usize,
+    value: LPCVOID,
+    size: usize,
+) -> Result<(), Error> {
+    let result =
+        unsafe { UpdateProcThreadAttribute(attribute_list, 0, attribute, value, size, null_mut(), std::ptr::null()) };
+
+    result.ok().or_code(Error::ProcessError)
+}
+
+/// Deletes an attribute list that was initialized with
+/// `initialize_proc_thread_attribute_list`.
+pub fn delete_proc_thread_attribute_list(attribute_list: ProcThreadAttributeList) {
+    unsafe { DeleteProcThreadAttributeList(attribute_list) }
+}
+
+/// Retrieves the termination status of the specified thread, which is
+/// `STILL_ACTIVE` while the thread is running.
+///
+/// A thread that returned 259 itself can't be told apart from a running one by
+/// its exit code, wait on the thread with a time-out of 0 to be sure.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn get_exit_code_thread(thread: Handle) -> Result<DWORD, Error> {
+    let mut exit_code = 0;
+    let res = unsafe { GetExitCodeThread(thread, &raw mut exit_code) };
+
+    res.ok().map(|()| exit_code).or_code(Error::ProcessError)
+}
+
+/// Retrieves the information selected by `class` about the specified process
+/// from `ntdll` into the `length` bytes at `information`.
+///
+/// Providing none to `return_length` will let us default the value with a
+/// null pointer.
+///
+/// # Errors
+/// If the function fails, `Error::Windows` is returned with the `NTSTATUS`
+/// converted into an `HRESULT`.
+pub fn nt_query_information_process(
+    process_handle: Handle,
+    class: ProcessInfoClass,
+    information: LPVOID,
+    length: u32,
+    return_length: Option<*mut u32>,
+) -> Result<(), Error> {
+    unsafe {
+        NtQueryInformationProcess(
+            process_handle,
+            class,
+            information,
+            length,
+            return_length.unwrap_or(null_mut()),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Retrieves the termination status of the specified process, which is
+/// `STILL_ACTIVE` while the process is running.
+///
+/// The handle needs `PROCESS_QUERY_INFORMATION` or
+/// `PROCESS_QUERY_LIMITED_INFORMATION`.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn get_exit_code_process(process: Handle) -> Result<DWORD, Error> {
+    let mut exit_code = 0;
+    let res = unsafe { GetExitCodeProcess(process, &raw mut exit_code) };
+
+    res.ok().map(|()| exit_code).or_code(Error::ProcessError)
+}
+
 /// Creates a thread to execute within the virtual address space of the calling
 /// process.
 ///
@@ -276,11 +582,7 @@ pub fn create_thread(
         )
     };
 
-    if res.is_invalid() {
-        Err(Error::ProcessError(unsafe { GetLastError() }))
-    } else {
-        Ok(res)
-    }
+    res.ok().or_code(Error::ProcessError)
 }
 
 /// Closes an open object handle.
@@ -290,44 +592,89 @@ pub fn create_thread(
 pub fn close_handle(handle: Handle) -> Result<(), Error> {
     let res = unsafe { CloseHandle(handle) };
 
-    if res.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::Handle(unsafe { GetLastError() }))
-    }
+    res.ok().or_code(Error::Handle)
 }
 
 /// Retrieves a pseudo handle for the current process.
 #[must_use]
 pub fn get_current_process() -> Handle { unsafe { GetCurrentProcess() } }
 
-/// Allocates a console for the calling process. A process is only able to have
-/// one console, this function will fail if it already has a console. If you
-/// want to get rid of the existing console you should call our `free_console`
-/// function.
-/// # Errors
-pub fn alloc_console() -> Result<(), Error> {
-    let success = unsafe { AllocConsole() };
+/// Whether `handle` could refer to an object, which is the case for anything
+/// but a null handle.
+///
+/// `INVALID_HANDLE_VALUE` is also the pseudo handle `get_current_process`
+/// returns so it counts as valid here.
+#[must_use]
+pub const fn is_valid_handle(handle: Handle) -> bool { handle.0 != 0 }
 
-    if success.as_bool() {
+/// Stops a wrapper before it makes a syscall with a null handle, which would
+/// only fail with a less helpful error code.
+const fn check_handle(handle: Handle) -> Result<(), Error> {
+    if is_valid_handle(handle) {
         Ok(())
     } else {
-        Err(Error::ConsoleAllocation(unsafe { GetLastError() }))
+        Err(Error::InvalidHandle)
     }
 }
 
+/// Allocates a console for the calling process.
+///
+/// A process is only able to have one console, this function will fail if it
+/// already has a console. If you want to get rid of the existing console you
+/// should call our `free_console` function.
+/// # Errors
+#[cfg(feature = "console")]
+pub fn alloc_console() -> Result<(), Error> {
+    let success = unsafe { AllocConsole() };
+
+    success.ok().or_code(Error::ConsoleAllocation)
+}
+
 /// Frees a console from the calling process.
 /// # Errors
+#[cfg(feature = "console")]
 pub fn free_console() -> Result<(), Error> {
     let success = unsafe { FreeConsole() };
 
-    if success.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::ConsoleDeallocation(unsafe { GetLastError() }))
-    }
+    success.ok().or_code(Error::ConsoleDeallocation)
+}
+
+/// Opens `CONOUT$` or `CONIN$`, the output or input buffer of the console of
+/// the calling process, for reading and writing.
+///
+/// # Errors
+/// If the function fails, `Error::ConsoleAllocation` is returned.
+#[cfg(feature = "console")]
+pub fn open_console_buffer(name: &str) -> Result<Handle, Error> {
+    let handle = unsafe {
+        CreateFileW(
+            name,
+            GENERIC_READ | GENERIC_WRITE,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            std::ptr::null(),
+            OPEN_EXISTING,
+            FILE_ATTRIBUTE_NORMAL,
+            HANDLE(0),
+        )
+    };
+
+    handle.ok().or_code(Error::ConsoleAllocation)
+}
+
+/// Replaces the standard input, output or error handle of the calling process,
+/// `std_handle` is one of the `STD_*_HANDLE` constants.
+///
+/// # Errors
+/// If the function fails, `Error::ConsoleAllocation` is returned.
+#[cfg(feature = "console")]
+pub fn set_std_handle(std_handle: DWORD, handle: Handle) -> Result<(), Error> {
+    let success = unsafe { SetStdHandle(std_handle, handle) };
+
+    success.ok().or_code(Error::ConsoleAllocation)
 }
 
+/// Calls `FreeLibrary` on the module and then `ExitThread`.
+///
 /// Firstly `FreeLibrary` is called which frees the DLL and if needed decrements
 /// the reference count, when the reference count reaches zero the module will
 /// be unloaded from the address space and the handle will no longer be valid
@@ -338,12 +685,161 @@ pub fn free_library_and_exit_thread(module_handle: HandleInstance, exit_code: DW
     }
 }
 
+/// Ends the calling thread with `exit_code`.
+pub fn exit_thread(exit_code: DWORD) -> ! {
+    unsafe { ExitThread(exit_code) };
+
+    unreachable!("ExitThread returned")
+}
+
 /// Opens an existing local process object.
+///
+/// An invalid handle is returned on failure, use `try_open_process` instead.
 #[must_use]
+#[deprecated(note = "returns an invalid handle on failure, use `try_open_process` instead")]
 pub fn open_process(desired_access: ProcessAccessRights, inherit_handle: bool, process_id: DWORD) -> Handle {
     unsafe { OpenProcess(desired_access, inherit_handle, process_id) }
 }
 
+/// Opens an existing local process object.
+///
+/// # Errors
+/// `Error::AccessDenied` if the process is protected or we aren't allowed to
+/// open it with `desired_access`, otherwise `Error::ProcessError` if the
+/// function fails.
+pub fn try_open_process(
+    desired_access: ProcessAccessRights,
+    inherit_handle: bool,
+    process_id: DWORD,
+) -> Result<Handle, Error> {
+    let handle = unsafe { OpenProcess(desired_access, inherit_handle, process_id) };
+
+    handle.ok().map_err(|error| match win32_code(&error) {
+        ERROR_ACCESS_DENIED => Error::AccessDenied { pid: process_id },
+        code => Error::ProcessError(code),
+    })
+}
+
+/// Like `try_open_process` but the handle is closed once it is dropped.
+///
+/// # Errors
+/// See `try_open_process`.
+pub fn open_process_owned(
+    desired_access: ProcessAccessRights,
+    inherit_handle: bool,
+    process_id: DWORD,
+) -> Result<SafeHandle, Error> {
+    try_open_process(desired_access, inherit_handle, process_id).map(SafeHandle::owned)
+}
+
+/// Opens the access token of `process_handle`, close it with `close_handle`
+/// once you are done with it.
+///
+/// # Errors
+/// `Error::Win32` if the function fails.
+pub fn open_process_token(process_handle: Handle, desired_access: TokenAccessMask) -> Result<Handle, Error> {
+    let mut token = HANDLE::default();
+    unsafe { OpenProcessToken(process_handle, desired_access, &raw mut token) }
+        .ok()
+        .map(|()| token)
+        .or_context("OpenProcessToken")
+}
+
+/// Looks up the id the local system uses for the privilege `name`, for
+/// example `SeDebugPrivilege`.
+///
+/// # Errors
+/// `Error::Win32` if there is no privilege with that name.
+pub fn lookup_privilege_value(name: &str) -> Result<Luid, Error> {
+    let mut name = to_wide(name);
+    let mut luid = Luid::default();
+
+    unsafe { LookupPrivilegeValueW(PWSTR(null_mut()), PWSTR(name.as_mut_ptr()), &raw mut luid) }
+        .ok()
+        .map(|()| luid)
+        .or_context("LookupPrivilegeValueW")
+}
+
+/// Enables or disables the privileges in `new_state` for `token`, which has to
+/// be opened with `TOKEN_ADJUST_PRIVILEGES`.
+///
+/// # Errors
+/// `Error::Win32` if the function fails, or with `ERROR_NOT_ALL_ASSIGNED` if
+/// the token doesn't have one of the privileges, which is the case for most
+/// privileges unless we run as administrator.
+pub fn adjust_token_privileges(token: Handle, new_state: &TokenPrivileges) -> Result<(), Error> {
+    unsafe { AdjustTokenPrivileges(token, false, new_state, 0, null_mut(), null_mut()) }
+        .ok()
+        .or_context("AdjustTokenPrivileges")?;
+
+    // The call succeeds even if only some of the privileges could be changed
+    match unsafe { GetLastError() } {
+        ERROR_NOT_ALL_ASSIGNED => Err(Error::win32(ERROR_NOT_ALL_ASSIGNED, "AdjustTokenPrivileges")),
+        _ => Ok(()),
+    }
+}
+
+/// Opens an existing thread object, the handle is closed once it is dropped.
+///
+/// # Errors
+/// `Error::ProcessError` if the thread doesn't exist or can't be opened with
+/// `desired_access`.
+pub fn open_thread(desired_access: ThreadAccessRights, inherit_handle: bool, thread_id: DWORD) -> Result<SafeHandle, Error> {
+    let handle = unsafe { OpenThread(desired_access, inherit_handle, thread_id) };
+
+    handle
+        .ok()
+        .map(SafeHandle::owned)
+        .map_err(|error| Error::ProcessError(win32_code(&error)))
+}
+
+/// Suspends the specified thread, returning the previous suspend count.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn suspend_thread(thread: Handle) -> Result<DWORD, Error> {
+    let res = unsafe { SuspendThread(thread) };
+    if res == u32::MAX {
+        Err(Error::ProcessError(unsafe { GetLastError() }))
+    } else {
+        Ok(res)
+    }
+}
+
+/// Decrements the suspend count of a thread, when it reaches zero the thread
+/// resumes running. The previous suspend count is returned.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn resume_thread(thread: Handle) -> Result<DWORD, Error> {
+    let res = unsafe { ResumeThread(thread) };
+    if res == u32::MAX {
+        Err(Error::ProcessError(unsafe { GetLastError() }))
+    } else {
+        Ok(res)
+    }
+}
+
+/// Retrieves the context of the specified thread, `ContextFlags` of `context`
+/// has to be set to the parts of the context you want.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn get_thread_context(thread: Handle, context: &mut Context) -> Result<(), Error> {
+    let res = unsafe { GetThreadContext(thread, context) };
+    res.ok().or_code(Error::ProcessError)
+}
+
+/// Sets the context of the specified thread, only the parts selected by
+/// `ContextFlags` are applied.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn set_thread_context(thread: Handle, context: &Context) -> Result<(), Error> {
+    let res = unsafe { SetThreadContext(thread, context) };
+    res.ok().or_code(Error::ProcessError)
+}
+
 /// Takes a snapshot of the specified processes, as well as the heaps, modules,
 /// and threads used by these processes.
 ///
@@ -351,11 +847,7 @@ pub fn open_process(desired_access: ProcessAccessRights, inherit_handle: bool, p
 /// If the function fails, `Error::MemoryError` is returned.
 pub fn create_tool_help32_snapshot(flags: CreateToolhelpSnapshotFlags, process_id: DWORD) -> Result<Handle, Error> {
     let res = unsafe { CreateToolhelp32Snapshot(flags, process_id) };
-    if res.is_invalid() {
-        Err(Error::MemoryError(unsafe { GetLastError() }))
-    } else {
-        Ok(res)
-    }
+    res.ok().or_code(Error::MemoryError)
 }
 
 /// Retrieves information about the first module associated with a process.
@@ -364,11 +856,7 @@ pub fn create_tool_help32_snapshot(flags: CreateToolhelpSnapshotFlags, process_i
 /// If the function fails, `Error::MemoryError` is returned.
 pub fn module32_first(snapshot: Handle, module_entry: &mut ModuleEntry32) -> Result<(), Error> {
     let res = unsafe { Module32First(snapshot, module_entry) };
-    if res.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::MemoryError(unsafe { GetLastError() }))
-    }
+    res.ok().or_code(Error::MemoryError)
 }
 
 /// Retrieves information about the next module associated with a process or
@@ -378,11 +866,7 @@ pub fn module32_first(snapshot: Handle, module_entry: &mut ModuleEntry32) -> Res
 /// If the function fails, `Error::MemoryError` is returned.
 pub fn module32_next(snapshot: Handle, module_entry: &mut ModuleEntry32) -> Result<(), Error> {
     let res = unsafe { Module32Next(snapshot, module_entry) };
-    if res.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::MemoryError(unsafe { GetLastError() }))
-    }
+    res.ok().or_code(Error::MemoryError)
 }
 
 /// Retrieves information about the first process encountered in a system
@@ -392,11 +876,7 @@ pub fn module32_next(snapshot: Handle, module_entry: &mut ModuleEntry32) -> Resu
 /// If the function fails, `Error::MemoryError` is returned.
 pub fn process32_first(snapshot: Handle, process_entry: &mut ProcessEntry32) -> Result<(), Error> {
     let res = unsafe { Process32First(snapshot, process_entry) };
-    if res.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::MemoryError(unsafe { GetLastError() }))
-    }
+    res.ok().or_code(Error::MemoryError)
 }
 
 /// Retrieves information about the next process recorded in a system snapshot.
@@ -405,18 +885,35 @@ pub fn process32_first(snapshot: Handle, process_entry: &mut ProcessEntry32) ->
 /// If the function fails, `Error::MemoryError` is returned.
 pub fn process32_next(snapshot: Handle, process_entry: &mut ProcessEntry32) -> Result<(), Error> {
     let res = unsafe { Process32Next(snapshot, process_entry) };
-    if res.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::MemoryError(unsafe { GetLastError() }))
-    }
+    res.ok().or_code(Error::MemoryError)
+}
+
+/// Retrieves information about the first thread of any process encountered in
+/// a system snapshot.
+///
+/// # Errors
+/// If the function fails, `Error::MemoryError` is returned.
+pub fn thread32_first(snapshot: Handle, thread_entry: &mut ThreadEntry32) -> Result<(), Error> {
+    let res = unsafe { Thread32First(snapshot, thread_entry) };
+    res.ok().or_code(Error::MemoryError)
+}
+
+/// Retrieves information about the next thread of any process encountered in
+/// the system snapshot.
+///
+/// # Errors
+/// If the function fails, `Error::MemoryError` is returned.
+pub fn thread32_next(snapshot: Handle, thread_entry: &mut ThreadEntry32) -> Result<(), Error> {
+    let res = unsafe { Thread32Next(snapshot, thread_entry) };
+    res.ok().or_code(Error::MemoryError)
 }
 
 /// Writes data to an area of memory in a specified process. The entire area to
 /// be written to must be accessible or the operation fails.
 ///
 /// # Errors
-/// If the function fails, `Error::MemoryError` is returned.
+/// If the function fails, `Error::MemoryError` is returned, or
+/// `Error::InvalidHandle` if the handle is null.
 pub fn write_process_memory(
     process_handle: Handle,
     base_address: LPVOID,
@@ -424,6 +921,8 @@ pub fn write_process_memory(
     size: size_t,
     number_of_bytes_written: Option<*mut size_t>,
 ) -> Result<(), Error> {
+    check_handle(process_handle)?;
+
     let result = unsafe {
         WriteProcessMemory(
             process_handle,
@@ -433,17 +932,14 @@ pub fn write_process_memory(
             number_of_bytes_written.unwrap_or(null_mut()),
         )
     };
-    if result.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::MemoryError(unsafe { GetLastError() }))
-    }
+    result.ok().or_code(Error::MemoryError)
 }
 
 /// Reads data from an area of memory in a specified process.
 ///
 /// # Errors
-/// If the function fails, `Error::MemoryError` is returned.
+/// If the function fails, `Error::MemoryError` is returned, or
+/// `Error::InvalidHandle` if the handle is null.
 pub fn read_process_memory(
     process_handle: Handle,
     base_address: LPCVOID,
@@ -451,12 +947,10 @@ pub fn read_process_memory(
     size: size_t,
     number_of_bytes_written: *mut size_t,
 ) -> Result<(), Error> {
+    check_handle(process_handle)?;
+
     let res = unsafe { ReadProcessMemory(process_handle, base_address, buffer, size, number_of_bytes_written) };
-    if res.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::MemoryError(unsafe { GetLastError() }))
-    }
+    res.ok().or_code(Error::MemoryError)
 }
 
 /// Retrieves the address of an exported function or variable from the specified
@@ -471,6 +965,21 @@ pub fn get_proc_address(hmodule: HINSTANCE, lpprocname: &str) -> Result<usize, E
     Ok(function_address as usize)
 }
 
+/// Retrieves the address of a function or variable the specified dynamic-link
+/// library (DLL) exports by `ordinal`, which is how some DLLs like `ws2_32.dll`
+/// export functions that have no name.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessAddress` is returned.
+pub fn get_proc_address_ordinal(hmodule: HINSTANCE, ordinal: u16) -> Result<usize, Error> {
+    // Like MAKEINTRESOURCE the ordinal goes in the low word of the name pointer
+    let name = PSTR(usize::from(ordinal) as *mut u8);
+    let function_address =
+        unsafe { GetProcAddress(hmodule, name) }.ok_or_else(|| Error::ProcessAddress(unsafe { GetLastError() }))?;
+
+    Ok(function_address as usize)
+}
+
 /// Reserves, commits, or changes the state of a region of memory within the
 /// virtual address space of a specified process. The function initializes the
 /// memory it allocates to zero.
@@ -478,7 +987,8 @@ pub fn get_proc_address(hmodule: HINSTANCE, lpprocname: &str) -> Result<usize, E
 /// If you provide none to `address` we will default it to a null pointer.
 ///
 /// # Errors
-/// If the function fails, `Error::MemoryError` is returned.
+/// If the function fails, `Error::MemoryError` is returned, or
+/// `Error::InvalidHandle` if the handle is null.
 pub fn virtual_alloc_ex(
     handle: Handle,
     address: Option<*mut c_void>,
@@ -486,6 +996,8 @@ pub fn virtual_alloc_ex(
     allocation_type: VirtualAllocationType,
     protection_flags: PageProtectionFlags,
 ) -> Result<*mut c_void, Error> {
+    check_handle(handle)?;
+
     let res = unsafe { VirtualAllocEx(handle, address.unwrap_or(null_mut()), size, allocation_type, protection_flags) };
 
     if res.is_null() {
@@ -507,11 +1019,7 @@ pub fn virtual_free_ex(
     virtual_free_type: VirtualFreeType,
 ) -> Result<(), Error> {
     let result = unsafe { VirtualFreeEx(process_handle, address, size, virtual_free_type) };
-    if result.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::MemoryError(unsafe { GetLastError() }))
-    }
+    result.ok().or_code(Error::MemoryError)
 }
 
 /// Disables the `DLL_THREAD_ATTACH` and `DLL_THREAD_DETACH` notifications for
@@ -522,11 +1030,7 @@ pub fn virtual_free_ex(
 /// If the function fails, `Error::Handle` is returned.
 pub fn disable_thread_library_calls(module_handle: HandleInstance) -> Result<(), Error> {
     let result = unsafe { DisableThreadLibraryCalls(module_handle) };
-    if result.as_bool() {
-        Ok(())
-    } else {
-        Err(Error::Handle(unsafe { GetLastError() }))
-    }
+    result.ok().or_code(Error::Handle)
 }
 
 /// Will return a PID for the Handle it is given.
@@ -542,3 +1046,193 @@ pub fn get_process_id(handle: Handle) -> Result<u32, Error> {
         Ok(result)
     }
 }
+
+/// Determines whether the process is running under WOW64, which is the case
+/// for 32 bit processes on 64 bit windows.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn is_wow64_process(process: Handle) -> Result<bool, Error> {
+    let mut wow64 = BOOL::default();
+    let res = unsafe { IsWow64Process(process, &raw mut wow64) };
+
+    res.ok().map(|()| wow64.as_bool()).or_code(Error::ProcessError)
+}
+
+/// Determines whether `process` is being debugged.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn check_remote_debugger_present(process: Handle) -> Result<bool, Error> {
+    let mut present = BOOL::default();
+    let res = unsafe { CheckRemoteDebuggerPresent(process, &raw mut present) };
+
+    res.ok().map(|()| present.as_bool()).or_code(Error::ProcessError)
+}
+
+/// Finds the top level window with the window class `class_name` and the
+/// title `window_name`, `None` matches any class or title.
+///
+/// `None` is returned if there is no such window.
+#[must_use]
+pub fn find_window(class_name: Option<&str>, window_name: Option<&str>) -> Option<Hwnd> {
+    let mut class_name = class_name.map(to_wide);
+    let mut window_name = window_name.map(to_wide);
+    let as_pwstr = |name: &mut Option<Vec<WCHAR>>| PWSTR(name.as_mut().map_or(null_mut(), Vec::as_mut_ptr));
+
+    let window = unsafe { FindWindowW(as_pwstr(&mut class_name), as_pwstr(&mut window_name)) };
+    (window != 0).then_some(window)
+}
+
+/// Returns the ids of the thread that created `window` and of the process it
+/// belongs to, in that order.
+///
+/// # Errors
+/// If `window` isn't a valid window, `Error::Handle` is returned.
+pub fn get_window_thread_process_id(window: Hwnd) -> Result<(DWORD, DWORD), Error> {
+    let mut process_id = 0;
+    let thread_id = unsafe { GetWindowThreadProcessId(window, &raw mut process_id) };
+
+    if thread_id == 0 {
+        Err(Error::Handle(unsafe { GetLastError() }))
+    } else {
+        Ok((thread_id, process_id))
+    }
+}
+
+/// Looks up the system's description of the windows error `code`, for example
+/// "Access is denied." for 5.
+///
+/// An empty string is returned if windows doesn't know the code.
+#[must_use]
+pub fn format_message(code: DWORD) -> String {
+    let mut buffer = [0 as WCHAR; 512];
+    let len = unsafe {
+        FormatMessageW(
+            FORMAT_MESSAGE_FROM_SYSTEM | FORMAT_MESSAGE_IGNORE_INSERTS,
+            std::ptr::null(),
+            code,
+            0,
+            PWSTR(buffer.as_mut_ptr()),
+            buffer.len() as u32,
+            std::ptr::null(),
+        )
+    };
+
+    // Messages end with a line break we don't want
+    String::from_utf16_lossy(&buffer[..len as usize]).trim_end().to_owned()
+}
+
+/// Retrieves the full path of `module` inside of `process_handle`, pass a
+/// module of 0 to get the path of the executable.
+///
+/// The handle needs `PROCESS_QUERY_INFORMATION` and `PROCESS_VM_READ`.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn get_module_file_name_ex(process_handle: Handle, module: HINSTANCE) -> Result<OsString, Error> {
+    // Paths can be longer than MAX_PATH, the buffer is grown until it fits
+    // or hits the longest path windows supports
+    let mut buffer = vec![0 as WCHAR; 260];
+    loop {
+        let len =
+            unsafe { K32GetModuleFileNameExW(process_handle, module, PWSTR(buffer.as_mut_ptr()), buffer.len() as u32) };
+        if len == 0 {
+            return Err(Error::ProcessError(unsafe { GetLastError() }));
+        }
+
+        let len = len as usize;
+        if len < buffer.len() || buffer.len() >= 0x8000 {
+            return Ok(OsString::from_wide(&buffer[..len]));
+        }
+
+        buffer.resize(buffer.len() * 2, 0);
+    }
+}
+
+/// Retrieves the path of the file that is mapped at `address` inside of
+/// `process_handle`, in the native form like
+/// `\Device\HarddiskVolume3\Windows\System32\ntdll.dll`.
+///
+/// The handle needs `PROCESS_QUERY_INFORMATION`.
+///
+/// # Errors
+/// If `address` isn't in a mapped file or the function fails,
+/// `Error::ProcessError` is returned.
+pub fn get_mapped_file_name(process_handle: Handle, address: LPCVOID) -> Result<OsString, Error> {
+    let mut buffer = vec![0 as WCHAR; 260];
+    loop {
+        let len = unsafe { K32GetMappedFileNameW(process_handle, address, PWSTR(buffer.as_mut_ptr()), buffer.len() as u32) };
+        if len == 0 {
+            return Err(Error::ProcessError(unsafe { GetLastError() }));
+        }
+
+        // The name is cut off without an error if it doesn't fit
+        let len = len as usize;
+        if len < buffer.len() - 1 || buffer.len() >= 0x8000 {
+            return Ok(OsString::from_wide(&buffer[..len]));
+        }
+
+        buffer.resize(buffer.len() * 2, 0);
+    }
+}
+
+/// Retrieves the native device path the drive `device_name` like `C:` refers
+/// to, for example `\Device\HarddiskVolume3`.
+///
+/// # Errors
+/// If there is no such drive, `Error::Handle` is returned.
+pub fn query_dos_device(device_name: &str) -> Result<OsString, Error> {
+    let mut name = to_wide(device_name);
+    let mut buffer = [0 as WCHAR; 260];
+    let len = unsafe { QueryDosDeviceW(PWSTR(name.as_mut_ptr()), PWSTR(buffer.as_mut_ptr()), buffer.len() as u32) };
+    if len == 0 {
+        return Err(Error::Handle(unsafe { GetLastError() }));
+    }
+
+    // The buffer holds a list of null terminated paths, the first one is the
+    // one in use
+    let first = buffer.iter().position(|&c| c == 0).unwrap_or(len as usize);
+    Ok(OsString::from_wide(&buffer[..first]))
+}
+
+/// Retrieves the base address, image size and entry point of `module` inside
+/// of `process_handle` as the windows loader sees them.
+///
+/// The handle needs `PROCESS_QUERY_INFORMATION` and `PROCESS_VM_READ`.
+///
+/// # Errors
+/// If the function fails, `Error::ProcessError` is returned.
+pub fn get_module_information(process_handle: Handle, module: HModule) -> Result<ModuleInfo, Error> {
+    let mut info = MODULEINFO::default();
+    let result = unsafe {
+        K32GetModuleInformation(
+            process_handle,
+            module,
+            &raw mut info,
+            std::mem::size_of::<MODULEINFO>() as u32,
+        )
+    };
+    result.ok().or_code(Error::ProcessError)?;
+
+    Ok(ModuleInfo {
+        base: info.lpBaseOfDll as usize,
+        size: info.SizeOfImage as usize,
+        entry_point: info.EntryPoint as usize,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_module_handle_finds_kernel32() {
+        assert!(get_module_handle("kernel32.dll").is_ok_and(|module| module != 0));
+    }
+
+    #[test]
+    fn get_module_handle_fails_for_missing_modules() {
+        assert!(matches!(get_module_handle("not a loaded module.dll"), Err(Error::Handle(_))));
+    }
+}
@@ -0,0 +1,39 @@
+//! Shares a single `Process` between several threads that all read from it at
+//! the same time, every thread has to get back exactly the values it reads.
+use mem::process::{Access, Process};
+
+const THREADS: usize = 8;
+const READS: usize = 10_000;
+
+#[test]
+fn threads_read_through_the_same_process() {
+    let values: Vec<u64> = (0..1024).map(|value| value * 3).collect();
+    let process = Process::open_by_pid(std::process::id(), Access::read_only()).unwrap();
+
+    let reads: Vec<usize> = std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..THREADS)
+            .map(|thread| {
+                let process = &process;
+                let values = &values;
+
+                scope.spawn(move || {
+                    let mut reads = 0;
+                    for read in 0..READS {
+                        let index = (thread * READS + read) % values.len();
+                        let address = std::ptr::from_ref(&values[index]) as usize;
+
+                        assert_eq!(process.read::<u64>(address).unwrap(), values[index]);
+                        assert_eq!(process.read_checked::<u64>(address).unwrap(), values[index]);
+                        reads += 1;
+                    }
+
+                    reads
+                })
+            })
+            .collect();
+
+        threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+    });
+
+    assert_eq!(reads, vec![READS; THREADS]);
+}
@@ -0,0 +1,72 @@
+//! Hooks a function whose first instructions include a short conditional jump,
+//! which only keeps working through the trampoline if the jump is rewritten
+//! when it is moved there.
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use mem::{
+    hooks::InlineHook,
+    windows::wrappers::{get_current_process, virtual_alloc_ex},
+};
+use windows::Win32::System::Memory::{MEM_COMMIT, MEM_RESERVE, PAGE_EXECUTE_READWRITE};
+
+/// Returns 7 if its argument is 0 and 42 otherwise:
+///
+/// ```text
+/// xor eax, eax
+/// test ecx, ecx
+/// je zero
+/// mov eax, 42
+/// ret
+/// zero:
+/// mov eax, 7
+/// ret
+/// ```
+const FUNCTION: [u8; 18] = [
+    0x31, 0xC0, 0x85, 0xC9, 0x74, 0x06, 0xB8, 0x2A, 0x00, 0x00, 0x00, 0xC3, 0xB8, 0x07, 0x00, 0x00, 0x00, 0xC3,
+];
+
+// The function takes its argument in ecx, which is the first argument of the
+// x64 calling convention and of fastcall on x86
+#[cfg(target_arch = "x86_64")]
+type Function = unsafe extern "system" fn(u32) -> u32;
+#[cfg(target_arch = "x86")]
+type Function = unsafe extern "fastcall" fn(u32) -> u32;
+
+static TRAMPOLINE: AtomicUsize = AtomicUsize::new(0);
+
+fn original(value: u32) -> u32 {
+    let trampoline: Function = unsafe { std::mem::transmute(TRAMPOLINE.load(Ordering::SeqCst)) };
+    unsafe { trampoline(value) }
+}
+
+#[cfg(target_arch = "x86_64")]
+unsafe extern "system" fn detour(value: u32) -> u32 { original(value) + 100 }
+#[cfg(target_arch = "x86")]
+unsafe extern "fastcall" fn detour(value: u32) -> u32 { original(value) + 100 }
+
+#[test]
+fn trampoline_calls_through_a_stolen_short_branch() {
+    let target = virtual_alloc_ex(
+        get_current_process(),
+        None,
+        0x1000,
+        MEM_COMMIT | MEM_RESERVE,
+        PAGE_EXECUTE_READWRITE,
+    )
+    .unwrap() as usize;
+    unsafe { std::ptr::copy_nonoverlapping(FUNCTION.as_ptr(), target as *mut u8, FUNCTION.len()) };
+    let function: Function = unsafe { std::mem::transmute(target) };
+
+    assert_eq!(unsafe { function(0) }, 7);
+    assert_eq!(unsafe { function(1) }, 42);
+
+    let hook = unsafe { InlineHook::new(target, detour as Function as usize) }.unwrap();
+    TRAMPOLINE.store(hook.trampoline(), Ordering::SeqCst);
+
+    // Both sides of the stolen branch still work through the trampoline
+    assert_eq!(unsafe { function(0) }, 107);
+    assert_eq!(unsafe { function(1) }, 142);
+
+    drop(hook);
+    assert_eq!(unsafe { function(0) }, 7);
+}